@@ -5,9 +5,15 @@ use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 
+use crate::html::raw_html::HtmlPolicy;
+use crate::html::wikilink::BrokenLinkPolicy;
+
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 pub struct Cli {
+    /// site configuration file [default: biotite.toml]
+    #[arg(short, long, global = true)]
+    pub config: Option<PathBuf>,
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
@@ -19,11 +25,23 @@ pub enum Commands {
         /// local dev server
         #[arg(long)]
         serve: bool,
-        /// input directory
-        #[arg(short, long, default_value = "contents")]
-        directory: PathBuf,
-        /// output directory
-        #[arg(short, long, default_value = "public")]
-        output: PathBuf,
+        /// input directory [default: contents]
+        #[arg(short, long)]
+        directory: Option<PathBuf>,
+        /// output directory [default: public]
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// render every newline inside a paragraph as <br>
+        #[arg(long)]
+        breaks: bool,
+        /// how to output raw HTML written in notes [default: escape]
+        #[arg(long, value_enum)]
+        html: Option<HtmlPolicy>,
+        /// how to output wikilinks to missing or unpublished notes [default: span]
+        #[arg(long, value_enum)]
+        broken_links: Option<BrokenLinkPolicy>,
+        /// maximum nesting depth of embedded notes [default: 5]
+        #[arg(long)]
+        embed_depth: Option<usize>,
     },
 }
@@ -1,50 +1,164 @@
+use crate::config::Config;
 use crate::file::{self, image, scan, write};
+use crate::html::backlink::LinkGraph;
+use crate::html::embed::EmbedExpander;
+use crate::html::feed::feed_files;
+use crate::html::highlight::{HIGHLIGHT_CSS_PATH, HighlightMode, theme_css};
+use crate::html::index::index_pages;
+use crate::html::sitemap::{SITEMAP_PATH, robots_txt, sitemap};
+use crate::html::tags::tag_pages;
+use crate::html::template::load_templates;
 use crate::html::to_html::md_to_html;
+use crate::html::wikilink::{BrokenLinkPolicy, LinkIndex, resolve_wikilinks};
 use crate::parser::document::ast::MdDocument;
+use crate::parser::document::front_matter::Date;
 use crate::parser::parse;
-use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-pub fn build(dir: &PathBuf, output_dir: &PathBuf) -> Result<(), std::io::Error> {
-    let images_dir = output_dir.clone().join("images");
-    file::clear(output_dir, &images_dir)?;
+pub fn build(config: &Config) -> Result<(), std::io::Error> {
+    // テンプレートの誤りは、何も消さないうちに知らせる
+    let templates = load_templates(&config.templates_dir)?;
+    let images_dir = config.image_dir();
+    file::clear(&config.output_dir, &images_dir)?;
+    file::copy_static(&config.static_dir, &config.output_dir)?;
 
-    let files = scan::scan_dir(dir);
+    let files = scan::scan_dir(&config.content_dir);
     println!("Starting analysis of {} files...", files.len());
 
-    let document: Vec<MdDocument> = files
+    let mut document: Vec<MdDocument> = files
         .iter()
-        .filter_map(|path| parse::parse(path).ok())
-        .filter(|doc| {
-            doc.front_matter
-                .as_ref()
-                .and_then(|fm| fm.get("publish"))
-                .map(|v| v == "true")
-                .unwrap_or(false)
-                && doc.body.is_some()
+        .filter_map(|path| match parse::parse(path) {
+            Ok(doc) => Some(doc),
+            Err(e) => {
+                println!("Warning: {} in {:?}", e, path);
+                None
+            }
         })
+        .filter(|doc| config.is_published(doc.front_matter.as_ref()) && doc.body.is_some())
         .filter_map(|mut doc| {
-            image::copy_document_images(&mut doc, &images_dir)
+            image::copy_document_images(&mut doc, &images_dir, &config.image_prefix)
                 .ok()
                 .map(|_| doc)
         })
+        .map(|mut doc| {
+            if config.breaks
+                && let Some(body) = doc.body.as_mut()
+            {
+                body.convert_soft_breaks();
+            }
+            if config.permalink_needs_date()
+                && doc.front_matter.as_ref().and_then(|fm| fm.date()).is_none()
+            {
+                println!(
+                    "Warning: no date for permalink {} in {:?}",
+                    config.permalink, doc.path
+                );
+            }
+            doc
+        })
         .collect();
 
+    // 全ドキュメントを読み込んでから埋め込みを展開し、Wikiリンクを出力先のURLに解決する
+    let index = LinkIndex::new(&document, config);
+    let graph = LinkGraph::new(&document, &index);
+    let expander = EmbedExpander::new(&document, &index, config.embed_depth);
+    for (i, doc) in document.iter_mut().enumerate() {
+        if let Some(body) = doc.body.as_mut() {
+            for warning in expander.expand(body, i) {
+                println!("Warning: {} in {:?}", warning, doc.path);
+            }
+        }
+    }
+
+    let mut broken_count = 0;
+    for doc in &mut document {
+        if let Some(body) = doc.body.as_mut() {
+            for message in resolve_wikilinks(body, &index, config.broken_links) {
+                println!("Warning: {} in {:?}", message, doc.path);
+                broken_count += 1;
+            }
+        }
+    }
+    if config.broken_links == BrokenLinkPolicy::Fail && broken_count > 0 {
+        return Err(std::io::Error::other(format!(
+            "{} broken wikilinks found",
+            broken_count
+        )));
+    }
+
     println!(
         "{} HTML files are being written to the {:#?} directory.",
         document.len(),
-        output_dir
+        config.output_dir
     );
 
-    document.iter().map(|doc| md_to_html(doc)).try_for_each(
-        |html_doc| -> Result<(), std::io::Error> {
-            println!("path: {}", html_doc.path);
-            println!("title: {}", html_doc.title);
-            println!("tags: {:?}", html_doc.tags);
-            write::write(output_dir, html_doc.path.to_string(), html_doc.content)?;
+    let html_docs = document
+        .iter()
+        .enumerate()
+        .map(|(i, doc)| md_to_html(doc, &templates, config, index.url(i), graph.backlinks(i)))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(std::io::Error::other)?;
+
+    // トップページを持つサイトでは一覧を作らない
+    let index_pages = match html_docs
+        .iter()
+        .find(|doc| matches!(doc.path.as_str(), "/" | "/index" | "/index.html"))
+    {
+        Some(home) => {
+            println!(
+                "Warning: the index page is not generated because {} is a page",
+                home.path
+            );
+            Vec::new()
+        }
+        None => index_pages(&html_docs, &templates, config).map_err(std::io::Error::other)?,
+    };
+
+    let tag_pages = tag_pages(&html_docs, &templates, config).map_err(std::io::Error::other)?;
+
+    let mut site_files = Vec::new();
+    if config.highlight == HighlightMode::Class {
+        site_files.push((HIGHLIGHT_CSS_PATH.to_string(), theme_css()));
+    }
+
+    // フィードとサイトマップは絶対URLが必要
+    if config.base_url.is_empty() {
+        println!("Warning: feeds and sitemap.xml are not generated because base_url is not set");
+    } else {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+        site_files.extend(feed_files(&html_docs, config, Date::from_unix_time(now)));
+
+        let pages: Vec<(String, Option<Date>)> = html_docs
+            .iter()
+            .filter(|doc| !doc.noindex)
+            .map(|doc| (doc.path.clone(), doc.updated))
+            .chain(index_pages.iter().map(|(url, _)| (url.clone(), None)))
+            .chain(tag_pages.iter().map(|(path, _)| (path.clone(), None)))
+            .collect();
+        site_files.push((SITEMAP_PATH.to_string(), sitemap(&pages, config)));
+        // static_dirにrobots.txtがあればそちらを使う
+        if !config.output_dir.join("robots.txt").exists() {
+            site_files.push(("/robots.txt".to_string(), robots_txt(config)));
+        }
+    }
 
-            Ok(())
-        },
-    )?;
+    for html_doc in html_docs {
+        println!("path: {}", html_doc.path);
+        println!("title: {}", html_doc.title);
+        println!("tags: {:?}", html_doc.tags);
+        write::write(&config.output_dir, &html_doc.path, html_doc.content)?;
+    }
+    for (url, html) in index_pages {
+        write::write(&config.output_dir, &url, html)?;
+    }
+    for (path, html) in tag_pages {
+        write::write(&config.output_dir, &path, html)?;
+    }
+    for (path, content) in site_files {
+        write::write_file(&config.output_dir, &path, content)?;
+    }
 
     Ok(())
 }
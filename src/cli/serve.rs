@@ -26,8 +26,8 @@ async fn html_path_resolver(
 
     let has_extension = decoded_path
         .split('/')
-        .last()
-        .map_or(false, |p| p.contains('.'));
+        .next_back()
+        .is_some_and(|p| p.contains('.'));
     let is_directory = decoded_path.ends_with('/');
 
     if !has_extension && !is_directory {
@@ -47,7 +47,7 @@ async fn html_path_resolver(
     next.run(req).await
 }
 
-pub async fn start_server(output_dir: &PathBuf) -> Result<(), std::io::Error> {
+pub async fn start_server(output_dir: &PathBuf, addr: &str) -> Result<(), std::io::Error> {
     let state = ServerState {
         output_dir: Arc::new(output_dir.to_path_buf()),
     };
@@ -56,10 +56,10 @@ pub async fn start_server(output_dir: &PathBuf) -> Result<(), std::io::Error> {
         .fallback_service(ServeDir::new(output_dir))
         .layer(middleware::from_fn_with_state(state, html_path_resolver));
 
-    let addr = "0.0.0.0:8080";
     let listener = TcpListener::bind(addr).await?;
+    let port = listener.local_addr()?.port();
 
-    println!("🚀 Server running at http://localhost:8080");
+    println!("🚀 Server running at http://localhost:{}", port);
 
     axum::serve(listener, app).await
 }
@@ -0,0 +1,712 @@
+use crate::html::feed::{FeedContent, FeedFormat};
+use crate::html::highlight::HighlightMode;
+use crate::html::raw_html::HtmlPolicy;
+use crate::html::to_html::output_path;
+use crate::html::wikilink::BrokenLinkPolicy;
+use crate::parser::document::ast::MdDocument;
+use crate::parser::document::front_matter::toml::parse_toml;
+use crate::parser::document::front_matter::{FrontMatter, FrontMatterError, Value};
+use clap::ValueEnum;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE: &str = "biotite.toml";
+
+// permalinkに書けるプレースホルダー
+const PERMALINK_PLACEHOLDERS: &[&str] = &[":slug", ":path", ":year", ":month", ":day"];
+
+// biotite.tomlの設定。CLIの引数で上書きできる
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub title: String,
+    // 末尾の `/` は取り除いておく
+    pub base_url: String,
+    pub language: String,
+    // フィードの著者。空ならtitleを使う
+    pub author: String,
+    pub content_dir: PathBuf,
+    pub output_dir: PathBuf,
+    // 中身をそのまま出力先にコピーする
+    pub static_dir: PathBuf,
+    // 組み込みのテンプレートを上書きするテンプレート
+    pub templates_dir: PathBuf,
+    // front matterの `layout` がないページのテンプレート
+    pub layout: String,
+    // content_dirからのフォルダごとのlayout。深いフォルダの指定を優先する
+    pub folder_layouts: Vec<(PathBuf, String)>,
+    // `!draft` のように `!` を付けると、そのキーがtrueのもの以外を公開する
+    pub publish_key: String,
+    // `/:slug.html` `/posts/:year/:slug/` のようなページのURL
+    pub permalink: String,
+    // 画像の出力先とURL。`/` で始まり `/` で終わる
+    pub image_prefix: String,
+    // 一覧の1ページあたりの件数。0なら1ページにまとめる
+    pub paginate: usize,
+    // 一覧の2ページ目以降のURL。`:num` がページ番号になる
+    pub paginate_path: String,
+    pub server_address: String,
+    // base_urlがなければフィードは作らない
+    pub feed_formats: Vec<FeedFormat>,
+    // フィードの1つあたりの件数。0なら全件
+    pub feed_limit: usize,
+    pub feed_content: FeedContent,
+    pub breaks: bool,
+    pub html: HtmlPolicy,
+    pub broken_links: BrokenLinkPolicy,
+    pub embed_depth: usize,
+    // 見出しにカーソルを合わせたときに出るリンク
+    pub heading_anchors: bool,
+    // 目次に入れる見出しのレベル
+    pub toc_min_level: u8,
+    pub toc_max_level: u8,
+    pub highlight: HighlightMode,
+    // 情報文字列の `linenos` がないコードブロックに行番号を付けるか
+    pub line_numbers: bool,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Syntax(FrontMatterError),
+    Invalid { key: String, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "failed to read {}: {}", CONFIG_FILE, e),
+            ConfigError::Syntax(e) => write!(
+                f,
+                "invalid {} at line {}: {}",
+                CONFIG_FILE, e.line, e.message
+            ),
+            ConfigError::Invalid { key, message } => {
+                write!(f, "invalid {}: `{}` {}", CONFIG_FILE, key, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<ConfigError> for std::io::Error {
+    fn from(e: ConfigError) -> Self {
+        match e {
+            ConfigError::Io(e) => e,
+            e => std::io::Error::other(e.to_string()),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            title: String::new(),
+            base_url: String::new(),
+            language: "ja".to_string(),
+            author: String::new(),
+            content_dir: PathBuf::from("contents"),
+            output_dir: PathBuf::from("public"),
+            static_dir: PathBuf::from("static"),
+            templates_dir: PathBuf::from("templates"),
+            layout: "page".to_string(),
+            folder_layouts: Vec::new(),
+            publish_key: "publish".to_string(),
+            permalink: "/:slug.html".to_string(),
+            image_prefix: "/images/".to_string(),
+            paginate: 10,
+            paginate_path: "/page/:num/".to_string(),
+            server_address: "0.0.0.0:8080".to_string(),
+            feed_formats: vec![FeedFormat::Atom, FeedFormat::Rss],
+            feed_limit: 20,
+            feed_content: FeedContent::Full,
+            breaks: false,
+            html: HtmlPolicy::Escape,
+            broken_links: BrokenLinkPolicy::Span,
+            embed_depth: 5,
+            heading_anchors: true,
+            toc_min_level: 1,
+            toc_max_level: 6,
+            highlight: HighlightMode::Class,
+            line_numbers: false,
+        }
+    }
+}
+
+fn invalid<T>(key: &str, message: impl Into<String>) -> Result<T, ConfigError> {
+    Err(ConfigError::Invalid {
+        key: key.to_string(),
+        message: message.into(),
+    })
+}
+
+fn expect_string(key: &str, value: &Value) -> Result<String, ConfigError> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        _ => invalid(key, "must be a string"),
+    }
+}
+
+fn expect_bool(key: &str, value: &Value) -> Result<bool, ConfigError> {
+    match value {
+        Value::Bool(b) => Ok(*b),
+        _ => invalid(key, "must be true or false"),
+    }
+}
+
+fn expect_count(key: &str, value: &Value) -> Result<usize, ConfigError> {
+    match value {
+        Value::Integer(n) if *n >= 0 => Ok(*n as usize),
+        _ => invalid(key, "must be a non-negative integer"),
+    }
+}
+
+fn expect_heading_level(key: &str, value: &Value) -> Result<u8, ConfigError> {
+    match value {
+        Value::Integer(n) if (1..=6).contains(n) => Ok(*n as u8),
+        _ => invalid(key, "must be a heading level from 1 to 6"),
+    }
+}
+
+fn expect_enum<T: ValueEnum>(key: &str, value: &Value) -> Result<T, ConfigError> {
+    let s = expect_string(key, value)?;
+    T::from_str(&s, true).or_else(|_| {
+        let names: Vec<String> = T::value_variants()
+            .iter()
+            .filter_map(|v| v.to_possible_value())
+            .map(|v| format!("`{}`", v.get_name()))
+            .collect();
+        invalid(key, format!("must be one of {}", names.join(", ")))
+    })
+}
+
+// `wide` と `wide.html` のどちらでもよい
+fn expect_layout(key: &str, value: &Value) -> Result<String, ConfigError> {
+    let layout = expect_string(key, value)?;
+    if layout.is_empty() || layout.split('/').any(|segment| segment == "..") {
+        return invalid(key, "must be a template name such as `page`");
+    }
+
+    Ok(layout)
+}
+
+fn expect_table<'v>(key: &str, value: &'v Value) -> Result<&'v [(String, Value)], ConfigError> {
+    match value {
+        Value::Map(entries) => Ok(entries),
+        _ => invalid(key, "must be a table"),
+    }
+}
+
+impl Config {
+    // 設定ファイルがなければ既定の設定を使う。pathを明示したときはファイルが必要
+    pub fn load(path: Option<&Path>) -> Result<Config, ConfigError> {
+        let source = match path {
+            Some(path) => std::fs::read_to_string(path).map_err(ConfigError::Io)?,
+            None => match std::fs::read_to_string(CONFIG_FILE) {
+                Ok(source) => source,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    return Ok(Config::default());
+                }
+                Err(e) => return Err(ConfigError::Io(e)),
+            },
+        };
+
+        Config::from_toml(&source)
+    }
+
+    pub fn from_toml(source: &str) -> Result<Config, ConfigError> {
+        let values = parse_toml(&source.replace("\r\n", "\n"), 1).map_err(ConfigError::Syntax)?;
+        let mut config = Config::default();
+
+        for (key, value) in &values {
+            match key.as_str() {
+                "title" => config.title = expect_string(key, value)?,
+                "base_url" => config.base_url = expect_string(key, value)?,
+                "language" => config.language = expect_string(key, value)?,
+                "author" => config.author = expect_string(key, value)?,
+                "build" => config.read_build(expect_table(key, value)?)?,
+                "server" => config.read_server(expect_table(key, value)?)?,
+                "feed" => config.read_feed(expect_table(key, value)?)?,
+                "layouts" => {
+                    for (folder, layout) in expect_table(key, value)? {
+                        let key = &format!("layouts.{}", folder);
+                        let layout = expect_layout(key, layout)?;
+                        config
+                            .folder_layouts
+                            .push((PathBuf::from(folder.trim_matches('/')), layout));
+                    }
+                }
+                _ => return invalid(key, "is not a known setting"),
+            }
+        }
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn read_build(&mut self, entries: &[(String, Value)]) -> Result<(), ConfigError> {
+        for (name, value) in entries {
+            let key = &format!("build.{}", name);
+            match name.as_str() {
+                "content_dir" => self.content_dir = expect_string(key, value)?.into(),
+                "output_dir" => self.output_dir = expect_string(key, value)?.into(),
+                "static_dir" => self.static_dir = expect_string(key, value)?.into(),
+                "templates_dir" => self.templates_dir = expect_string(key, value)?.into(),
+                "layout" => self.layout = expect_layout(key, value)?,
+                "publish_key" => self.publish_key = expect_string(key, value)?,
+                "permalink" => self.permalink = expect_string(key, value)?,
+                "image_prefix" => self.image_prefix = expect_string(key, value)?,
+                "breaks" => self.breaks = expect_bool(key, value)?,
+                "paginate" => self.paginate = expect_count(key, value)?,
+                "paginate_path" => self.paginate_path = expect_string(key, value)?,
+                "html" => self.html = expect_enum(key, value)?,
+                "broken_links" => self.broken_links = expect_enum(key, value)?,
+                "embed_depth" => self.embed_depth = expect_count(key, value)?,
+                "heading_anchors" => self.heading_anchors = expect_bool(key, value)?,
+                "toc_min_level" => self.toc_min_level = expect_heading_level(key, value)?,
+                "toc_max_level" => self.toc_max_level = expect_heading_level(key, value)?,
+                "highlight" => self.highlight = expect_enum(key, value)?,
+                "line_numbers" => self.line_numbers = expect_bool(key, value)?,
+                _ => return invalid(key, "is not a known setting"),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_feed(&mut self, entries: &[(String, Value)]) -> Result<(), ConfigError> {
+        for (name, value) in entries {
+            let key = &format!("feed.{}", name);
+            match name.as_str() {
+                "formats" => {
+                    let Value::List(formats) = value else {
+                        return invalid(key, "must be a list");
+                    };
+                    self.feed_formats = formats
+                        .iter()
+                        .map(|format| expect_enum(key, format))
+                        .collect::<Result<_, _>>()?;
+                }
+                "limit" => self.feed_limit = expect_count(key, value)?,
+                "content" => self.feed_content = expect_enum(key, value)?,
+                _ => return invalid(key, "is not a known setting"),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_server(&mut self, entries: &[(String, Value)]) -> Result<(), ConfigError> {
+        for (name, value) in entries {
+            let key = &format!("server.{}", name);
+            match name.as_str() {
+                "address" => self.server_address = expect_string(key, value)?,
+                _ => return invalid(key, "is not a known setting"),
+            }
+        }
+
+        Ok(())
+    }
+
+    // CLIの引数で上書きした後にも呼ぶ
+    pub fn validate(&mut self) -> Result<(), ConfigError> {
+        if !self.base_url.is_empty()
+            && !self.base_url.starts_with("http://")
+            && !self.base_url.starts_with("https://")
+        {
+            return invalid("base_url", "must start with http:// or https://");
+        }
+        self.base_url = self.base_url.trim_end_matches('/').to_string();
+
+        if self.language.is_empty()
+            || !self
+                .language
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return invalid("language", "must be a language tag such as `en` or `ja`");
+        }
+
+        if self.content_dir.as_os_str().is_empty() {
+            return invalid("build.content_dir", "must not be empty");
+        }
+        // 出力先は毎回消すので、入力と同じ場所やカレントディレクトリにはできない
+        if self.output_dir.as_os_str().is_empty()
+            || self.output_dir == Path::new(".")
+            || self.content_dir.starts_with(&self.output_dir)
+        {
+            return invalid(
+                "build.output_dir",
+                "must be a directory outside the content directory",
+            );
+        }
+
+        if self.publish_key.trim_start_matches('!').is_empty() {
+            return invalid("build.publish_key", "must not be empty");
+        }
+
+        if !self.permalink.starts_with('/') {
+            return invalid("build.permalink", "must start with `/`");
+        }
+        if !self.permalink.contains(":slug") {
+            return invalid("build.permalink", "must contain `:slug`");
+        }
+        let mut rest = self.permalink.as_str();
+        while let Some(i) = rest.find(':') {
+            rest = &rest[i..];
+            let Some(placeholder) = PERMALINK_PLACEHOLDERS
+                .iter()
+                .find(|p| rest.starts_with(**p))
+            else {
+                return invalid(
+                    "build.permalink",
+                    format!(
+                        "has an unknown placeholder; use {}",
+                        PERMALINK_PLACEHOLDERS.join(", ")
+                    ),
+                );
+            };
+            rest = &rest[placeholder.len()..];
+        }
+
+        if self.toc_min_level > self.toc_max_level {
+            return invalid(
+                "build.toc_min_level",
+                "must not be greater than build.toc_max_level",
+            );
+        }
+
+        if !self.paginate_path.starts_with('/') || !self.paginate_path.contains(":num") {
+            return invalid(
+                "build.paginate_path",
+                "must start with `/` and contain `:num`",
+            );
+        }
+
+        if !self.image_prefix.starts_with('/')
+            || self.image_prefix.trim_matches('/').is_empty()
+            || self.image_prefix.split('/').any(|segment| segment == "..")
+        {
+            return invalid(
+                "build.image_prefix",
+                "must be a path inside the output directory such as `/images/`",
+            );
+        }
+        if !self.image_prefix.ends_with('/') {
+            self.image_prefix.push('/');
+        }
+
+        if self.server_address.parse::<SocketAddr>().is_err() {
+            return invalid(
+                "server.address",
+                "must be an address such as `0.0.0.0:8080`",
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn is_published(&self, front_matter: Option<&FrontMatter>) -> bool {
+        match self.publish_key.strip_prefix('!') {
+            Some(key) => !front_matter.is_some_and(|fm| fm.flag(key)),
+            None => front_matter.is_some_and(|fm| fm.flag(&self.publish_key)),
+        }
+    }
+
+    pub fn image_dir(&self) -> PathBuf {
+        self.output_dir.join(self.image_prefix.trim_matches('/'))
+    }
+
+    // front matterの `layout`、フォルダのlayout、サイトのlayoutの順に探し、テンプレートの名前にする
+    pub fn layout(&self, doc: &MdDocument) -> String {
+        let dir = doc
+            .path
+            .parent()
+            .and_then(|dir| dir.strip_prefix(&self.content_dir).ok())
+            .unwrap_or(Path::new(""));
+        let folder_layout = self
+            .folder_layouts
+            .iter()
+            .filter(|(folder, _)| dir.starts_with(folder))
+            .max_by_key(|(folder, _)| folder.components().count())
+            .map(|(_, layout)| layout);
+        let layout = doc
+            .front_matter
+            .as_ref()
+            .and_then(|fm| fm.layout())
+            .or(folder_layout.map(String::as_str))
+            .unwrap_or(&self.layout);
+
+        if layout.ends_with(".html") {
+            layout.to_string()
+        } else {
+            format!("{}.html", layout)
+        }
+    }
+
+    // 一覧のnページ目(1から)のURL
+    pub fn index_url(&self, page: usize) -> String {
+        if page <= 1 {
+            "/".to_string()
+        } else {
+            self.paginate_path.replace(":num", &page.to_string())
+        }
+    }
+
+    pub fn permalink_needs_date(&self) -> bool {
+        [":year", ":month", ":day"]
+            .iter()
+            .any(|p| self.permalink.contains(p))
+    }
+
+    // permalinkからページのURLを作る。日付がなければ日付を含むセグメントを省く
+    pub fn page_url(&self, doc: &MdDocument) -> String {
+        let date = doc.front_matter.as_ref().and_then(|fm| fm.date());
+        let dir = doc
+            .path
+            .parent()
+            .and_then(|dir| dir.strip_prefix(&self.content_dir).ok())
+            .map(|dir| dir.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+
+        let segments: Vec<String> = self
+            .permalink
+            .trim_matches('/')
+            .split('/')
+            .filter_map(|segment| {
+                let mut segment = segment
+                    .replace(":slug", output_path(doc))
+                    .replace(":path", &dir);
+                for (placeholder, value) in [
+                    (":year", date.map(|d| format!("{:04}", d.year))),
+                    (":month", date.map(|d| format!("{:02}", d.month))),
+                    (":day", date.map(|d| format!("{:02}", d.day))),
+                ] {
+                    if segment.contains(placeholder) {
+                        segment = segment.replace(placeholder, &value?);
+                    }
+                }
+                Some(segment)
+            })
+            .filter(|segment| !segment.is_empty())
+            .collect();
+
+        let mut url = format!("/{}", segments.join("/"));
+        if self.permalink.ends_with('/') && url != "/" {
+            url.push('/');
+        }
+
+        url
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::document::front_matter::parse_front_matter;
+
+    fn invalid_key(source: &str) -> String {
+        match Config::from_toml(source) {
+            Err(ConfigError::Invalid { key, .. }) => key,
+            result => panic!("Expected Invalid, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_from_toml() {
+        let config = Config::from_toml(concat!(
+            "title = \"My Notes\"\n",
+            "base_url = \"https://example.com/\"\n",
+            "language = \"en\"\n",
+            "\n",
+            "[build]\n",
+            "content_dir = \"notes\"\n",
+            "templates_dir = \"layouts\"\n",
+            "publish_key = \"!draft\"\n",
+            "permalink = \"/posts/:year/:slug/\"\n",
+            "image_prefix = \"/assets/img\"\n",
+            "html = \"sanitize\"\n",
+            "embed_depth = 2\n",
+            "paginate = 5\n",
+            "heading_anchors = false\n",
+            "toc_min_level = 2\n",
+            "toc_max_level = 3\n",
+            "highlight = \"inline\"\n",
+            "line_numbers = true\n",
+            "paginate_path = \"/posts/:num.html\"\n",
+            "\n",
+            "[feed]\n",
+            "formats = [\"rss\"]\n",
+            "content = \"summary\"\n",
+            "\n",
+            "[server]\n",
+            "address = \"127.0.0.1:3000\"\n",
+        ))
+        .unwrap();
+
+        assert_eq!(
+            config,
+            Config {
+                title: "My Notes".to_string(),
+                base_url: "https://example.com".to_string(),
+                language: "en".to_string(),
+                content_dir: PathBuf::from("notes"),
+                templates_dir: PathBuf::from("layouts"),
+                publish_key: "!draft".to_string(),
+                permalink: "/posts/:year/:slug/".to_string(),
+                image_prefix: "/assets/img/".to_string(),
+                server_address: "127.0.0.1:3000".to_string(),
+                html: HtmlPolicy::Sanitize,
+                embed_depth: 2,
+                paginate: 5,
+                heading_anchors: false,
+                toc_min_level: 2,
+                toc_max_level: 3,
+                highlight: HighlightMode::Inline,
+                line_numbers: true,
+                paginate_path: "/posts/:num.html".to_string(),
+                feed_formats: vec![FeedFormat::Rss],
+                feed_content: FeedContent::Summary,
+                ..Config::default()
+            }
+        );
+        assert_eq!(config.image_dir(), PathBuf::from("public/assets/img"));
+        assert_eq!(config.index_url(1), "/");
+        assert_eq!(config.index_url(3), "/posts/3.html");
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert_eq!(invalid_key("titel = \"x\"\n"), "titel");
+        assert_eq!(invalid_key("[build]\nbreak = true\n"), "build.break");
+        assert_eq!(
+            invalid_key("[build]\nembed_depth = \"5\"\n"),
+            "build.embed_depth"
+        );
+        assert_eq!(invalid_key("[build]\nhtml = \"raw\"\n"), "build.html");
+        assert_eq!(
+            invalid_key("[build]\nhighlight = \"js\"\n"),
+            "build.highlight"
+        );
+        assert_eq!(
+            invalid_key("[build]\npermalink = \"/:title\"\n"),
+            "build.permalink"
+        );
+        assert_eq!(
+            invalid_key("[build]\noutput_dir = \".\"\n"),
+            "build.output_dir"
+        );
+        assert_eq!(
+            invalid_key("[build]\npaginate_path = \"/page/\"\n"),
+            "build.paginate_path"
+        );
+        assert_eq!(
+            invalid_key("[feed]\nformats = [\"json\"]\n"),
+            "feed.formats"
+        );
+        assert_eq!(
+            invalid_key("[build]\ntoc_max_level = 7\n"),
+            "build.toc_max_level"
+        );
+        assert_eq!(
+            invalid_key("[build]\ntoc_min_level = 4\ntoc_max_level = 3\n"),
+            "build.toc_min_level"
+        );
+        assert_eq!(invalid_key("base_url = \"example.com\"\n"), "base_url");
+        assert_eq!(
+            invalid_key("[server]\naddress = \"localhost\"\n"),
+            "server.address"
+        );
+        assert!(matches!(
+            Config::from_toml("title = \n"),
+            Err(ConfigError::Syntax(FrontMatterError { line: 1, .. }))
+        ));
+        assert_eq!(
+            Config::from_toml("[build]\nhtml = \"raw\"\n")
+                .unwrap_err()
+                .to_string(),
+            "invalid biotite.toml: `build.html` must be one of `escape`, `allow`, `sanitize`"
+        );
+    }
+
+    #[test]
+    fn test_page_url() {
+        let path = PathBuf::from("contents/dir/Note.md");
+        let (front_matter, _) =
+            parse_front_matter("---\nslug: my-note\ndate: 2024-03-05\n---\n").unwrap();
+        let dated = MdDocument {
+            path: &path,
+            front_matter,
+            body: None,
+        };
+        let undated = MdDocument {
+            path: &path,
+            front_matter: None,
+            body: None,
+        };
+        let url = |permalink: &str, doc: &MdDocument| {
+            Config {
+                permalink: permalink.to_string(),
+                ..Config::default()
+            }
+            .page_url(doc)
+        };
+
+        assert_eq!(url("/:slug.html", &dated), "/my-note.html");
+        assert_eq!(url("/:slug.html", &undated), "/Note.html");
+        assert_eq!(url("/:path/:slug/", &dated), "/dir/my-note/");
+        assert_eq!(
+            url("/posts/:year/:month-:day/:slug", &dated),
+            "/posts/2024/03-05/my-note"
+        );
+        assert_eq!(url("/posts/:year/:slug/", &undated), "/posts/Note/");
+    }
+
+    #[test]
+    fn test_is_published() {
+        let (front_matter, _) = parse_front_matter("---\ndraft: true\n---\n").unwrap();
+        let draft = front_matter.as_ref();
+        let config = |key: &str| Config {
+            publish_key: key.to_string(),
+            ..Config::default()
+        };
+
+        assert!(!config("publish").is_published(draft));
+        assert!(!config("publish").is_published(None));
+        assert!(!config("!draft").is_published(draft));
+        assert!(config("!draft").is_published(None));
+    }
+
+    #[test]
+    fn test_layout() {
+        let config = Config::from_toml(concat!(
+            "[build]\n",
+            "layout = \"post\"\n",
+            "\n",
+            "[layouts]\n",
+            "slides = \"wide\"\n",
+            "\"slides/print/\" = \"print.html\"\n",
+        ))
+        .unwrap();
+        let layout = |path: &str, source: &str| {
+            let path = PathBuf::from(path);
+            let (front_matter, _) = parse_front_matter(source).unwrap();
+            config.layout(&MdDocument {
+                path: &path,
+                front_matter,
+                body: None,
+            })
+        };
+
+        assert_eq!(layout("contents/About.md", ""), "post.html");
+        assert_eq!(layout("contents/slides/A.md", ""), "wide.html");
+        assert_eq!(layout("contents/slides/print/A.md", ""), "print.html");
+        assert_eq!(layout("contents/slideshow/A.md", ""), "post.html");
+        assert_eq!(
+            layout("contents/slides/A.md", "---\nlayout: home\n---\n"),
+            "home.html"
+        );
+        assert_eq!(invalid_key("[layouts]\nx = \"../x\"\n"), "layouts.x");
+    }
+}
@@ -2,8 +2,11 @@ pub mod image;
 pub mod scan;
 pub mod write;
 
+use crate::parser::document::front_matter::Date;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
 
 pub fn clear(output_dir: &PathBuf, image_dir: &PathBuf) -> Result<(), std::io::Error> {
     if output_dir.exists() {
@@ -19,3 +22,33 @@ pub fn clear(output_dir: &PathBuf, image_dir: &PathBuf) -> Result<(), std::io::E
 
     Ok(())
 }
+
+// 静的ファイルのディレクトリの中身を、そのまま出力先にコピーする
+pub fn copy_static(static_dir: &Path, output_dir: &Path) -> Result<(), std::io::Error> {
+    if !static_dir.is_dir() {
+        return Ok(());
+    }
+
+    for entry in WalkDir::new(static_dir) {
+        let entry = entry.map_err(std::io::Error::other)?;
+        let Ok(relative) = entry.path().strip_prefix(static_dir) else {
+            continue;
+        };
+        let target = output_dir.join(relative);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+
+    Ok(())
+}
+
+// ファイルの更新日時(UTC)
+pub fn modified_date(path: &Path) -> Option<Date> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    let secs = modified.duration_since(UNIX_EPOCH).ok()?.as_secs();
+
+    Some(Date::from_unix_time(secs as i64))
+}
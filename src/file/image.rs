@@ -1,16 +1,17 @@
-use crate::parser::document::ast::{Block, Inline, ListType, MdDocument};
+use crate::parser::document::ast::{Block, Inline, MdDocument};
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+// image_prefixはコピーした画像のURLの先頭(`/images/`)
 pub fn copy_document_images(
     doc: &mut MdDocument,
     image_dir: &PathBuf,
+    image_prefix: &str,
 ) -> Result<(), std::io::Error> {
     let base_dir = doc.path.parent().unwrap_or(Path::new("."));
-
     for block in &mut doc.body.as_mut().unwrap().blocks {
-        process_block_images(block, base_dir, image_dir)?;
+        process_block_images(block, base_dir, image_dir, image_prefix)?;
     }
 
     Ok(())
@@ -20,30 +21,48 @@ fn process_block_images(
     block: &mut Block,
     base_dir: &Path,
     image_dir: &PathBuf,
+    image_prefix: &str,
 ) -> Result<(), std::io::Error> {
     match block {
         Block::Paragraph(inlines)
         | Block::Heading {
             content: inlines, ..
-        } => process_inlines(inlines, base_dir, image_dir),
-        Block::Blockquote(blocks) => {
+        } => process_inlines(inlines, base_dir, image_dir, image_prefix),
+        Block::Blockquote(blocks) | Block::FootnoteDefinition { blocks, .. } => {
+            for b in blocks {
+                process_block_images(b, base_dir, image_dir, image_prefix)?;
+            }
+
+            Ok(())
+        }
+        Block::Callout { title, blocks, .. } => {
+            if let Some(title) = title {
+                process_inlines(title, base_dir, image_dir, image_prefix)?;
+            }
             for b in blocks {
-                process_block_images(b, base_dir, image_dir)?;
+                process_block_images(b, base_dir, image_dir, image_prefix)?;
             }
 
             Ok(())
         }
         Block::List(list_type) => {
-            let items = match list_type {
-                ListType::Ordered(items) => items,
-                ListType::Unordered(items) => items,
-            };
-            for item in items {
-                process_inlines(&mut item.content, base_dir, image_dir)?;
+            let items = list_type.items_mut();
+            for b in items.iter_mut().flat_map(|item| item.blocks.iter_mut()) {
+                process_block_images(b, base_dir, image_dir, image_prefix)?;
             }
 
             Ok(())
         }
+        Block::Table { headers, rows, .. } => {
+            for cell in headers.iter_mut().chain(rows.iter_mut().flatten()) {
+                process_inlines(cell, base_dir, image_dir, image_prefix)?;
+            }
+
+            Ok(())
+        }
+        Block::Embed(embed) if embed.is_asset() => {
+            handle_image_copy(&mut embed.target, base_dir, image_dir, image_prefix)
+        }
         _ => Ok(()),
     }
 }
@@ -52,17 +71,22 @@ fn process_inlines(
     inlines: &mut Vec<Inline>,
     base_dir: &Path,
     image_dir: &PathBuf,
+    image_prefix: &str,
 ) -> Result<(), std::io::Error> {
     for inline in inlines {
         match inline {
             Inline::Image { url, .. } => {
-                handle_image_copy(url, base_dir, image_dir)?;
+                handle_image_copy(url, base_dir, image_dir, image_prefix)?;
+            }
+            // 画像とPDFの埋め込みも同じように出力先へコピーする
+            Inline::Embed(embed) if embed.is_asset() => {
+                handle_image_copy(&mut embed.target, base_dir, image_dir, image_prefix)?;
             }
             Inline::Strong(children)
             | Inline::Italic(children)
             | Inline::Strikethrough(children)
             | Inline::Link { text: children, .. } => {
-                process_inlines(children, base_dir, image_dir)?;
+                process_inlines(children, base_dir, image_dir, image_prefix)?;
             }
             _ => {}
         }
@@ -74,7 +98,8 @@ fn process_inlines(
 fn handle_image_copy(
     url: &mut String,
     base_dir: &Path,
-    image_dir: &PathBuf,
+    image_dir: &Path,
+    image_prefix: &str,
 ) -> Result<(), std::io::Error> {
     if url.starts_with("http://") || url.starts_with("https://") {
         return Ok(());
@@ -82,9 +107,9 @@ fn handle_image_copy(
 
     let source_path = if url.starts_with("/") {
         Path::new(url).to_path_buf()
-    } else if url.starts_with("~/") {
+    } else if let Some(rest) = url.strip_prefix("~/") {
         let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
-        Path::new(&home).join(&url[2..])
+        Path::new(&home).join(rest)
     } else {
         base_dir.join(&url)
     };
@@ -95,7 +120,7 @@ fn handle_image_copy(
 
             fs::copy(&source_path, &copied_path)?;
 
-            let new_url = format!("/images/{}", file_name.to_string_lossy());
+            let new_url = format!("{}{}", image_prefix, file_name.to_string_lossy());
             *url = new_url;
         }
     } else {
@@ -4,7 +4,7 @@ use walkdir::WalkDir;
 fn is_markdown_file(path: impl AsRef<Path>) -> bool {
     path.as_ref()
         .extension()
-        .map_or(false, |ext| ext == "md" || ext == "markdown")
+        .is_some_and(|ext| ext == "md" || ext == "markdown")
 }
 
 pub fn scan_dir(dir: &PathBuf) -> Vec<PathBuf> {
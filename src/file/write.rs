@@ -1,15 +1,30 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::Path;
 
-pub fn write(output_dir: &PathBuf, file_name: String, html: String) -> Result<(), std::io::Error> {
-    let file_path = file_name + ".html";
-    let output_path = output_dir.join(file_path);
+// ページのURLから出力するファイルを決める。`/a/` は `a/index.html`、`/a` は `a.html`
+pub fn write(output_dir: &Path, url: &str, html: String) -> Result<(), std::io::Error> {
+    let mut file_path = url.trim_start_matches('/').to_string();
+    if file_path.is_empty() || file_path.ends_with('/') {
+        file_path.push_str("index.html");
+    } else if !file_path.ends_with(".html") {
+        file_path.push_str(".html");
+    }
+
+    write_file(output_dir, &file_path, html)
+}
+
+// `feed.xml` のようなHTML以外のファイルを、パスのとおりに書き出す
+pub fn write_file(output_dir: &Path, path: &str, content: String) -> Result<(), std::io::Error> {
+    let output_path = output_dir.join(path.trim_start_matches('/'));
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
 
     let mut file = File::create(&output_path)?;
-    file.write_all(html.as_bytes())?;
+    file.write_all(content.as_bytes())?;
 
-    println!("Successfully generated HTML file at: {:?}", output_path);
+    println!("Successfully generated file at: {:?}", output_path);
 
     Ok(())
 }
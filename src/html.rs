@@ -1,3 +1,17 @@
+pub mod backlink;
+pub mod block_id;
 pub mod convert;
+pub mod embed;
+pub mod feed;
+pub mod footnote;
+pub mod heading;
+pub mod highlight;
+pub mod index;
+pub mod math;
+pub mod raw_html;
+pub mod sitemap;
+pub mod slug;
+pub mod tags;
 pub mod template;
 pub mod to_html;
+pub mod wikilink;
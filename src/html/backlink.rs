@@ -0,0 +1,254 @@
+use crate::html::to_html::document_title;
+use crate::html::wikilink::LinkIndex;
+use crate::parser::document::ast::{Block, Inline, MdDocument};
+use crate::parser::document::front_matter::Value;
+use percent_encoding::percent_decode_str;
+
+// あるページへのリンクを含むページと、リンクの前後の文
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backlink {
+    pub title: String,
+    pub url: String,
+    pub contexts: Vec<String>,
+}
+
+// ページ間のリンクのグラフ。公開するドキュメントだけから作る
+#[derive(Debug, Default)]
+pub struct LinkGraph {
+    // 索引と同じ並びで、そのページへの被リンク
+    backlinks: Vec<Vec<Backlink>>,
+}
+
+// 通常のリンクのURLがどのページを指しているか
+fn link_target(url: &str, index: &LinkIndex) -> Option<usize> {
+    let path = url.split('#').next().unwrap_or(url);
+    if path.is_empty() || path.contains("://") || path.starts_with("mailto:") {
+        return None;
+    }
+
+    let path = percent_decode_str(path).decode_utf8_lossy();
+    index
+        .find_url(&path)
+        .or_else(|| index.find(path.trim_start_matches("./")))
+}
+
+// インラインを文字列にしながら、リンクの位置(バイト位置)とリンク先を集める
+fn flatten(
+    inlines: &[Inline],
+    index: &LinkIndex,
+    text: &mut String,
+    links: &mut Vec<(usize, usize)>,
+) {
+    for inline in inlines {
+        match inline {
+            Inline::WikiLink {
+                target, text: t, ..
+            } => {
+                if let Some(doc) = index.find(target) {
+                    links.push((text.len(), doc));
+                }
+                text.push_str(t);
+            }
+            Inline::Embed(embed) if !embed.is_asset() => {
+                if let Some(doc) = index.find(&embed.target) {
+                    links.push((text.len(), doc));
+                }
+                text.push_str(&embed.target);
+            }
+            Inline::Link {
+                text: children,
+                url,
+            } => {
+                if let Some(doc) = link_target(url, index) {
+                    links.push((text.len(), doc));
+                }
+                flatten(children, index, text, links);
+            }
+            Inline::Strong(children)
+            | Inline::Italic(children)
+            | Inline::Strikethrough(children) => flatten(children, index, text, links),
+            inline => text.push_str(&Inline::plain_text(std::slice::from_ref(inline))),
+        }
+    }
+}
+
+// 位置を含む1文を取り出す
+fn sentence_at(text: &str, position: usize) -> String {
+    let is_end = |c: char| matches!(c, '.' | '!' | '?' | '。' | '！' | '？');
+    let start = text[..position]
+        .char_indices()
+        .rev()
+        .find(|(_, c)| is_end(*c))
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let end = text[position..]
+        .char_indices()
+        .find(|(_, c)| is_end(*c))
+        .map_or(text.len(), |(i, c)| position + i + c.len_utf8());
+
+    text[start..end].trim().to_string()
+}
+
+// インライン中のリンク先と、リンクを含む文
+fn links_in(inlines: &[Inline], index: &LinkIndex) -> Vec<(usize, String)> {
+    let mut text = String::new();
+    let mut links = Vec::new();
+    flatten(inlines, index, &mut text, &mut links);
+
+    links
+        .into_iter()
+        .map(|(position, target)| (target, sentence_at(&text, position)))
+        .collect()
+}
+
+impl LinkGraph {
+    // docsは索引を作ったときと同じ並び。埋め込みを展開する前の本文から作る
+    pub fn new(docs: &[MdDocument], index: &LinkIndex) -> Self {
+        let mut backlinks = vec![Vec::<Backlink>::new(); docs.len()];
+
+        for (source, doc) in docs.iter().enumerate() {
+            let Some(mut body) = doc.body.clone() else {
+                continue;
+            };
+
+            let mut found = Vec::new();
+            for block in &mut body.blocks {
+                block.walk_blocks_mut(&mut |block| match block {
+                    Block::Heading { content, .. } | Block::Paragraph(content) => {
+                        found.extend(links_in(content, index))
+                    }
+                    Block::Callout {
+                        title: Some(title), ..
+                    } => found.extend(links_in(title, index)),
+                    Block::Table { headers, rows, .. } => headers
+                        .iter()
+                        .chain(rows.iter().flatten())
+                        .for_each(|cell| found.extend(links_in(cell, index))),
+                    Block::Embed(embed) if !embed.is_asset() => {
+                        if let Some(target) = index.find(&embed.target) {
+                            found.push((target, String::new()));
+                        }
+                    }
+                    _ => {}
+                });
+            }
+
+            for (target, context) in found {
+                if target == source {
+                    continue;
+                }
+                let entries = &mut backlinks[target];
+                let entry = match entries.iter().position(|b| b.url == index.url(source)) {
+                    Some(i) => &mut entries[i],
+                    None => {
+                        entries.push(Backlink {
+                            title: document_title(doc).to_string(),
+                            url: index.url(source).to_string(),
+                            contexts: Vec::new(),
+                        });
+                        entries.last_mut().unwrap()
+                    }
+                };
+                if !context.is_empty() && !entry.contexts.contains(&context) {
+                    entry.contexts.push(context);
+                }
+            }
+        }
+
+        LinkGraph { backlinks }
+    }
+
+    pub fn backlinks(&self, doc: usize) -> &[Backlink] {
+        &self.backlinks[doc]
+    }
+}
+
+impl Backlink {
+    // テンプレートの `page.backlinks` の要素
+    pub fn to_value(&self) -> Value {
+        Value::Map(vec![
+            ("title".to_string(), Value::String(self.title.clone())),
+            ("url".to_string(), Value::String(self.url.clone())),
+            (
+                "contexts".to_string(),
+                Value::List(self.contexts.iter().cloned().map(Value::String).collect()),
+            ),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_link_graph() {
+        let paths = ["a", "b", "c"].map(|name| PathBuf::from(format!("contents/{}.md", name)));
+        let docs = vec![
+            MdDocument::for_test(
+                &paths[0],
+                &[("title", "A")],
+                "Intro. See [[b]] for more! Also [[a]].\n",
+            ),
+            MdDocument::for_test(
+                &paths[1],
+                &[("title", "B")],
+                "- a [link](/c.html#top) here. Next\n",
+            ),
+            MdDocument::for_test(
+                &paths[2],
+                &[("title", "C")],
+                "![[b]]\n\n*From* [[b|B note]]\n",
+            ),
+        ];
+        let index = LinkIndex::new(&docs, &Config::default());
+        let graph = LinkGraph::new(&docs, &index);
+
+        assert_eq!(graph.backlinks(0), &[]);
+        assert_eq!(
+            graph.backlinks(1),
+            &[
+                Backlink {
+                    title: "A".to_string(),
+                    url: "/a.html".to_string(),
+                    contexts: vec!["See b for more!".to_string()],
+                },
+                Backlink {
+                    title: "C".to_string(),
+                    url: "/c.html".to_string(),
+                    contexts: vec!["From B note".to_string()],
+                },
+            ]
+        );
+        assert_eq!(
+            graph.backlinks(2),
+            &[Backlink {
+                title: "B".to_string(),
+                url: "/b.html".to_string(),
+                contexts: vec!["a link here.".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_backlink_to_value() {
+        let backlink = Backlink {
+            title: "A".to_string(),
+            url: "/a.html".to_string(),
+            contexts: vec!["x".to_string()],
+        };
+
+        assert_eq!(
+            backlink.to_value(),
+            Value::Map(vec![
+                ("title".to_string(), Value::String("A".to_string())),
+                ("url".to_string(), Value::String("/a.html".to_string())),
+                (
+                    "contexts".to_string(),
+                    Value::List(vec![Value::String("x".to_string())])
+                ),
+            ])
+        );
+    }
+}
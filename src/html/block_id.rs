@@ -0,0 +1,137 @@
+use crate::parser::document::ast::{Block, Document, Inline};
+
+// 段落の末尾の `^id` を取り出す。`^` の前は空白か段落の先頭
+fn block_marker(inlines: &[Inline]) -> Option<String> {
+    let text = Inline::plain_text(inlines);
+    let text = text.trim_end();
+    let (head, id) = text.rsplit_once('^')?;
+    if id.is_empty()
+        || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        || !(head.is_empty() || head.ends_with(char::is_whitespace))
+    {
+        return None;
+    }
+
+    Some(id.to_string())
+}
+
+// 目印を取り除けたらそのidを返す。コードの中などTextでない目印はそのまま
+fn strip_marker(inlines: &mut Vec<Inline>) -> Option<String> {
+    let id = block_marker(inlines)?;
+    Inline::strip_text_suffix(inlines, &format!("^{}", id)).then_some(id)
+}
+
+fn assign_ids(blocks: &mut Vec<Block>, with_ids: bool) {
+    let mut i = 0;
+    while i < blocks.len() {
+        match &mut blocks[i] {
+            Block::Paragraph(inlines) => {
+                if let Some(id) = strip_marker(inlines) {
+                    // `^id` だけの段落は直前のブロックに付く
+                    let owner = if inlines.is_empty() && i > 0 {
+                        blocks.remove(i);
+                        i -= 1;
+                        i
+                    } else {
+                        i
+                    };
+                    if with_ids {
+                        let block = std::mem::replace(&mut blocks[owner], Block::HorizontalRule);
+                        blocks[owner] = Block::Identified {
+                            id: format!("^{}", id),
+                            block: Box::new(block),
+                        };
+                    }
+                }
+            }
+            Block::Blockquote(blocks)
+            | Block::Callout { blocks, .. }
+            | Block::FootnoteDefinition { blocks, .. } => assign_ids(blocks, with_ids),
+            // 埋め込んだブロックのidは埋め込み元のページにあるので、目印だけ消す
+            Block::Transclusion { blocks, .. } => assign_ids(blocks, false),
+            Block::List(list) => {
+                for item in list.items_mut() {
+                    if let Some(Block::Paragraph(inlines)) = item.blocks.first_mut()
+                        && let Some(id) = strip_marker(inlines)
+                        && with_ids
+                    {
+                        item.id = Some(format!("^{}", id));
+                    }
+                    assign_ids(&mut item.blocks, with_ids);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+// `^id` の目印を本文から消し、そのidを目印の付いたブロックの要素に付ける
+// `[[Note#^id]]` のリンク先(html::wikilink)はこのidになる
+pub fn assign_block_ids(doc: &mut Document) {
+    assign_ids(&mut doc.blocks, true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::html::convert::ToHtml;
+    use crate::html::wikilink::{BrokenLinkPolicy, LinkIndex, resolve_wikilinks};
+    use crate::parser::document::ast::MdDocument;
+    use crate::parser::document::parse_document;
+    use std::path::PathBuf;
+
+    fn parse(input: &str) -> Document {
+        parse_document()(input).unwrap().0
+    }
+
+    #[test]
+    fn test_assign_block_ids() {
+        let mut doc = parse(concat!(
+            "text ^blk\n\n",
+            "- one ^item\n- two\n\n",
+            "> quoted\n\n^quote\n\n",
+            "no^marker and `code ^x`\n"
+        ));
+        assign_block_ids(&mut doc);
+
+        assert_eq!(
+            doc.to_html(),
+            concat!(
+                "<p id=\"^blk\">text</p>",
+                "<ul>\n<li id=\"^item\">one</li>\n<li>two</li>\n</ul>",
+                "<blockquote id=\"^quote\"><p>quoted</p></blockquote>",
+                "<p>no^marker and <code>code ^x</code></p>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_block_link_round_trip() {
+        let paths = ["A", "B"].map(|name| PathBuf::from(format!("contents/{}.md", name)));
+        let docs: Vec<_> = ["some text ^blk\n", "see [[A#^blk]]\n"]
+            .iter()
+            .zip(&paths)
+            .map(|(input, path)| MdDocument::for_test(path, &[], input))
+            .collect();
+        let index = LinkIndex::new(&docs, &Config::default());
+
+        let mut link = docs[1].body.clone().unwrap();
+        resolve_wikilinks(&mut link, &index, BrokenLinkPolicy::Text);
+        let mut href = String::new();
+        link.blocks[0].walk_inlines_mut(&mut |inline| {
+            if let Inline::Link { url, .. } = inline {
+                href = url.clone();
+            }
+        });
+        let (page, anchor) = href.split_once('#').unwrap();
+        assert_eq!(page, "/A.html");
+
+        let mut target = docs[0].body.clone().unwrap();
+        assign_block_ids(&mut target);
+        let html = target.to_html();
+        assert_eq!(html, format!("<p id=\"{}\">some text</p>", anchor));
+        assert!(!html.contains("^blk<"));
+    }
+}
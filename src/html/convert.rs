@@ -1,6 +1,7 @@
-use crate::parser::document::ast::{Block, Document, Inline, ListItem, ListType};
+use crate::html::math::tex_to_mathml;
+use crate::parser::document::ast::{Alignment, Block, Document, Embed, Inline, ListItem, ListType};
 
-fn escape_html(s: &str) -> String {
+pub(crate) fn escape_html(s: &str) -> String {
     s.replace("&", "&amp;")
         .replace("<", "&lt;")
         .replace(">", "&gt;")
@@ -20,9 +21,26 @@ impl ToHtml for Inline {
             Inline::Italic(inlines) => format!("<em>{}</em>", inlines.to_html()),
             Inline::Strikethrough(inlines) => format!("<del>{}</del>", inlines.to_html()),
             Inline::Code(s) => format!("<code>{}</code>", escape_html(s)),
+            Inline::SoftBreak => "\n".to_string(),
+            Inline::HardBreak => "<br />\n".to_string(),
+            Inline::Math { tex, display } => match tex_to_mathml(tex, *display) {
+                Ok(mathml) => mathml,
+                Err(_) => {
+                    let delimiter = if *display { "$$" } else { "$" };
+                    format!(
+                        "<span class=\"math-error\">{}</span>",
+                        escape_html(&format!("{}{}{}", delimiter, tex, delimiter))
+                    )
+                }
+            },
             Inline::Link { text, url } => {
                 format!("<a href=\"{}\">{}</a>", escape_html(url), text.to_html())
             }
+            // 解決できなかったWikiリンク
+            Inline::WikiLink { text, .. } => {
+                format!("<span class=\"broken-link\">{}</span>", escape_html(text))
+            }
+            Inline::Embed(embed) => embed_html(embed),
             Inline::Image { alt, url } => {
                 format!(
                     "<img src=\"{}\" alt=\"{}\" />",
@@ -30,6 +48,28 @@ impl ToHtml for Inline {
                     escape_html(alt)
                 )
             }
+            Inline::FootnoteRef {
+                number: Some(n),
+                occurrence,
+                ..
+            } => {
+                let ref_id = if *occurrence > 1 {
+                    format!("fnref-{}-{}", n, occurrence)
+                } else {
+                    format!("fnref-{}", n)
+                };
+                format!(
+                    "<sup class=\"footnote-ref\"><a href=\"#fn-{}\" id=\"{}\">{}</a></sup>",
+                    n, ref_id, n
+                )
+            }
+            // 定義のない参照は元の記法のまま出力する
+            Inline::FootnoteRef {
+                label,
+                number: None,
+                ..
+            } => escape_html(&format!("[^{}]", label)),
+            Inline::Html(html) => html.clone(),
         }
     }
 }
@@ -40,12 +80,77 @@ impl ToHtml for Vec<Inline> {
     }
 }
 
+fn code_attribute<'a>(attributes: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+// 見出し(`title="src/main.rs"`)、差分、折りたたみのあるコードブロックはfigureで囲む
+fn code_figure_html(
+    language: Option<&str>,
+    attributes: &[(String, String)],
+    code: &str,
+    pre_html: String,
+) -> String {
+    let title = code_attribute(attributes, "title").filter(|t| !t.is_empty());
+    let diff = code_attribute(attributes, "diff").is_some();
+    let collapse = code_attribute(attributes, "collapse");
+    if title.is_none() && !diff && collapse.is_none() {
+        return pre_html;
+    }
+
+    let class = if diff {
+        "code-block diff"
+    } else {
+        "code-block"
+    };
+    match collapse {
+        // 折りたたんだときは見出しがなければ言語と行数を出す
+        Some(collapse) => {
+            let lines = code.lines().count();
+            let summary = match title {
+                Some(title) => escape_html(title),
+                None => format!(
+                    "{} ({} lines)",
+                    escape_html(language.unwrap_or("code")),
+                    lines
+                ),
+            };
+            format!(
+                "<figure class=\"{}\"><details{}><summary class=\"code-title\">{}</summary>{}</details></figure>",
+                class,
+                if collapse == "open" { " open" } else { "" },
+                summary,
+                pre_html
+            )
+        }
+        None => format!(
+            "<figure class=\"{}\">{}{}</figure>",
+            class,
+            title.map_or(String::new(), |title| format!(
+                "<figcaption class=\"code-title\">{}</figcaption>",
+                escape_html(title)
+            )),
+            pre_html
+        ),
+    }
+}
+
 impl ToHtml for Block {
     fn to_html(&self) -> String {
         match self {
-            Block::Heading { level, content } => {
-                format!("<h{}>{}</h{}>", level, content.to_html(), level)
-            }
+            Block::Heading { level, content, id } => match id {
+                Some(id) => format!(
+                    "<h{} id=\"{}\">{}</h{}>",
+                    level,
+                    escape_html(id),
+                    content.to_html(),
+                    level
+                ),
+                None => format!("<h{}>{}</h{}>", level, content.to_html(), level),
+            },
             Block::Paragraph(content) => {
                 format!("<p>{}</p>", content.to_html())
             }
@@ -53,70 +158,302 @@ impl ToHtml for Block {
                 let inner_html = blocks.iter().map(|b| b.to_html()).collect::<String>();
                 format!("<blockquote>{}</blockquote>", inner_html)
             }
-            Block::FencedCodeBlock { language, code } => {
+            Block::Callout {
+                kind,
+                title,
+                foldable,
+                default_open,
+                blocks,
+            } => {
+                // タイトルが省略された場合は種類名を先頭大文字にして使う
+                let title_html = match title {
+                    Some(inlines) => inlines.to_html(),
+                    None => {
+                        let mut chars = kind.chars();
+                        let capitalized = chars
+                            .next()
+                            .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+                            .unwrap_or_default();
+                        escape_html(&capitalized)
+                    }
+                };
+                let inner_html = blocks.iter().map(|b| b.to_html()).collect::<String>();
+                let class = format!("callout callout-{}", escape_html(kind));
+
+                if *foldable {
+                    format!(
+                        "<details class=\"{}\"{}><summary class=\"callout-title\">{}</summary><div class=\"callout-content\">{}</div></details>",
+                        class,
+                        if *default_open { " open" } else { "" },
+                        title_html,
+                        inner_html
+                    )
+                } else {
+                    format!(
+                        "<div class=\"{}\"><div class=\"callout-title\">{}</div><div class=\"callout-content\">{}</div></div>",
+                        class, title_html, inner_html
+                    )
+                }
+            }
+            Block::FencedCodeBlock {
+                language,
+                attributes,
+                code,
+                highlighted,
+            } => {
                 let lang_class = match language {
                     Some(l) => format!(" class=\"language-{}\"", escape_html(l)),
                     None => "".to_string(),
                 };
-                format!(
+                let pre_html = format!(
                     "<pre><code{}>{}</code></pre>",
                     lang_class,
-                    escape_html(code)
-                )
+                    highlighted.clone().unwrap_or_else(|| escape_html(code))
+                );
+                code_figure_html(language.as_deref(), attributes, code, pre_html)
             }
             Block::HorizontalRule => "<hr />".to_string(),
+            // 変換できない数式は元のTeXをそのまま出す
+            Block::MathBlock(tex) => match tex_to_mathml(tex, true) {
+                Ok(mathml) => mathml,
+                Err(_) => format!(
+                    "<pre class=\"math-error\">{}</pre>",
+                    escape_html(&format!("$$\n{}\n$$", tex))
+                ),
+            },
             Block::List(list_type) => list_type.to_html(),
+            Block::Table {
+                headers,
+                alignments,
+                rows,
+            } => {
+                let header_html = table_row_html("th", headers, alignments);
+                let body_html = rows
+                    .iter()
+                    .map(|row| table_row_html("td", row, alignments))
+                    .collect::<String>();
+
+                if body_html.is_empty() {
+                    format!("<table>\n<thead>\n{}</thead>\n</table>", header_html)
+                } else {
+                    format!(
+                        "<table>\n<thead>\n{}</thead>\n<tbody>\n{}</tbody>\n</table>",
+                        header_html, body_html
+                    )
+                }
+            }
+            // 脚注定義は本文には出さず、Documentの末尾にまとめて出力する
+            Block::FootnoteDefinition { .. } => String::new(),
+            Block::Html(html) => format!("{}\n", html),
+            Block::Embed(embed) => format!("<p>{}</p>", embed_html(embed)),
+            Block::Transclusion { title, url, blocks } => format!(
+                "<div class=\"embed\"><div class=\"embed-title\"><a href=\"{}\">{}</a></div><div class=\"embed-content\">{}</div></div>",
+                escape_html(url),
+                escape_html(title),
+                blocks.iter().map(|b| b.to_html()).collect::<String>()
+            ),
+            Block::Identified { id, block } => with_id(&block.to_html(), id),
         }
     }
 }
 
+// 最初の開始タグにidを足す。タグで始まらなければdivで囲む
+fn with_id(html: &str, id: &str) -> String {
+    let id_attribute = format!(" id=\"{}\"", escape_html(id));
+    if html.starts_with('<') && html[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+        let name_end = html
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .unwrap_or(html.len());
+        format!("{}{}{}", &html[..name_end], id_attribute, &html[name_end..])
+    } else {
+        format!("<div{}>{}</div>", id_attribute, html)
+    }
+}
+
+// 画像とPDFの埋め込み。ノートの埋め込みは展開(html::embed)されずに残ったもの
+fn embed_html(embed: &Embed) -> String {
+    // コピー後のURLではなくファイル名を代替テキストにする
+    let file_name = embed.target.rsplit('/').next().unwrap_or(&embed.target);
+    let label = embed.label.as_deref().unwrap_or(file_name);
+    let size = embed
+        .width
+        .map(|w| format!(" width=\"{}\"", w))
+        .into_iter()
+        .chain(embed.height.map(|h| format!(" height=\"{}\"", h)))
+        .collect::<String>();
+
+    if embed.is_image() {
+        format!(
+            "<img src=\"{}\" alt=\"{}\"{} />",
+            escape_html(&embed.target),
+            escape_html(label),
+            size
+        )
+    } else if embed.is_pdf() {
+        let fragment = embed
+            .fragment
+            .as_ref()
+            .map(|f| format!("#{}", f))
+            .unwrap_or_default();
+        format!(
+            "<iframe class=\"embed-pdf\" src=\"{}{}\" title=\"{}\"{}></iframe>",
+            escape_html(&embed.target),
+            escape_html(&fragment),
+            escape_html(label),
+            size
+        )
+    } else {
+        format!("<span class=\"broken-link\">{}</span>", escape_html(label))
+    }
+}
+
+fn table_row_html(tag: &str, cells: &[Vec<Inline>], alignments: &[Alignment]) -> String {
+    let cells_html = cells
+        .iter()
+        .zip(alignments)
+        .map(|(cell, alignment)| {
+            let style = match alignment {
+                Alignment::Left => " style=\"text-align: left\"",
+                Alignment::Center => " style=\"text-align: center\"",
+                Alignment::Right => " style=\"text-align: right\"",
+                Alignment::None => "",
+            };
+            format!("<{}{}>{}</{}>", tag, style, cell.to_html(), tag)
+        })
+        .collect::<String>();
+
+    format!("<tr>{}</tr>\n", cells_html)
+}
+
 impl ToHtml for ListType {
     fn to_html(&self) -> String {
-        let (tag, items) = match self {
-            ListType::Unordered(items) => ("ul", items),
-            ListType::Ordered(items) => ("ol", items),
+        let (open_tag, tag, items, loose) = match self {
+            ListType::Unordered { items, loose } => ("<ul>".to_string(), "ul", items, *loose),
+            ListType::Ordered {
+                start: 1,
+                items,
+                loose,
+            } => ("<ol>".to_string(), "ol", items, *loose),
+            ListType::Ordered {
+                start,
+                items,
+                loose,
+            } => (format!("<ol start=\"{}\">", start), "ol", items, *loose),
         };
 
-        if items.is_empty() {
-            return String::new();
-        }
-
-        let initial_indent = items[0].indent;
+        let items_html = items
+            .iter()
+            .map(|item| list_item_html(item, loose))
+            .collect::<String>();
 
-        let (mut html, _) = items.iter().fold(
-            (format!("<{}>", tag), initial_indent),
-            |(mut html, last_indent), item| {
-                if item.indent > last_indent {
-                    html.push_str(&format!("\n<{}>", tag));
-                } else if item.indent < last_indent {
-                    html.push_str(&format!("</{}>", tag));
-                }
+        format!("{}\n{}</{}>", open_tag, items_html, tag)
+    }
+}
 
-                html.push_str(&item.to_html());
+// tightなリストでは項目内の段落を<p>で囲まない
+fn list_item_html(item: &ListItem, loose: bool) -> String {
+    let checkbox_html = match item.checked {
+        Some(true) => "<input type=\"checkbox\" checked disabled> ",
+        Some(false) => "<input type=\"checkbox\" disabled> ",
+        None => "",
+    };
 
-                (html, item.indent)
-            },
-        );
+    let content_html = item
+        .blocks
+        .iter()
+        .map(|block| match block {
+            Block::Paragraph(inlines) if !loose => inlines.to_html(),
+            block => block.to_html(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
 
-        html.push_str(&format!("</{}>", tag));
-        html
-    }
+    let id_attribute = item
+        .id
+        .as_ref()
+        .map_or(String::new(), |id| format!(" id=\"{}\"", escape_html(id)));
+    format!(
+        "<li{}>{}{}</li>\n",
+        id_attribute, checkbox_html, content_html
+    )
 }
 
-impl ToHtml for ListItem {
-    fn to_html(&self) -> String {
-        let checkbox_html = match self.checked {
-            Some(true) => "<input type=\"checkbox\" checked disabled> ",
-            Some(false) => "<input type=\"checkbox\" disabled> ",
-            None => "",
-        };
+fn footnote_html(number: usize, references: usize, blocks: &[Block]) -> String {
+    let backrefs = (1..=references)
+        .map(|occurrence| {
+            let ref_id = if occurrence > 1 {
+                format!("fnref-{}-{}", number, occurrence)
+            } else {
+                format!("fnref-{}", number)
+            };
+            format!("<a href=\"#{}\" class=\"footnote-backref\">↩</a>", ref_id)
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    // 戻りリンクは最後の段落の中に入れる
+    let content = match blocks.split_last() {
+        Some((Block::Paragraph(inlines), init)) => format!(
+            "{}<p>{} {}</p>",
+            init.iter().map(|b| b.to_html()).collect::<String>(),
+            inlines.to_html(),
+            backrefs
+        ),
+        _ => format!(
+            "{}{}",
+            blocks.iter().map(|b| b.to_html()).collect::<String>(),
+            backrefs
+        ),
+    };
+
+    format!("<li id=\"fn-{}\">{}</li>\n", number, content)
+}
 
-        format!("<li>{}{}\n</li>", checkbox_html, self.content.to_html())
+// 採番された脚注定義を (番号, 参照回数, 中身) で集める。埋め込んだノートの定義も含む
+fn collect_footnotes<'b>(blocks: &'b [Block], footnotes: &mut Vec<(usize, usize, &'b [Block])>) {
+    for block in blocks {
+        match block {
+            Block::FootnoteDefinition {
+                number: Some(n),
+                references,
+                blocks,
+                ..
+            } => footnotes.push((*n, *references, blocks)),
+            Block::Blockquote(blocks)
+            | Block::Callout { blocks, .. }
+            | Block::Transclusion { blocks, .. } => collect_footnotes(blocks, footnotes),
+            Block::Identified { block, .. } => {
+                collect_footnotes(std::slice::from_ref(block.as_ref()), footnotes)
+            }
+            Block::List(list) => list
+                .items()
+                .iter()
+                .for_each(|item| collect_footnotes(&item.blocks, footnotes)),
+            _ => {}
+        }
     }
 }
 
 impl ToHtml for Document {
     fn to_html(&self) -> String {
-        self.blocks.iter().map(|b| b.to_html()).collect::<String>()
+        let body = self.blocks.iter().map(|b| b.to_html()).collect::<String>();
+
+        let mut footnotes = Vec::new();
+        collect_footnotes(&self.blocks, &mut footnotes);
+
+        if footnotes.is_empty() {
+            return body;
+        }
+
+        footnotes.sort_by_key(|(n, _, _)| *n);
+        let items = footnotes
+            .into_iter()
+            .map(|(n, references, blocks)| footnote_html(n, references, blocks))
+            .collect::<String>();
+
+        format!(
+            "{}<section class=\"footnotes\">\n<hr />\n<ol>\n{}</ol>\n</section>",
+            body, items
+        )
     }
 }
@@ -0,0 +1,461 @@
+use crate::html::slug::slugify;
+use crate::html::wikilink::{LinkIndex, fragment_anchor};
+use crate::parser::document::ast::{Block, Document, Embed, Inline, MdDocument};
+use std::collections::HashSet;
+
+// ノートの埋め込み `![[Note]]` `![[Note#Section]]` を埋め込み先の本文で置き換える
+pub struct EmbedExpander<'a> {
+    // 索引と同じ並びの、展開前の本文
+    sources: Vec<Option<Document>>,
+    index: &'a LinkIndex,
+    max_depth: usize,
+}
+
+fn embed_title(embed: &Embed) -> String {
+    match (&embed.label, &embed.fragment) {
+        (Some(label), _) => label.clone(),
+        (None, Some(fragment)) => format!("{}#{}", embed.target, fragment),
+        (None, None) => embed.target.clone(),
+    }
+}
+
+// 展開できない埋め込みはWikiリンクとして残す
+fn embed_link(embed: &Embed) -> Inline {
+    Inline::WikiLink {
+        target: embed.target.clone(),
+        fragment: embed.fragment.clone(),
+        text: embed_title(embed),
+    }
+}
+
+// リストの項目やコールアウトなど、ブロックの中に入れ子になったブロックの並び
+fn nested_blocks(block: &Block) -> Vec<&[Block]> {
+    match block {
+        Block::Blockquote(blocks)
+        | Block::Callout { blocks, .. }
+        | Block::FootnoteDefinition { blocks, .. } => vec![blocks],
+        Block::List(list) => list.items().iter().map(|item| &item.blocks[..]).collect(),
+        _ => Vec::new(),
+    }
+}
+
+// 見出し `Heading` の節、またはブロックID `^id` のブロックを取り出す
+fn select_fragment(blocks: &[Block], fragment: &str) -> Option<Vec<Block>> {
+    let last = fragment.rsplit('#').next().unwrap_or(fragment).trim();
+    if let Some(id) = last.strip_prefix('^') {
+        return select_block(blocks, &format!("^{}", id));
+    }
+
+    select_section(blocks, &slugify(last))
+}
+
+// 見出しから、同じかそれより上のレベルの見出しの手前まで
+// 入れ子の中の見出しなら、その入れ子の終わりまで
+fn select_section(blocks: &[Block], slug: &str) -> Option<Vec<Block>> {
+    let start = blocks.iter().position(|block| {
+        matches!(block, Block::Heading { content, .. } if slugify(&Inline::plain_text(content)) == slug)
+    });
+    let Some(start) = start else {
+        return blocks
+            .iter()
+            .flat_map(nested_blocks)
+            .find_map(|blocks| select_section(blocks, slug));
+    };
+    let Block::Heading { level, .. } = &blocks[start] else {
+        return None;
+    };
+    let end = blocks[start + 1..]
+        .iter()
+        .position(|block| matches!(block, Block::Heading { level: l, .. } if l <= level))
+        .map_or(blocks.len(), |i| start + 1 + i);
+
+    Some(blocks[start..end].to_vec())
+}
+
+// 段落が目印 `^id` で終わっていれば、目印を除いた段落を返す
+fn strip_block_marker(inlines: &[Inline], marker: &str) -> Option<Vec<Inline>> {
+    let text = Inline::plain_text(inlines);
+    if !text.trim_end().ends_with(&format!(" {}", marker)) {
+        return None;
+    }
+    // 目印は複数のTextにまたがっていることがある
+    let mut inlines = inlines.to_vec();
+    Inline::strip_text_suffix(&mut inlines, marker);
+
+    Some(inlines)
+}
+
+// `^id` で終わる段落、1つ目の段落が `^id` で終わるリストの項目、
+// または `^id` だけの段落の直前のブロック。入れ子の中も探す
+fn select_block(blocks: &[Block], marker: &str) -> Option<Vec<Block>> {
+    for (i, block) in blocks.iter().enumerate() {
+        match block {
+            Block::Paragraph(inlines) => {
+                if i > 0 && Inline::plain_text(inlines).trim_end() == marker {
+                    return Some(vec![blocks[i - 1].clone()]);
+                }
+                if let Some(inlines) = strip_block_marker(inlines, marker) {
+                    return Some(vec![Block::Paragraph(inlines)]);
+                }
+            }
+            Block::List(list) => {
+                for item in list.items() {
+                    if let Some(Block::Paragraph(inlines)) = item.blocks.first()
+                        && let Some(inlines) = strip_block_marker(inlines, marker)
+                    {
+                        let mut blocks = item.blocks.clone();
+                        blocks[0] = Block::Paragraph(inlines);
+                        return Some(blocks);
+                    }
+                    if let Some(found) = select_block(&item.blocks, marker) {
+                        return Some(found);
+                    }
+                }
+            }
+            _ => {
+                if let Some(found) = nested_blocks(block)
+                    .into_iter()
+                    .find_map(|blocks| select_block(blocks, marker))
+                {
+                    return Some(found);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+// 埋め込んだノートの脚注が埋め込み先の同じラベルの脚注と混ざらないよう、
+// 定義のあるラベルに埋め込みごとの接頭辞を付ける。節だけを埋め込んだときは、
+// 参照している定義をノートから持ち込む
+fn scope_footnotes(blocks: &mut Vec<Block>, source: &[Block], scope: usize) {
+    let mut defined = HashSet::new();
+    let mut referenced = HashSet::new();
+    for block in blocks.iter_mut() {
+        block.walk_blocks_mut(&mut |block| {
+            if let Block::FootnoteDefinition { label, .. } = block {
+                defined.insert(label.clone());
+            }
+        });
+        block.walk_inlines_mut(&mut |inline| {
+            if let Inline::FootnoteRef { label, .. } = inline {
+                referenced.insert(label.clone());
+            }
+        });
+    }
+    for block in source {
+        if let Block::FootnoteDefinition { label, .. } = block
+            && referenced.contains(label)
+            && defined.insert(label.clone())
+        {
+            blocks.push(block.clone());
+        }
+    }
+
+    let scoped = |label: &str| format!("embed-{}:{}", scope, label);
+    for block in blocks.iter_mut() {
+        block.walk_blocks_mut(&mut |block| {
+            if let Block::FootnoteDefinition { label, .. } = block {
+                *label = scoped(label);
+            }
+        });
+        block.walk_inlines_mut(&mut |inline| {
+            if let Inline::FootnoteRef { label, .. } = inline
+                && defined.contains(label.as_str())
+            {
+                *label = scoped(label);
+            }
+        });
+    }
+}
+
+impl<'a> EmbedExpander<'a> {
+    // docsは索引を作ったときと同じ並び
+    pub fn new(docs: &[MdDocument], index: &'a LinkIndex, max_depth: usize) -> Self {
+        EmbedExpander {
+            sources: docs.iter().map(|doc| doc.body.clone()).collect(),
+            index,
+            max_depth,
+        }
+    }
+
+    // currentは索引でのdocの番号。循環の検出に使う
+    pub fn expand(&self, doc: &mut Document, current: usize) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let mut stack = vec![current];
+        let mut scopes = 0;
+        self.expand_blocks(&mut doc.blocks, &mut stack, &mut scopes, &mut warnings);
+
+        warnings
+    }
+
+    fn expand_blocks(
+        &self,
+        blocks: &mut [Block],
+        stack: &mut Vec<usize>,
+        scopes: &mut usize,
+        warnings: &mut Vec<String>,
+    ) {
+        for block in blocks.iter_mut() {
+            block.walk_blocks_mut(&mut |block| {
+                if let Block::Embed(embed) = block
+                    && !embed.is_asset()
+                {
+                    let embed = embed.clone();
+                    *block = self.expand_embed(&embed, stack, scopes, warnings);
+                }
+            });
+            // 文中の埋め込みはノートへのリンクにする
+            block.walk_inlines_mut(&mut |inline| {
+                if let Inline::Embed(embed) = inline
+                    && !embed.is_asset()
+                {
+                    *inline = embed_link(embed);
+                }
+            });
+        }
+    }
+
+    fn expand_embed(
+        &self,
+        embed: &Embed,
+        stack: &mut Vec<usize>,
+        scopes: &mut usize,
+        warnings: &mut Vec<String>,
+    ) -> Block {
+        let link = Block::Paragraph(vec![embed_link(embed)]);
+        // 見つからない埋め込み先はWikiリンクの解決で警告する
+        let Some(target) = self.index.find(&embed.target) else {
+            return link;
+        };
+        let Some(source) = &self.sources[target] else {
+            return link;
+        };
+
+        if stack.contains(&target) {
+            warnings.push(format!("embed cycle detected at ![[{}]]", embed.target));
+            return link;
+        }
+        if stack.len() > self.max_depth {
+            warnings.push(format!(
+                "embeds nested deeper than {} at ![[{}]]",
+                self.max_depth, embed.target
+            ));
+            return link;
+        }
+
+        let mut blocks = match &embed.fragment {
+            Some(fragment) => match select_fragment(&source.blocks, fragment) {
+                Some(blocks) => blocks,
+                None => {
+                    warnings.push(format!(
+                        "section #{} not found in ![[{}]]",
+                        fragment, embed.target
+                    ));
+                    return link;
+                }
+            },
+            None => source.blocks.clone(),
+        };
+
+        stack.push(target);
+        self.expand_blocks(&mut blocks, stack, scopes, warnings);
+        stack.pop();
+        *scopes += 1;
+        scope_footnotes(&mut blocks, &source.blocks, *scopes);
+
+        let anchor = embed
+            .fragment
+            .as_deref()
+            .map(fragment_anchor)
+            .unwrap_or_default();
+
+        Block::Transclusion {
+            title: embed_title(embed),
+            url: format!("{}{}", self.index.url(target), anchor),
+            blocks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::html::convert::ToHtml;
+    use crate::html::footnote::number_footnotes;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_expand_embeds() {
+        let paths = ["a", "b", "c"].map(|name| PathBuf::from(format!("contents/{}.md", name)));
+        let docs = vec![
+            MdDocument::for_test(&paths[0], &[], "![[b#Two]]\n\n![[c#^id]]\n\nsee ![[b]]\n"),
+            MdDocument::for_test(
+                &paths[1],
+                &[],
+                "# One\n\n1\n\n## Two\n\n2\n\n![[a]]\n\n# Three\n",
+            ),
+            MdDocument::for_test(&paths[2], &[], "before\n\nthe block ^id\n"),
+        ];
+        let index = LinkIndex::new(&docs, &Config::default());
+        let expander = EmbedExpander::new(&docs, &index, 5);
+
+        let mut doc = docs[0].body.clone().unwrap();
+        let warnings = expander.expand(&mut doc, 0);
+
+        assert_eq!(warnings, vec!["embed cycle detected at ![[a]]".to_string()]);
+        match &doc.blocks[0] {
+            Block::Transclusion { title, url, blocks } => {
+                assert_eq!(title, "b#Two");
+                assert_eq!(url, "/b.html#two");
+                assert_eq!(blocks.len(), 3);
+                assert_eq!(
+                    blocks[2],
+                    Block::Paragraph(vec![Inline::WikiLink {
+                        target: "a".to_string(),
+                        fragment: None,
+                        text: "a".to_string(),
+                    }])
+                );
+            }
+            block => panic!("Expected Transclusion, got {:?}", block),
+        }
+        match &doc.blocks[1] {
+            Block::Transclusion { blocks, .. } => assert_eq!(
+                blocks,
+                &vec![Block::Paragraph(vec![Inline::Text(
+                    "the block".to_string()
+                )])]
+            ),
+            block => panic!("Expected Transclusion, got {:?}", block),
+        }
+        assert!(matches!(
+            &doc.blocks[2],
+            Block::Paragraph(inlines) if matches!(inlines[1], Inline::WikiLink { .. })
+        ));
+    }
+
+    #[test]
+    fn test_block_marker_across_texts() {
+        let paths = ["a", "b"].map(|name| PathBuf::from(format!("contents/{}.md", name)));
+        let docs = vec![
+            MdDocument::for_test(&paths[0], &[], "foo ^x)\n"),
+            MdDocument::for_test(&paths[1], &[], "![[a#^x)]]\n"),
+        ];
+        let index = LinkIndex::new(&docs, &Config::default());
+        let expander = EmbedExpander::new(&docs, &index, 5);
+
+        let mut doc = docs[1].body.clone().unwrap();
+        assert!(expander.expand(&mut doc, 1).is_empty());
+        match &doc.blocks[0] {
+            Block::Transclusion { blocks, .. } => assert_eq!(
+                blocks,
+                &vec![Block::Paragraph(vec![Inline::Text("foo".to_string())])]
+            ),
+            block => panic!("Expected Transclusion, got {:?}", block),
+        }
+    }
+
+    #[test]
+    fn test_embed_nested_targets() {
+        let paths = ["src", "host"].map(|name| PathBuf::from(format!("contents/{}.md", name)));
+        let docs = vec![
+            MdDocument::for_test(
+                &paths[0],
+                &[],
+                concat!(
+                    "- one ^li1\n  - child\n- two\n\n",
+                    "> [!note] Box\n> ## Inner\n> inside\n>\n> quoted ^q1\n\n",
+                    "# After\n",
+                ),
+            ),
+            MdDocument::for_test(
+                &paths[1],
+                &[],
+                "![[src#^li1]]\n\n![[src#^q1]]\n\n![[src#Inner]]\n",
+            ),
+        ];
+        let index = LinkIndex::new(&docs, &Config::default());
+        let expander = EmbedExpander::new(&docs, &index, 5);
+
+        let mut doc = docs[1].body.clone().unwrap();
+        assert_eq!(expander.expand(&mut doc, 1), Vec::<String>::new());
+        let embedded: Vec<_> = doc
+            .blocks
+            .iter()
+            .map(|block| match block {
+                Block::Transclusion { blocks, .. } => blocks.clone(),
+                block => panic!("Expected Transclusion, got {:?}", block),
+            })
+            .collect();
+
+        assert_eq!(embedded[0].len(), 2);
+        assert_eq!(
+            embedded[0][0],
+            Block::Paragraph(vec![Inline::Text("one".to_string())])
+        );
+        assert!(matches!(embedded[0][1], Block::List(_)));
+        assert_eq!(
+            embedded[1],
+            vec![Block::Paragraph(vec![Inline::Text("quoted".to_string())])]
+        );
+        assert_eq!(embedded[2].len(), 3);
+        assert!(matches!(embedded[2][0], Block::Heading { level: 2, .. }));
+    }
+
+    #[test]
+    fn test_embed_footnote_scope() {
+        let paths =
+            ["host", "note", "part"].map(|name| PathBuf::from(format!("contents/{}.md", name)));
+        let docs = vec![
+            MdDocument::for_test(
+                &paths[0],
+                &[],
+                "host[^1]\n\n![[note]]\n\n![[part#Sec]]\n\n[^1]: host note\n",
+            ),
+            MdDocument::for_test(&paths[1], &[], "embedded[^1]\n\n[^1]: embedded note\n"),
+            MdDocument::for_test(
+                &paths[2],
+                &[],
+                "# Sec\n\nsection[^1]\n\n# Other\n\n[^1]: section note\n",
+            ),
+        ];
+        let index = LinkIndex::new(&docs, &Config::default());
+        let expander = EmbedExpander::new(&docs, &index, 5);
+
+        let mut doc = docs[0].body.clone().unwrap();
+        assert!(expander.expand(&mut doc, 0).is_empty());
+        assert!(number_footnotes(&mut doc).is_empty());
+        let html = doc.to_html();
+
+        for (n, text) in [(1, "host"), (2, "embedded"), (3, "section")] {
+            assert!(html.contains(&format!(
+                "{}<sup class=\"footnote-ref\"><a href=\"#fn-{}\"",
+                text, n
+            )));
+            assert!(html.contains(&format!("<li id=\"fn-{}\"><p>{} note ", n, text)));
+        }
+    }
+
+    #[test]
+    fn test_expand_embeds_max_depth() {
+        let paths = ["a", "b", "c"].map(|name| PathBuf::from(format!("contents/{}.md", name)));
+        let docs = vec![
+            MdDocument::for_test(&paths[0], &[], "![[b]]\n"),
+            MdDocument::for_test(&paths[1], &[], "![[c]]\n"),
+            MdDocument::for_test(&paths[2], &[], "end\n"),
+        ];
+        let index = LinkIndex::new(&docs, &Config::default());
+        let expander = EmbedExpander::new(&docs, &index, 1);
+
+        let mut doc = docs[0].body.clone().unwrap();
+        let warnings = expander.expand(&mut doc, 0);
+
+        assert_eq!(
+            warnings,
+            vec!["embeds nested deeper than 1 at ![[c]]".to_string()]
+        );
+    }
+}
@@ -0,0 +1,489 @@
+use crate::config::Config;
+use crate::html::index::sort_posts;
+use crate::html::tags::{TAGS_PATH, collect_tags, tag_url};
+use crate::html::to_html::HTMLDocument;
+use crate::parser::document::front_matter::Date;
+use clap::ValueEnum;
+use percent_encoding::{AsciiSet, CONTROLS, utf8_percent_encode};
+
+// フィードに書くURLでエンコードする文字。ASCII以外の文字は常にエンコードされる
+const URL_ENCODE_SET: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'<').add(b'>').add(b'`');
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum FeedFormat {
+    // feed.xml
+    Atom,
+    // rss.xml
+    Rss,
+}
+
+impl FeedFormat {
+    pub fn file_name(self) -> &'static str {
+        match self {
+            FeedFormat::Atom => "feed.xml",
+            FeedFormat::Rss => "rss.xml",
+        }
+    }
+
+    pub fn media_type(self) -> &'static str {
+        match self {
+            FeedFormat::Atom => "application/atom+xml",
+            FeedFormat::Rss => "application/rss+xml",
+        }
+    }
+}
+
+// フィードの各エントリーに入れるもの
+#[derive(Debug, Clone, Copy, PartialEq, Default, ValueEnum)]
+pub enum FeedContent {
+    // 本文のHTML
+    #[default]
+    Full,
+    // 一覧と同じ抜粋
+    Summary,
+}
+
+// XMLの文字データと属性値のエスケープ。XMLに書けない制御文字は捨てる
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() || c == '\u{fffe}' || c == '\u{ffff}' => {}
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+// 本文の `/` で始まるリンクと画像を絶対URLにする
+fn absolute_links(html: &str, base_url: &str) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(i) = ["href=\"/", "src=\"/"]
+        .iter()
+        .filter_map(|attr| rest.find(attr).map(|i| i + attr.len() - 1))
+        .min()
+    {
+        result.push_str(&rest[..i]);
+        // `//example.com` はそのまま
+        if !rest[i..].starts_with("//") {
+            result.push_str(base_url);
+        }
+        rest = &rest[i..];
+        result.push('/');
+        rest = &rest[1..];
+    }
+    result.push_str(rest);
+
+    result
+}
+
+pub fn absolute_url(config: &Config, url: &str) -> String {
+    format!(
+        "{}{}",
+        config.base_url,
+        utf8_percent_encode(url, URL_ENCODE_SET)
+    )
+}
+
+fn offset_of(date: &Date) -> i32 {
+    date.offset.unwrap_or(0)
+}
+
+// `2024-03-05T10:20:30+09:00`。時刻がなければ0時、タイムゾーンがなければUTCとみなす
+pub fn rfc3339(date: &Date) -> String {
+    let (hour, minute, second) = date.time.unwrap_or((0, 0, 0));
+    let offset = match offset_of(date) {
+        0 => "Z".to_string(),
+        offset => format!(
+            "{}{:02}:{:02}",
+            if offset < 0 { '-' } else { '+' },
+            offset.abs() / 60,
+            offset.abs() % 60
+        ),
+    };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}",
+        date.year, date.month, date.day, hour, minute, second, offset
+    )
+}
+
+// `Tue, 05 Mar 2024 10:20:30 +0900`
+fn rfc822(date: &Date) -> String {
+    let (hour, minute, second) = date.time.unwrap_or((0, 0, 0));
+    let offset = offset_of(date);
+    // 1970-01-01は木曜日
+    let weekday = (date.days_since_epoch() + 4).rem_euclid(7) as usize;
+
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} {}{:02}{:02}",
+        WEEKDAYS[weekday],
+        date.day,
+        MONTHS[(date.month as usize).clamp(1, 12) - 1],
+        date.year,
+        hour,
+        minute,
+        second,
+        if offset < 0 { '-' } else { '+' },
+        offset.abs() / 60,
+        offset.abs() % 60
+    )
+}
+
+struct Feed<'d, 'a> {
+    title: String,
+    // フィードが対象にしているページのURL
+    url: String,
+    // タグのフィードなら `/tags/rust/`
+    dir: String,
+    entries: Vec<&'d HTMLDocument<'a>>,
+}
+
+impl Feed<'_, '_> {
+    fn feed_url(&self, config: &Config, format: FeedFormat) -> String {
+        absolute_url(config, &format!("{}{}", self.dir, format.file_name()))
+    }
+
+    // 一番新しいエントリーの日付。エントリーがなければnow
+    fn updated(&self, now: Date) -> Date {
+        self.entries
+            .iter()
+            .filter_map(|doc| doc.date)
+            .max()
+            .unwrap_or(now)
+    }
+}
+
+fn entry_content(doc: &HTMLDocument, config: &Config) -> String {
+    match config.feed_content {
+        FeedContent::Full => escape_xml(&absolute_links(&doc.body, &config.base_url)),
+        FeedContent::Summary => escape_xml(&doc.excerpt),
+    }
+}
+
+fn atom(feed: &Feed, config: &Config, now: Date) -> String {
+    let author = [&config.author, &config.title, &config.base_url]
+        .into_iter()
+        .find(|name| !name.is_empty())
+        .map_or(String::new(), |name| escape_xml(name));
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str(&format!(
+        "<feed xmlns=\"http://www.w3.org/2005/Atom\" xml:lang=\"{}\">\n",
+        escape_xml(&config.language)
+    ));
+    xml.push_str(&format!("<title>{}</title>\n", escape_xml(&feed.title)));
+    xml.push_str(&format!("<link href=\"{}\"/>\n", escape_xml(&feed.url)));
+    xml.push_str(&format!(
+        "<link rel=\"self\" type=\"{}\" href=\"{}\"/>\n",
+        FeedFormat::Atom.media_type(),
+        escape_xml(&feed.feed_url(config, FeedFormat::Atom))
+    ));
+    xml.push_str(&format!("<id>{}</id>\n", escape_xml(&feed.url)));
+    xml.push_str(&format!(
+        "<updated>{}</updated>\n",
+        rfc3339(&feed.updated(now))
+    ));
+    xml.push_str(&format!("<author><name>{}</name></author>\n", author));
+
+    for doc in &feed.entries {
+        let url = escape_xml(&absolute_url(config, &doc.path));
+        xml.push_str("<entry>\n");
+        xml.push_str(&format!("<title>{}</title>\n", escape_xml(doc.title)));
+        xml.push_str(&format!("<link href=\"{}\"/>\n", url));
+        xml.push_str(&format!("<id>{}</id>\n", url));
+        xml.push_str(&format!(
+            "<updated>{}</updated>\n",
+            rfc3339(&doc.date.unwrap_or(now))
+        ));
+        for tag in doc.tags.iter().flatten() {
+            xml.push_str(&format!("<category term=\"{}\"/>\n", escape_xml(tag)));
+        }
+        match config.feed_content {
+            FeedContent::Full => xml.push_str(&format!(
+                "<content type=\"html\">{}</content>\n",
+                entry_content(doc, config)
+            )),
+            FeedContent::Summary => xml.push_str(&format!(
+                "<summary>{}</summary>\n",
+                entry_content(doc, config)
+            )),
+        }
+        xml.push_str("</entry>\n");
+    }
+    xml.push_str("</feed>\n");
+
+    xml
+}
+
+fn rss(feed: &Feed, config: &Config, now: Date) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str("<channel>\n");
+    xml.push_str(&format!("<title>{}</title>\n", escape_xml(&feed.title)));
+    xml.push_str(&format!("<link>{}</link>\n", escape_xml(&feed.url)));
+    xml.push_str(&format!(
+        "<description>{}</description>\n",
+        escape_xml(&feed.title)
+    ));
+    xml.push_str(&format!(
+        "<language>{}</language>\n",
+        escape_xml(&config.language)
+    ));
+    xml.push_str(&format!(
+        "<atom:link href=\"{}\" rel=\"self\" type=\"{}\"/>\n",
+        escape_xml(&feed.feed_url(config, FeedFormat::Rss)),
+        FeedFormat::Rss.media_type()
+    ));
+    xml.push_str(&format!(
+        "<lastBuildDate>{}</lastBuildDate>\n",
+        rfc822(&feed.updated(now))
+    ));
+
+    for doc in &feed.entries {
+        let url = escape_xml(&absolute_url(config, &doc.path));
+        xml.push_str("<item>\n");
+        xml.push_str(&format!("<title>{}</title>\n", escape_xml(doc.title)));
+        xml.push_str(&format!("<link>{}</link>\n", url));
+        xml.push_str(&format!("<guid isPermaLink=\"true\">{}</guid>\n", url));
+        xml.push_str(&format!(
+            "<pubDate>{}</pubDate>\n",
+            rfc822(&doc.date.unwrap_or(now))
+        ));
+        for tag in doc.tags.iter().flatten() {
+            xml.push_str(&format!("<category>{}</category>\n", escape_xml(tag)));
+        }
+        xml.push_str(&format!(
+            "<description>{}</description>\n",
+            entry_content(doc, config)
+        ));
+        xml.push_str("</item>\n");
+    }
+    xml.push_str("</channel>\n</rss>\n");
+
+    xml
+}
+
+// noindexでないものを新しい順にlimit件。limitが0なら全件
+fn newest<'d, 'a>(
+    mut entries: Vec<&'d HTMLDocument<'a>>,
+    limit: usize,
+) -> Vec<&'d HTMLDocument<'a>> {
+    // 検索エンジンに載せないページはフィードにも流さない
+    entries.retain(|doc| !doc.noindex);
+    sort_posts(&mut entries);
+    if limit > 0 {
+        entries.truncate(limit);
+    }
+
+    entries
+}
+
+// サイト全体とタグごとのフィードを (出力するパス, XML) で返す。nowは日付のないエントリーに使う
+pub fn feed_files(docs: &[HTMLDocument], config: &Config, now: Date) -> Vec<(String, String)> {
+    let site_title = if config.title.is_empty() {
+        config.base_url.clone()
+    } else {
+        config.title.clone()
+    };
+
+    let mut feeds = vec![Feed {
+        title: site_title.clone(),
+        url: absolute_url(config, "/"),
+        dir: "/".to_string(),
+        entries: newest(docs.iter().collect(), config.feed_limit),
+    }];
+    for (key, tag) in collect_tags(docs) {
+        let entries = newest(
+            tag.docs.iter().map(|&i| &docs[i]).collect(),
+            config.feed_limit,
+        );
+        // noindexのページにしか付いていないタグのフィードは作らない
+        if entries.is_empty() {
+            continue;
+        }
+        feeds.push(Feed {
+            title: format!("#{} | {}", tag.name, site_title),
+            url: absolute_url(config, &tag_url(&tag.name).unwrap_or_default()),
+            dir: format!("{}{}/", TAGS_PATH, key.join("/")),
+            entries,
+        });
+    }
+
+    let mut files = Vec::new();
+    for feed in &feeds {
+        for format in &config.feed_formats {
+            let xml = match format {
+                FeedFormat::Atom => atom(feed, config, now),
+                FeedFormat::Rss => rss(feed, config, now),
+            };
+            files.push((format!("{}{}", feed.dir, format.file_name()), xml));
+        }
+    }
+
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32, time: Option<(u32, u32, u32)>, offset: Option<i32>) -> Date {
+        Date {
+            year: 2024,
+            month: 3,
+            day,
+            time,
+            offset,
+        }
+    }
+
+    fn config() -> Config {
+        Config {
+            title: "Site <1>".to_string(),
+            base_url: "https://example.com".to_string(),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(
+            escape_xml("<a href=\"x\">'&'</a>\u{1}\n"),
+            "&lt;a href=&quot;x&quot;&gt;&apos;&amp;&apos;&lt;/a&gt;\n"
+        );
+    }
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(rfc3339(&date(5, None, None)), "2024-03-05T00:00:00Z");
+        assert_eq!(
+            rfc3339(&date(5, Some((10, 20, 30)), Some(540))),
+            "2024-03-05T10:20:30+09:00"
+        );
+        assert_eq!(
+            rfc822(&date(5, Some((10, 20, 30)), Some(-330))),
+            "Tue, 05 Mar 2024 10:20:30 -0530"
+        );
+    }
+
+    #[test]
+    fn test_feed_files() {
+        let docs = vec![
+            HTMLDocument::for_test("old", vec!["rust"], Some(1)),
+            HTMLDocument {
+                body: "<p><a href=\"/x.html\">x</a><img src=\"//cdn/i.png\"></p>".to_string(),
+                ..HTMLDocument::for_test("new", vec![], Some(9))
+            },
+            HTMLDocument::for_test("日本語", vec!["日記"], Some(5)),
+        ];
+        let config = Config {
+            feed_limit: 2,
+            ..config()
+        };
+        let files = feed_files(&docs, &config, date(10, None, None));
+        let paths: Vec<&str> = files.iter().map(|(path, _)| path.as_str()).collect();
+
+        assert_eq!(
+            paths,
+            vec![
+                "/feed.xml",
+                "/rss.xml",
+                "/tags/rust/feed.xml",
+                "/tags/rust/rss.xml",
+                "/tags/日記/feed.xml",
+                "/tags/日記/rss.xml",
+            ]
+        );
+
+        let atom = &files[0].1;
+        assert!(atom.starts_with(concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+            "<feed xmlns=\"http://www.w3.org/2005/Atom\" xml:lang=\"ja\">\n",
+            "<title>Site &lt;1&gt;</title>\n",
+            "<link href=\"https://example.com/\"/>\n",
+            "<link rel=\"self\" type=\"application/atom+xml\" href=\"https://example.com/feed.xml\"/>\n",
+            "<id>https://example.com/</id>\n",
+            "<updated>2024-03-09T00:00:00Z</updated>\n",
+            "<author><name>Site &lt;1&gt;</name></author>\n",
+            "<entry>\n",
+            "<title>new</title>\n",
+            "<link href=\"https://example.com/new.html\"/>\n",
+        )));
+        assert!(atom.contains(concat!(
+            "<content type=\"html\">&lt;p&gt;&lt;a href=&quot;https://example.com/x.html&quot;&gt;",
+            "x&lt;/a&gt;&lt;img src=&quot;//cdn/i.png&quot;&gt;&lt;/p&gt;</content>\n"
+        )));
+        assert!(atom.contains("<id>https://example.com/%E6%97%A5%E6%9C%AC%E8%AA%9E.html</id>"));
+        assert!(!atom.contains("/old.html"));
+
+        let rss = &files[3].1;
+        assert!(rss.contains(
+            "<title>#rust | Site &lt;1&gt;</title>\n<link>https://example.com/tags/rust/</link>\n"
+        ));
+        assert!(
+            rss.contains("<atom:link href=\"https://example.com/tags/rust/rss.xml\" rel=\"self\"")
+        );
+        assert!(rss.contains(concat!(
+            "<item>\n",
+            "<title>old</title>\n",
+            "<link>https://example.com/old.html</link>\n",
+            "<guid isPermaLink=\"true\">https://example.com/old.html</guid>\n",
+            "<pubDate>Fri, 01 Mar 2024 00:00:00 +0000</pubDate>\n",
+            "<category>rust</category>\n",
+        )));
+    }
+
+    #[test]
+    fn test_feed_summary() {
+        let config = Config {
+            feed_formats: vec![FeedFormat::Atom],
+            feed_content: FeedContent::Summary,
+            ..config()
+        };
+        let doc = HTMLDocument {
+            excerpt: "A & B".to_string(),
+            ..HTMLDocument::for_test("a", vec![], Some(1))
+        };
+        let files = feed_files(&[doc], &config, date(10, None, None));
+
+        assert_eq!(files.len(), 1);
+        assert!(files[0].1.contains("<summary>A &amp; B</summary>\n"));
+        assert!(!files[0].1.contains("<content"));
+    }
+
+    #[test]
+    fn test_feed_noindex() {
+        let config = Config {
+            feed_formats: vec![FeedFormat::Atom],
+            ..config()
+        };
+        let docs = vec![
+            HTMLDocument::for_test("public", vec!["rust"], Some(1)),
+            HTMLDocument {
+                noindex: true,
+                ..HTMLDocument::for_test("hidden", vec!["rust", "secret"], Some(2))
+            },
+        ];
+        let files = feed_files(&docs, &config, date(10, None, None));
+        let paths: Vec<&str> = files.iter().map(|(path, _)| path.as_str()).collect();
+
+        assert_eq!(paths, vec!["/feed.xml", "/tags/rust/feed.xml"]);
+        assert!(files.iter().all(|(_, xml)| !xml.contains("/hidden.html")));
+        assert!(files[0].1.contains("/public.html"));
+    }
+}
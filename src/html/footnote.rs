@@ -0,0 +1,123 @@
+use crate::parser::document::ast::{Block, Document, Inline};
+use std::collections::{HashMap, HashSet};
+
+// 脚注を最初に参照された順に採番し、参照と定義に番号を書き込む
+// 定義のない参照・参照されない定義は警告として返す
+pub fn number_footnotes(doc: &mut Document) -> Vec<String> {
+    // 埋め込んだノートの定義(html::embed)は入れ子の中にある
+    let mut defined = HashSet::new();
+    for block in &mut doc.blocks {
+        block.walk_blocks_mut(&mut |block| {
+            if let Block::FootnoteDefinition { label, .. } = block {
+                defined.insert(label.clone());
+            }
+        });
+    }
+
+    // label -> (番号, 参照回数)
+    let mut numbers: HashMap<String, (usize, usize)> = HashMap::new();
+    let mut warnings = Vec::new();
+
+    for block in &mut doc.blocks {
+        block.walk_inlines_mut(&mut |inline| {
+            if let Inline::FootnoteRef {
+                label,
+                number,
+                occurrence,
+            } = inline
+            {
+                if !defined.contains(label.as_str()) {
+                    let warning = format!("footnote reference [^{}] has no definition", label);
+                    if !warnings.contains(&warning) {
+                        warnings.push(warning);
+                    }
+                    return;
+                }
+
+                let next_number = numbers.len() + 1;
+                let (n, count) = numbers.entry(label.clone()).or_insert((next_number, 0));
+                *count += 1;
+                *number = Some(*n);
+                *occurrence = *count;
+            }
+        });
+    }
+
+    for block in &mut doc.blocks {
+        block.walk_blocks_mut(&mut |block| {
+            if let Block::FootnoteDefinition {
+                label,
+                number,
+                references,
+                ..
+            } = block
+            {
+                match numbers.get(label.as_str()) {
+                    Some((n, count)) => {
+                        *number = Some(*n);
+                        *references = *count;
+                    }
+                    None => warnings.push(format!(
+                        "footnote [^{}] is defined but never referenced",
+                        label
+                    )),
+                }
+            }
+        });
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::document::parse_document;
+
+    #[test]
+    fn test_number_footnotes() {
+        let input = "Second[^b] then first[^a] and again[^b] and missing[^x]\n\n[^a]: A\n[^b]: B\n[^c]: C\n";
+        let (mut doc, _) = parse_document()(input).unwrap();
+
+        let warnings = number_footnotes(&mut doc);
+
+        assert_eq!(
+            warnings,
+            vec![
+                "footnote reference [^x] has no definition".to_string(),
+                "footnote [^c] is defined but never referenced".to_string(),
+            ]
+        );
+
+        let mut refs = Vec::new();
+        doc.blocks[0].walk_inlines_mut(&mut |inline| {
+            if let Inline::FootnoteRef {
+                label,
+                number,
+                occurrence,
+            } = inline
+            {
+                refs.push((label.clone(), *number, *occurrence));
+            }
+        });
+        assert_eq!(
+            refs,
+            vec![
+                ("b".to_string(), Some(1), 1),
+                ("a".to_string(), Some(2), 1),
+                ("b".to_string(), Some(1), 2),
+                ("x".to_string(), None, 0),
+            ]
+        );
+
+        match &doc.blocks[2] {
+            Block::FootnoteDefinition {
+                number, references, ..
+            } => {
+                assert_eq!(*number, Some(1));
+                assert_eq!(*references, 2);
+            }
+            _ => panic!("Expected FootnoteDefinition"),
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use crate::html::convert::escape_html;
+use crate::html::slug::slugify;
+use crate::parser::document::ast::{Block, Document, Inline};
+use std::collections::HashSet;
+
+// 目次の項目。childrenは直後にある、より深い見出し
+#[derive(Debug, Clone, PartialEq)]
+pub struct TocEntry {
+    pub level: u8,
+    pub id: String,
+    pub title: String,
+    pub children: Vec<TocEntry>,
+}
+
+// コールアウトやリスト、埋め込んだノートの中も含め、すべての見出しにidを付ける
+// 同じslugの2つ目からは `-1` `-2` を付けて、ページ全体で重複させない
+// anchorsがtrueなら見出しの末尾にそのidへのリンクを置く
+pub fn assign_heading_ids(doc: &mut Document, anchors: bool) {
+    let mut used = HashSet::new();
+    let mut assign = |block: &mut Block| {
+        let Block::Heading { content, id, .. } = block else {
+            return;
+        };
+
+        let mut slug = slugify(&Inline::plain_text(content));
+        if slug.is_empty() {
+            slug = "section".to_string();
+        }
+        let mut unique = slug.clone();
+        let mut n = 1;
+        while used.contains(&unique) {
+            unique = format!("{}-{}", slug, n);
+            n += 1;
+        }
+        used.insert(unique.clone());
+
+        if anchors {
+            content.push(Inline::Html(format!(
+                "<a class=\"heading-anchor\" href=\"#{}\" aria-hidden=\"true\">#</a>",
+                escape_html(&unique)
+            )));
+        }
+        *id = Some(unique);
+    };
+
+    for block in &mut doc.blocks {
+        block.walk_blocks_mut(&mut assign);
+    }
+}
+
+fn push_entry(entries: &mut Vec<TocEntry>, entry: TocEntry) {
+    match entries.last_mut() {
+        Some(last) if last.level < entry.level => push_entry(&mut last.children, entry),
+        _ => entries.push(entry),
+    }
+}
+
+// 本文の最上位にある見出しのうち、min_levelからmax_levelまでで目次を作る
+// コールアウトや埋め込んだノートの中の見出しは目次に入れない
+pub fn build_toc(doc: &Document, min_level: u8, max_level: u8) -> Vec<TocEntry> {
+    let mut toc = Vec::new();
+    for block in &doc.blocks {
+        if let Block::Heading {
+            level,
+            content,
+            id: Some(id),
+        } = block
+            && (min_level..=max_level).contains(level)
+        {
+            push_entry(
+                &mut toc,
+                TocEntry {
+                    level: *level,
+                    id: id.clone(),
+                    title: Inline::plain_text(content).trim().to_string(),
+                    children: Vec::new(),
+                },
+            );
+        }
+    }
+
+    toc
+}
+
+fn toc_list_html(entries: &[TocEntry]) -> String {
+    let items: String = entries
+        .iter()
+        .map(|entry| {
+            let children = if entry.children.is_empty() {
+                String::new()
+            } else {
+                toc_list_html(&entry.children)
+            };
+            format!(
+                "<li><a href=\"#{}\">{}</a>{}</li>",
+                escape_html(&entry.id),
+                escape_html(&entry.title),
+                children
+            )
+        })
+        .collect();
+
+    format!("<ul>{}</ul>", items)
+}
+
+pub fn toc_html(toc: &[TocEntry]) -> String {
+    if toc.is_empty() {
+        return String::new();
+    }
+
+    format!("<nav class=\"toc\">{}</nav>", toc_list_html(toc))
+}
+
+fn is_toc_marker(block: &Block) -> bool {
+    match block {
+        Block::Paragraph(content) => {
+            content
+                .iter()
+                .all(|inline| matches!(inline, Inline::Text(_)))
+                && Inline::plain_text(content).trim() == "[TOC]"
+        }
+        _ => false,
+    }
+}
+
+// `[TOC]` だけの段落を目次にする。markerがなくてもat_topなら本文の先頭に置く
+pub fn insert_toc(doc: &mut Document, toc: &[TocEntry], at_top: bool) {
+    let html = toc_html(toc);
+    let mut found = false;
+    doc.blocks.retain_mut(|block| {
+        if !is_toc_marker(block) {
+            return true;
+        }
+        found = true;
+        *block = Block::Html(html.clone());
+        !html.is_empty()
+    });
+
+    if !found && at_top && !html.is_empty() {
+        doc.blocks.insert(0, Block::Html(html));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::convert::ToHtml;
+    use crate::parser::document::parse_document;
+
+    fn parse(input: &str) -> Document {
+        parse_document()(input).unwrap().0
+    }
+
+    #[test]
+    fn test_assign_heading_ids() {
+        let mut doc = parse("# Intro\n## 概要 と目的\n## Intro\n## Intro\n### ???\n");
+        assign_heading_ids(&mut doc, false);
+        let ids: Vec<_> = doc
+            .blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Heading { id, .. } => id.clone(),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            ids,
+            vec!["intro", "概要-と目的", "intro-1", "intro-2", "section"]
+        );
+        assert_eq!(doc.blocks[0].to_html(), "<h1 id=\"intro\">Intro</h1>");
+
+        let mut doc = parse("## A & B\n");
+        assign_heading_ids(&mut doc, true);
+        assert_eq!(
+            doc.blocks[0].to_html(),
+            "<h2 id=\"a-b\">A &amp; B<a class=\"heading-anchor\" href=\"#a-b\" aria-hidden=\"true\">#</a></h2>"
+        );
+    }
+
+    #[test]
+    fn test_assign_nested_heading_ids() {
+        let mut doc = parse("# Note\n\n> [!note]\n> # Note\n\n- item\n\n  ## Deep\n");
+        assign_heading_ids(&mut doc, false);
+
+        let mut ids = Vec::new();
+        for block in &mut doc.blocks {
+            block.walk_blocks_mut(&mut |block| {
+                if let Block::Heading { id, .. } = block {
+                    ids.push(id.clone());
+                }
+            });
+        }
+        assert_eq!(
+            ids,
+            vec![
+                Some("note".to_string()),
+                Some("note-1".to_string()),
+                Some("deep".to_string())
+            ]
+        );
+        assert_eq!(build_toc(&doc, 1, 6).len(), 1);
+    }
+
+    #[test]
+    fn test_build_toc() {
+        let mut doc = parse("# Title\n## A\n#### A1\n### A2\n## B\n###### Deep\n");
+        assign_heading_ids(&mut doc, true);
+        let toc = build_toc(&doc, 2, 4);
+
+        assert_eq!(toc.len(), 2);
+        assert_eq!(toc[0].title, "A");
+        assert_eq!(
+            toc[0]
+                .children
+                .iter()
+                .map(|entry| entry.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a1", "a2"]
+        );
+        assert!(toc[1].children.is_empty());
+        assert_eq!(
+            toc_html(&toc),
+            concat!(
+                "<nav class=\"toc\"><ul>",
+                "<li><a href=\"#a\">A</a><ul><li><a href=\"#a1\">A1</a></li><li><a href=\"#a2\">A2</a></li></ul></li>",
+                "<li><a href=\"#b\">B</a></li>",
+                "</ul></nav>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_insert_toc() {
+        let mut doc = parse("Intro\n\n[TOC]\n\n## A\n");
+        assign_heading_ids(&mut doc, false);
+        let toc = build_toc(&doc, 1, 6);
+        insert_toc(&mut doc, &toc, true);
+        assert_eq!(
+            doc.blocks[1],
+            Block::Html(
+                "<nav class=\"toc\"><ul><li><a href=\"#a\">A</a></li></ul></nav>".to_string()
+            )
+        );
+
+        let mut doc = parse("Intro\n\n## A\n");
+        assign_heading_ids(&mut doc, false);
+        let toc = build_toc(&doc, 1, 6);
+        insert_toc(&mut doc, &toc, true);
+        assert!(matches!(&doc.blocks[0], Block::Html(html) if html.starts_with("<nav")));
+
+        // 見出しがなければ `[TOC]` は消す
+        let mut doc = parse("[TOC]\n\nText\n");
+        insert_toc(&mut doc, &[], false);
+        assert_eq!(doc.blocks.len(), 1);
+    }
+}
@@ -0,0 +1,439 @@
+pub mod languages;
+pub mod lexer;
+
+use crate::html::convert::escape_html;
+use crate::html::highlight::languages::find_language;
+use crate::html::highlight::lexer::{Token, TokenKind, tokenize};
+use crate::parser::document::ast::{Block, Document};
+use clap::ValueEnum;
+
+// コードブロックの色の付け方
+#[derive(Debug, Clone, Copy, PartialEq, Default, ValueEnum)]
+pub enum HighlightMode {
+    // `hl-keyword` のようなクラスを付け、色はhighlight.cssで指定する
+    #[default]
+    Class,
+    // style属性で色を指定する
+    Inline,
+    // 色を付けない
+    None,
+}
+
+// クラスを使うときのスタイルシートの出力先
+pub const HIGHLIGHT_CSS_PATH: &str = "/highlight.css";
+
+// (種類, CSSの宣言)
+const THEME: &[(TokenKind, &str)] = &[
+    (TokenKind::Comment, "color: #6a737d; font-style: italic"),
+    (TokenKind::String, "color: #032f62"),
+    (TokenKind::Number, "color: #005cc5"),
+    (TokenKind::Keyword, "color: #d73a49"),
+    (TokenKind::Type, "color: #6f42c1"),
+    (TokenKind::Literal, "color: #005cc5"),
+    (TokenKind::Function, "color: #6f42c1"),
+    (TokenKind::Property, "color: #005cc5"),
+    (TokenKind::Variable, "color: #e36209"),
+    (TokenKind::Tag, "color: #22863a"),
+    (TokenKind::Attribute, "color: #6f42c1"),
+    (TokenKind::Meta, "color: #735c0f"),
+];
+
+impl TokenKind {
+    pub fn class(self) -> &'static str {
+        match self {
+            TokenKind::Comment => "hl-comment",
+            TokenKind::String => "hl-string",
+            TokenKind::Number => "hl-number",
+            TokenKind::Keyword => "hl-keyword",
+            TokenKind::Type => "hl-type",
+            TokenKind::Literal => "hl-literal",
+            TokenKind::Function => "hl-function",
+            TokenKind::Property => "hl-property",
+            TokenKind::Variable => "hl-variable",
+            TokenKind::Tag => "hl-tag",
+            TokenKind::Attribute => "hl-attribute",
+            TokenKind::Meta => "hl-meta",
+        }
+    }
+
+    fn style(self) -> &'static str {
+        THEME
+            .iter()
+            .find(|(kind, _)| *kind == self)
+            .map_or("", |(_, style)| style)
+    }
+}
+
+// HighlightMode::Classのときに出力するスタイルシート
+pub fn theme_css() -> String {
+    THEME
+        .iter()
+        .map(|(kind, style)| format!(".{} {{ {}; }}\n", kind.class(), style))
+        .collect()
+}
+
+// `3-5,8` を (3, 5), (8, 8) にする
+fn parse_line_ranges(ranges: &str) -> Option<Vec<(usize, usize)>> {
+    ranges
+        .split(',')
+        .map(|range| {
+            let (start, end) = range.split_once('-').unwrap_or((range, range));
+            let start = start.trim().parse::<usize>().ok()?;
+            let end = end.trim().parse::<usize>().ok()?;
+            (start >= 1 && start <= end).then_some((start, end))
+        })
+        .collect()
+}
+
+// 色を付けた1行分のHTML。トークンが複数行にまたがるときは行ごとにspanを閉じる
+fn highlight_lines(tokens: &[Token], mode: HighlightMode) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    for token in tokens {
+        for (i, piece) in token.text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(String::new());
+            }
+            if piece.is_empty() {
+                continue;
+            }
+            let line = lines.last_mut().expect("lines is not empty");
+            match (token.kind, mode) {
+                (Some(kind), HighlightMode::Class) => line.push_str(&format!(
+                    "<span class=\"{}\">{}</span>",
+                    kind.class(),
+                    escape_html(piece)
+                )),
+                (Some(kind), HighlightMode::Inline) => line.push_str(&format!(
+                    "<span style=\"{}\">{}</span>",
+                    kind.style(),
+                    escape_html(piece)
+                )),
+                _ => line.push_str(&escape_html(piece)),
+            }
+        }
+    }
+
+    lines
+}
+
+// 行ごとの表示の指定
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LineOptions {
+    pub line_numbers: bool,
+    // 強調する行の範囲。1行目が1
+    pub highlighted: Vec<(usize, usize)>,
+    // 行頭の `+` `-` を追加と削除の行として表示する
+    pub diff: bool,
+}
+
+impl LineOptions {
+    fn is_empty(&self) -> bool {
+        !self.line_numbers && self.highlighted.is_empty() && !self.diff
+    }
+}
+
+// 差分の行頭の記号を取り除く。記号は行ごとに返す
+fn split_diff_markers(code: &str) -> (String, Vec<Option<char>>) {
+    let mut stripped = String::new();
+    let mut markers = Vec::new();
+    for (i, line) in code.split('\n').enumerate() {
+        if i > 0 {
+            stripped.push('\n');
+        }
+        let marker = line.chars().next().filter(|c| matches!(c, '+' | '-' | ' '));
+        stripped.push_str(&line[marker.map_or(0, char::len_utf8)..]);
+        markers.push(marker);
+    }
+
+    (stripped, markers)
+}
+
+// `<code>` の中身のHTML。色も行ごとの表示もなければNoneで、そのまま出力される
+pub fn highlight_code(
+    code: &str,
+    language: Option<&str>,
+    mode: HighlightMode,
+    options: &LineOptions,
+) -> Option<String> {
+    let language = language
+        .and_then(find_language)
+        .filter(|_| mode != HighlightMode::None);
+    if language.is_none() && options.is_empty() {
+        return None;
+    }
+
+    let code = code.strip_suffix('\n').unwrap_or(code);
+    // 差分の記号を除いてから色を付ける
+    let (code, markers) = if options.diff {
+        split_diff_markers(code)
+    } else {
+        (code.to_string(), Vec::new())
+    };
+    let tokens = match language {
+        Some(language) => tokenize(&code, language),
+        None => vec![Token {
+            kind: None,
+            text: &code,
+        }],
+    };
+    let lines = highlight_lines(&tokens, mode);
+    if options.is_empty() {
+        return Some(lines.iter().map(|line| format!("{}\n", line)).collect());
+    }
+
+    let html = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let number = i + 1;
+            let marker = markers.get(i).copied().flatten();
+            let mut class = "code-line".to_string();
+            match marker {
+                Some('+') => class.push_str(" added"),
+                Some('-') => class.push_str(" removed"),
+                _ => {}
+            }
+            if options
+                .highlighted
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&number))
+            {
+                class.push_str(" highlighted");
+            }
+
+            let mut prefix = String::new();
+            if options.line_numbers {
+                prefix.push_str(&format!("<span class=\"line-number\">{}</span>", number));
+            }
+            if let Some(marker) = marker {
+                prefix.push_str(&format!("<span class=\"diff-marker\">{}</span>", marker));
+            }
+            format!("<span class=\"{}\">{}{}\n</span>", class, prefix, line)
+        })
+        .collect();
+
+    Some(html)
+}
+
+// 描画前にコードブロックに色を付ける
+// 行番号は情報文字列の `linenos` `linenos=false` で、設定のline_numbersより優先される
+pub fn highlight_code_blocks(
+    doc: &mut Document,
+    mode: HighlightMode,
+    line_numbers: bool,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for block in &mut doc.blocks {
+        block.walk_blocks_mut(&mut |block| {
+            let Block::FencedCodeBlock {
+                language,
+                attributes,
+                code,
+                highlighted,
+            } = block
+            else {
+                return;
+            };
+
+            let mut options = LineOptions {
+                line_numbers,
+                ..LineOptions::default()
+            };
+            for (key, value) in attributes.iter() {
+                match key.as_str() {
+                    "linenos" => options.line_numbers = value != "false",
+                    "highlight" => match parse_line_ranges(value) {
+                        Some(parsed) => options.highlighted.extend(parsed),
+                        None => warnings.push(format!("invalid line range {:?}", value)),
+                    },
+                    "diff" => options.diff = true,
+                    _ => {}
+                }
+            }
+
+            *highlighted = highlight_code(code, language.as_deref(), mode, &options);
+        });
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::convert::ToHtml;
+    use crate::parser::document::parse_document;
+
+    fn render(markdown: &str, mode: HighlightMode, line_numbers: bool) -> (String, Vec<String>) {
+        let mut doc = parse_document()(markdown).unwrap().0;
+        let warnings = highlight_code_blocks(&mut doc, mode, line_numbers);
+        (doc.to_html(), warnings)
+    }
+
+    #[test]
+    fn test_highlight_classes() {
+        let (html, warnings) = render(
+            "```rust\nlet s = \"a\nb\"; // x\n```\n",
+            HighlightMode::Class,
+            false,
+        );
+        assert!(warnings.is_empty());
+        assert_eq!(
+            html,
+            concat!(
+                "<pre><code class=\"language-rust\">",
+                "<span class=\"hl-keyword\">let</span> s = <span class=\"hl-string\">&quot;a</span>\n",
+                "<span class=\"hl-string\">b&quot;</span>; <span class=\"hl-comment\">// x</span>\n",
+                "</code></pre>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_highlight_inline() {
+        let (html, _) = render("```json\n[true]\n```\n", HighlightMode::Inline, false);
+        assert_eq!(
+            html,
+            "<pre><code class=\"language-json\">[<span style=\"color: #005cc5\">true</span>]\n</code></pre>"
+        );
+    }
+
+    #[test]
+    fn test_plain_fallback() {
+        let plain = "<pre><code class=\"language-mermaid\">a --&gt; b\n</code></pre>";
+        let (html, _) = render("```mermaid\na --> b\n```\n", HighlightMode::Class, false);
+        assert_eq!(html, plain);
+
+        let (html, _) = render("```rust\nlet\n```\n", HighlightMode::None, false);
+        assert_eq!(
+            html,
+            "<pre><code class=\"language-rust\">let\n</code></pre>"
+        );
+
+        let (html, _) = render("    indented <code>\n", HighlightMode::Class, false);
+        assert_eq!(html, "<pre><code>indented &lt;code&gt;\n</code></pre>");
+    }
+
+    #[test]
+    fn test_line_numbers_and_ranges() {
+        let (html, warnings) = render(
+            "```py {2-3} linenos\na = 1\nb\nc\n```\n",
+            HighlightMode::Class,
+            false,
+        );
+        assert!(warnings.is_empty());
+        assert_eq!(
+            html,
+            concat!(
+                "<pre><code class=\"language-py\">",
+                "<span class=\"code-line\"><span class=\"line-number\">1</span>a = <span class=\"hl-number\">1</span>\n</span>",
+                "<span class=\"code-line highlighted\"><span class=\"line-number\">2</span>b\n</span>",
+                "<span class=\"code-line highlighted\"><span class=\"line-number\">3</span>c\n</span>",
+                "</code></pre>"
+            )
+        );
+
+        // 設定で行番号を付けても、linenos=falseなら付けない
+        let (html, _) = render(
+            "```text {1} linenos=false\nx\n```\n",
+            HighlightMode::Class,
+            true,
+        );
+        assert_eq!(
+            html,
+            "<pre><code class=\"language-text\"><span class=\"code-line highlighted\">x\n</span></code></pre>"
+        );
+
+        let (html, warnings) = render("```\nx\n```\n", HighlightMode::None, true);
+        assert!(warnings.is_empty());
+        assert_eq!(
+            html,
+            "<pre><code><span class=\"code-line\"><span class=\"line-number\">1</span>x\n</span></code></pre>"
+        );
+    }
+
+    #[test]
+    fn test_invalid_ranges() {
+        assert_eq!(parse_line_ranges("3-5, 8"), Some(vec![(3, 5), (8, 8)]));
+        assert_eq!(parse_line_ranges("5-3"), None);
+        assert_eq!(parse_line_ranges("0"), None);
+        assert_eq!(parse_line_ranges("a"), None);
+
+        let (html, warnings) = render("```sh {x}\nls\n```\n", HighlightMode::Class, false);
+        assert_eq!(warnings, vec!["invalid line range \"x\"".to_string()]);
+        assert_eq!(html, "<pre><code class=\"language-sh\">ls\n</code></pre>");
+    }
+
+    #[test]
+    fn test_diff() {
+        let (html, _) = render(
+            "```diff-rust\n fn f() {\n-    let a = 1;\n+    let a = \"x\";\n }\n```\n",
+            HighlightMode::Class,
+            false,
+        );
+        assert_eq!(
+            html,
+            concat!(
+                "<figure class=\"code-block diff\"><pre><code class=\"language-rust\">",
+                "<span class=\"code-line\"><span class=\"diff-marker\"> </span><span class=\"hl-keyword\">fn</span> <span class=\"hl-function\">f</span>() {\n</span>",
+                "<span class=\"code-line removed\"><span class=\"diff-marker\">-</span>    <span class=\"hl-keyword\">let</span> a = <span class=\"hl-number\">1</span>;\n</span>",
+                "<span class=\"code-line added\"><span class=\"diff-marker\">+</span>    <span class=\"hl-keyword\">let</span> a = <span class=\"hl-string\">&quot;x&quot;</span>;\n</span>",
+                "<span class=\"code-line\"><span class=\"diff-marker\"> </span>}\n</span>",
+                "</code></pre></figure>"
+            )
+        );
+
+        let (html, _) = render("```diff\n+a\nb\n```\n", HighlightMode::None, false);
+        assert_eq!(
+            html,
+            concat!(
+                "<figure class=\"code-block diff\"><pre><code class=\"language-diff\">",
+                "<span class=\"code-line added\"><span class=\"diff-marker\">+</span>a\n</span>",
+                "<span class=\"code-line\">b\n</span>",
+                "</code></pre></figure>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_title_and_collapse() {
+        let (html, _) = render(
+            "```toml title=\"biotite.toml\"\na = 1\n```\n",
+            HighlightMode::None,
+            false,
+        );
+        assert_eq!(
+            html,
+            concat!(
+                "<figure class=\"code-block\"><figcaption class=\"code-title\">biotite.toml</figcaption>",
+                "<pre><code class=\"language-toml\">a = 1\n</code></pre></figure>"
+            )
+        );
+
+        let (html, _) = render("```sh collapse\nls\npwd\n```\n", HighlightMode::None, false);
+        assert_eq!(
+            html,
+            concat!(
+                "<figure class=\"code-block\"><details><summary class=\"code-title\">sh (2 lines)</summary>",
+                "<pre><code class=\"language-sh\">ls\npwd\n</code></pre></details></figure>"
+            )
+        );
+
+        let (html, _) = render(
+            "``` collapse=open title=\"<a>\"\nx\n```\n",
+            HighlightMode::None,
+            false,
+        );
+        assert!(html.starts_with(
+            "<figure class=\"code-block\"><details open><summary class=\"code-title\">&lt;a&gt;</summary>"
+        ));
+    }
+
+    #[test]
+    fn test_theme_css() {
+        let css = theme_css();
+        assert!(css.contains(".hl-keyword { color: #d73a49; }\n"));
+        assert!(css.contains(".hl-comment { color: #6a737d; font-style: italic; }\n"));
+        assert_eq!(css.lines().count(), THEME.len());
+    }
+}
@@ -0,0 +1,476 @@
+use super::lexer::{Language, Markup, StringRule};
+
+const fn string(open: &'static str, close: &'static str) -> StringRule {
+    StringRule {
+        open,
+        close,
+        escapes: true,
+        multiline: false,
+    }
+}
+
+const fn raw_string(open: &'static str, close: &'static str) -> StringRule {
+    StringRule {
+        open,
+        close,
+        escapes: false,
+        multiline: true,
+    }
+}
+
+const fn multiline_string(open: &'static str, close: &'static str) -> StringRule {
+    StringRule {
+        open,
+        close,
+        escapes: true,
+        multiline: true,
+    }
+}
+
+const PLAIN: Language = Language {
+    names: &[],
+    line_comments: &[],
+    block_comments: &[],
+    strings: &[],
+    string_prefixes: &[],
+    keywords: &[],
+    types: &[],
+    literals: &[],
+    case_insensitive: false,
+    capitalized_types: false,
+    functions: true,
+    key_separator: None,
+    string_keys: false,
+    variables: false,
+    rust: false,
+    decorators: false,
+    markup: Markup::None,
+};
+
+const JS_KEYWORDS: &[&str] = &[
+    "as",
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "export",
+    "extends",
+    "finally",
+    "for",
+    "from",
+    "function",
+    "get",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "of",
+    "return",
+    "set",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+// JS_KEYWORDSにTypeScriptだけの予約語を足したもの
+const TS_KEYWORDS: &[&str] = &[
+    "abstract",
+    "as",
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "declare",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "finally",
+    "for",
+    "from",
+    "function",
+    "get",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "keyof",
+    "let",
+    "namespace",
+    "new",
+    "of",
+    "private",
+    "protected",
+    "public",
+    "readonly",
+    "return",
+    "satisfies",
+    "set",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "try",
+    "type",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+const JS_LITERALS: &[&str] = &["true", "false", "null", "undefined", "NaN", "Infinity"];
+
+pub const LANGUAGES: &[Language] = &[
+    Language {
+        names: &["rust", "rs"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        strings: &[multiline_string("\"", "\"")],
+        string_prefixes: &["b"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+            "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+            "type", "union", "unsafe", "use", "where", "while", "yield",
+        ],
+        types: &[
+            "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32",
+            "i64", "i128", "isize", "f32", "f64",
+        ],
+        literals: &["true", "false"],
+        capitalized_types: true,
+        rust: true,
+        ..PLAIN
+    },
+    Language {
+        names: &["typescript", "ts", "tsx"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        strings: &[
+            string("\"", "\""),
+            string("'", "'"),
+            multiline_string("`", "`"),
+        ],
+        keywords: TS_KEYWORDS,
+        types: &[
+            "any", "bigint", "boolean", "never", "number", "object", "string", "symbol", "unknown",
+            "void",
+        ],
+        literals: JS_LITERALS,
+        capitalized_types: true,
+        decorators: true,
+        ..PLAIN
+    },
+    Language {
+        names: &["javascript", "js", "jsx", "mjs", "cjs"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        strings: &[
+            string("\"", "\""),
+            string("'", "'"),
+            multiline_string("`", "`"),
+        ],
+        keywords: JS_KEYWORDS,
+        literals: JS_LITERALS,
+        capitalized_types: true,
+        ..PLAIN
+    },
+    Language {
+        names: &["python", "py"],
+        line_comments: &["#"],
+        strings: &[
+            multiline_string("\"\"\"", "\"\"\""),
+            multiline_string("'''", "'''"),
+            string("\"", "\""),
+            string("'", "'"),
+        ],
+        string_prefixes: &["rb", "br", "fr", "rf", "r", "b", "f", "u"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in",
+            "is", "lambda", "match", "nonlocal", "not", "or", "pass", "raise", "return", "try",
+            "while", "with", "yield",
+        ],
+        types: &[
+            "bool",
+            "bytes",
+            "dict",
+            "float",
+            "frozenset",
+            "int",
+            "list",
+            "object",
+            "set",
+            "str",
+            "tuple",
+            "type",
+        ],
+        literals: &["True", "False", "None", "self", "cls"],
+        capitalized_types: true,
+        decorators: true,
+        ..PLAIN
+    },
+    Language {
+        names: &["go", "golang"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        strings: &[string("\"", "\""), string("'", "'"), raw_string("`", "`")],
+        keywords: &[
+            "break",
+            "case",
+            "chan",
+            "const",
+            "continue",
+            "default",
+            "defer",
+            "else",
+            "fallthrough",
+            "for",
+            "func",
+            "go",
+            "goto",
+            "if",
+            "import",
+            "interface",
+            "map",
+            "package",
+            "range",
+            "return",
+            "select",
+            "struct",
+            "switch",
+            "type",
+            "var",
+        ],
+        types: &[
+            "any",
+            "bool",
+            "byte",
+            "complex64",
+            "complex128",
+            "error",
+            "float32",
+            "float64",
+            "int",
+            "int8",
+            "int16",
+            "int32",
+            "int64",
+            "rune",
+            "string",
+            "uint",
+            "uint8",
+            "uint16",
+            "uint32",
+            "uint64",
+            "uintptr",
+        ],
+        literals: &["true", "false", "nil", "iota"],
+        ..PLAIN
+    },
+    Language {
+        names: &["shell", "sh", "bash", "zsh", "console"],
+        line_comments: &["#"],
+        strings: &[multiline_string("\"", "\""), raw_string("'", "'")],
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+            "in", "local", "readonly", "return", "select", "then", "until", "while",
+        ],
+        types: &[
+            "alias", "cd", "echo", "eval", "exec", "exit", "printf", "pwd", "read", "set", "shift",
+            "source", "test", "trap", "unset",
+        ],
+        literals: &["true", "false"],
+        functions: false,
+        variables: true,
+        ..PLAIN
+    },
+    Language {
+        names: &["toml"],
+        line_comments: &["#"],
+        strings: &[
+            multiline_string("\"\"\"", "\"\"\""),
+            raw_string("'''", "'''"),
+            string("\"", "\""),
+            StringRule {
+                open: "'",
+                close: "'",
+                escapes: false,
+                multiline: false,
+            },
+        ],
+        literals: &["true", "false", "inf", "nan"],
+        functions: false,
+        key_separator: Some('='),
+        ..PLAIN
+    },
+    Language {
+        names: &["yaml", "yml"],
+        line_comments: &["#"],
+        strings: &[string("\"", "\""), string("'", "'")],
+        literals: &["true", "false", "null", "yes", "no", "on", "off", "~"],
+        functions: false,
+        key_separator: Some(':'),
+        ..PLAIN
+    },
+    Language {
+        names: &["json", "jsonc", "json5"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        strings: &[string("\"", "\"")],
+        literals: &["true", "false", "null"],
+        functions: false,
+        string_keys: true,
+        ..PLAIN
+    },
+    Language {
+        names: &["sql", "postgresql", "mysql", "sqlite"],
+        line_comments: &["--"],
+        block_comments: &[("/*", "*/")],
+        strings: &[string("'", "'"), string("\"", "\"")],
+        keywords: &[
+            "add",
+            "all",
+            "alter",
+            "and",
+            "as",
+            "asc",
+            "begin",
+            "between",
+            "by",
+            "case",
+            "check",
+            "column",
+            "commit",
+            "constraint",
+            "create",
+            "cross",
+            "default",
+            "delete",
+            "desc",
+            "distinct",
+            "drop",
+            "else",
+            "end",
+            "exists",
+            "foreign",
+            "from",
+            "full",
+            "group",
+            "having",
+            "if",
+            "in",
+            "index",
+            "inner",
+            "insert",
+            "into",
+            "is",
+            "join",
+            "key",
+            "left",
+            "like",
+            "limit",
+            "not",
+            "offset",
+            "on",
+            "or",
+            "order",
+            "outer",
+            "primary",
+            "references",
+            "returning",
+            "right",
+            "rollback",
+            "select",
+            "set",
+            "table",
+            "then",
+            "transaction",
+            "union",
+            "unique",
+            "update",
+            "using",
+            "values",
+            "view",
+            "when",
+            "where",
+            "with",
+        ],
+        types: &[
+            "bigint",
+            "blob",
+            "boolean",
+            "char",
+            "date",
+            "decimal",
+            "double",
+            "float",
+            "int",
+            "integer",
+            "json",
+            "numeric",
+            "real",
+            "serial",
+            "smallint",
+            "text",
+            "time",
+            "timestamp",
+            "uuid",
+            "varchar",
+        ],
+        literals: &["true", "false", "null"],
+        case_insensitive: true,
+        ..PLAIN
+    },
+    Language {
+        names: &["html", "xml", "svg", "xhtml"],
+        markup: Markup::Html,
+        ..PLAIN
+    },
+    Language {
+        names: &["css"],
+        markup: Markup::Css,
+        ..PLAIN
+    },
+];
+
+// コードブロックの言語名から探す。大文字小文字は区別しない
+pub fn find_language(name: &str) -> Option<&'static Language> {
+    let name = name.to_ascii_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|language| language.names.contains(&name.as_str()))
+}
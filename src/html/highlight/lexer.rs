@@ -0,0 +1,848 @@
+use super::languages::find_language;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Comment,
+    String,
+    Number,
+    Keyword,
+    Type,
+    Literal,
+    Function,
+    // TOML・YAMLのキー、JSONのキー、CSSのプロパティ
+    Property,
+    // シェルの `$VAR`、Rustのライフタイム
+    Variable,
+    Tag,
+    Attribute,
+    // Rustの `#[...]`、デコレーター、`<!DOCTYPE>`
+    Meta,
+}
+
+// kindがNoneのものは色を付けない
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Token<'a> {
+    pub kind: Option<TokenKind>,
+    pub text: &'a str,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StringRule {
+    pub open: &'static str,
+    pub close: &'static str,
+    // `\` で次の文字をエスケープできる
+    pub escapes: bool,
+    // falseなら閉じられていなくても行末で終わる
+    pub multiline: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Markup {
+    None,
+    Html,
+    Css,
+}
+
+#[derive(Debug)]
+pub struct Language {
+    pub names: &'static [&'static str],
+    pub line_comments: &'static [&'static str],
+    pub block_comments: &'static [(&'static str, &'static str)],
+    // 長い区切りを先に書く
+    pub strings: &'static [StringRule],
+    // Pythonの `f"..."` のような文字列の前置き
+    pub string_prefixes: &'static [&'static str],
+    pub keywords: &'static [&'static str],
+    pub types: &'static [&'static str],
+    pub literals: &'static [&'static str],
+    // SQL
+    pub case_insensitive: bool,
+    // 大文字で始まる識別子を型とみなす
+    pub capitalized_types: bool,
+    // `(` が続く識別子を関数とみなす
+    pub functions: bool,
+    // 行頭の `key =` (TOML) や `key:` (YAML) をキーとみなす
+    pub key_separator: Option<char>,
+    // `:` が続く文字列をキーとみなす(JSON)
+    pub string_keys: bool,
+    // シェルの `$VAR`
+    pub variables: bool,
+    // ライフタイム・文字リテラル・生文字列・マクロ・属性
+    pub rust: bool,
+    // `@decorator`
+    pub decorators: bool,
+    pub markup: Markup,
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn ident_len(s: &str) -> usize {
+    s.find(|c: char| !is_ident_char(c)).unwrap_or(s.len())
+}
+
+fn line_len(s: &str) -> usize {
+    s.find('\n').unwrap_or(s.len())
+}
+
+fn first_char(s: &str) -> Option<char> {
+    s.chars().next()
+}
+
+// openの直後から、閉じ記号を含めた長さ
+fn string_len(s: &str, open_len: usize, close: &str, escapes: bool, multiline: bool) -> usize {
+    let mut i = open_len;
+    while i < s.len() {
+        let rest = &s[i..];
+        if rest.starts_with(close) {
+            return i + close.len();
+        }
+        let c = first_char(rest).unwrap_or(' ');
+        if c == '\n' && !multiline {
+            return i;
+        }
+        if c == '\\' && escapes {
+            i += 1;
+            if let Some(escaped) = first_char(&s[i..]) {
+                i += escaped.len_utf8();
+            }
+            continue;
+        }
+        i += c.len_utf8();
+    }
+
+    s.len()
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+    tokens: Vec<Token<'a>>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(src: &'a str) -> Lexer<'a> {
+        Lexer {
+            src,
+            pos: 0,
+            tokens: Vec::new(),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn prev_char(&self) -> Option<char> {
+        self.src[..self.pos].chars().next_back()
+    }
+
+    // 行の先頭から今の位置まで
+    fn line_prefix(&self) -> &'a str {
+        let start = self.src[..self.pos].rfind('\n').map_or(0, |i| i + 1);
+        &self.src[start..self.pos]
+    }
+
+    fn push(&mut self, kind: Option<TokenKind>, len: usize) {
+        let text = &self.src[self.pos..self.pos + len];
+        self.pos += len;
+        if text.is_empty() {
+            return;
+        }
+        // 色を付けない部分はまとめる
+        if kind.is_none()
+            && let Some(last) = self.tokens.last_mut()
+            && last.kind.is_none()
+        {
+            let start = self.pos - len - last.text.len();
+            last.text = &self.src[start..self.pos];
+            return;
+        }
+        self.tokens.push(Token { kind, text });
+    }
+
+    fn push_char(&mut self) {
+        let len = first_char(self.rest()).map_or(0, char::len_utf8);
+        self.push(None, len);
+    }
+
+    // 別の言語で字句解析した部分(HTMLの中のscriptとstyle)を足す
+    fn push_embedded(&mut self, len: usize, language: &Language) {
+        let inner = &self.src[self.pos..self.pos + len];
+        for token in tokenize(inner, language) {
+            let offset = token.text.as_ptr() as usize - self.src.as_ptr() as usize;
+            self.pos = offset;
+            self.push(token.kind, token.text.len());
+        }
+        self.pos = inner.as_ptr() as usize - self.src.as_ptr() as usize + len;
+    }
+
+    fn lex_code(&mut self, lang: &Language) {
+        while let Some(c) = first_char(self.rest()) {
+            if let Some((kind, len)) = self
+                .comment(lang)
+                .or_else(|| self.key(lang))
+                .or_else(|| self.meta(lang))
+                .or_else(|| self.string(lang))
+                .or_else(|| self.number(c))
+                .or_else(|| self.variable(lang, c))
+            {
+                self.push(Some(kind), len);
+            } else if is_ident_start(c) {
+                let len = ident_len(self.rest());
+                let (kind, len) = self.classify_ident(lang, len);
+                self.push(kind, len);
+            } else {
+                self.push_char();
+            }
+        }
+    }
+
+    fn comment(&self, lang: &Language) -> Option<(TokenKind, usize)> {
+        let rest = self.rest();
+        for (open, close) in lang.block_comments {
+            if let Some(body) = rest.strip_prefix(open) {
+                let len = body
+                    .find(close)
+                    .map_or(rest.len(), |i| open.len() + i + close.len());
+                return Some((TokenKind::Comment, len));
+            }
+        }
+        for prefix in lang.line_comments {
+            // `#` は語の途中(`$#` や `a#b`)ではコメントにならない
+            if rest.starts_with(prefix)
+                && (*prefix != "#" || self.prev_char().is_none_or(char::is_whitespace))
+            {
+                return Some((TokenKind::Comment, line_len(rest)));
+            }
+        }
+
+        None
+    }
+
+    // 行頭の `key = value` `key: value` のキーと、TOMLの `[table]`
+    fn key(&self, lang: &Language) -> Option<(TokenKind, usize)> {
+        let separator = lang.key_separator?;
+        let prefix = self.line_prefix();
+        let at_line_start = if separator == ':' {
+            prefix.trim_start_matches([' ', '\t', '-']).is_empty()
+        } else {
+            prefix.trim().is_empty()
+        };
+        if !at_line_start {
+            return None;
+        }
+
+        let rest = self.rest();
+        // 行頭の空白とYAMLの `- ` は読み飛ばしてからキーを探す
+        if rest.starts_with(char::is_whitespace) || rest.starts_with("- ") {
+            return None;
+        }
+        let line = &rest[..line_len(rest)];
+        if separator == '=' && line.starts_with('[') {
+            let len = line.rfind(']').map_or(line.len(), |i| i + 1);
+            return Some((TokenKind::Type, len));
+        }
+
+        let i = line.find(separator)?;
+        let key = line[..i].trim_end();
+        let followed_by_space = line[i + 1..].chars().next().is_none_or(char::is_whitespace);
+        if key.is_empty()
+            || (separator == ':' && !followed_by_space)
+            || key.contains(|c: char| "\"'#{}[],&*!|>%@`=".contains(c))
+        {
+            return None;
+        }
+
+        Some((TokenKind::Property, key.len()))
+    }
+
+    fn meta(&self, lang: &Language) -> Option<(TokenKind, usize)> {
+        let rest = self.rest();
+        if lang.rust && (rest.starts_with("#[") || rest.starts_with("#![")) {
+            let mut depth = 0;
+            for (i, c) in rest.char_indices() {
+                match c {
+                    '[' => depth += 1,
+                    ']' if depth == 1 => return Some((TokenKind::Meta, i + 1)),
+                    ']' => depth -= 1,
+                    '\n' => break,
+                    _ => {}
+                }
+            }
+            return Some((TokenKind::Meta, line_len(rest)));
+        }
+        if lang.decorators
+            && let Some(name) = rest.strip_prefix('@')
+            && first_char(name).is_some_and(is_ident_start)
+        {
+            let len = name
+                .find(|c: char| !is_ident_char(c) && c != '.')
+                .unwrap_or(name.len());
+            return Some((TokenKind::Meta, 1 + len));
+        }
+
+        None
+    }
+
+    fn string(&self, lang: &Language) -> Option<(TokenKind, usize)> {
+        let rest = self.rest();
+        if lang.rust
+            && let Some(result) = self.rust_string(rest)
+        {
+            return result;
+        }
+
+        let prefix_len = lang
+            .string_prefixes
+            .iter()
+            .find(|prefix| {
+                rest.get(..prefix.len())
+                    .is_some_and(|p| p.eq_ignore_ascii_case(prefix))
+                    && lang
+                        .strings
+                        .iter()
+                        .any(|rule| rest[prefix.len()..].starts_with(rule.open))
+            })
+            .map_or(0, |prefix| prefix.len());
+        let body = &rest[prefix_len..];
+        let rule = lang
+            .strings
+            .iter()
+            .find(|rule| body.starts_with(rule.open))?;
+        let len = prefix_len
+            + string_len(
+                body,
+                rule.open.len(),
+                rule.close,
+                rule.escapes,
+                rule.multiline,
+            );
+
+        // JSONのキー
+        if lang.string_keys && rest[len..].trim_start().starts_with(':') {
+            return Some((TokenKind::Property, len));
+        }
+
+        Some((TokenKind::String, len))
+    }
+
+    // 生文字列 `r#"..."#`、文字リテラル `'a'`、ライフタイム `'a`
+    // Noneなら通常の文字列として扱う。Some(None)は文字列ではない
+    #[allow(clippy::option_option)]
+    fn rust_string(&self, rest: &str) -> Option<Option<(TokenKind, usize)>> {
+        let raw = rest
+            .strip_prefix("br")
+            .or_else(|| rest.strip_prefix('r'))
+            .filter(|after| after.starts_with(['#', '"']));
+        if let Some(after) = raw {
+            let hashes = after.len() - after.trim_start_matches('#').len();
+            if after[hashes..].starts_with('"') {
+                let prefix_len = rest.len() - after.len() + hashes;
+                let close = format!("\"{}", "#".repeat(hashes));
+                let len = prefix_len + string_len(&rest[prefix_len..], 1, &close, false, true);
+                return Some(Some((TokenKind::String, len)));
+            }
+        }
+
+        let after = rest.strip_prefix('\'')?;
+        let mut chars = after.chars();
+        let next = chars.next()?;
+        if next == '\\' {
+            return Some(Some((
+                TokenKind::String,
+                string_len(rest, 1, "'", true, false),
+            )));
+        }
+        if chars.next() == Some('\'') {
+            return Some(Some((TokenKind::String, 2 + next.len_utf8())));
+        }
+        if is_ident_start(next) {
+            return Some(Some((TokenKind::Variable, 1 + ident_len(after))));
+        }
+
+        Some(None)
+    }
+
+    fn number(&self, c: char) -> Option<(TokenKind, usize)> {
+        let rest = self.rest();
+        // `0..10` の `.10` は小数ではない
+        let starts_number = c.is_ascii_digit()
+            || (c == '.'
+                && self.prev_char() != Some('.')
+                && rest[1..].starts_with(|c: char| c.is_ascii_digit()));
+        if !starts_number || self.prev_char().is_some_and(is_ident_char) {
+            return None;
+        }
+
+        let hex = rest.starts_with("0x") || rest.starts_with("0X");
+        let mut len = 0;
+        let mut prev = '\0';
+        for c in rest.chars() {
+            let accept = is_ident_char(c)
+                || (c == '.'
+                    && prev != '.'
+                    && rest[len + 1..].starts_with(|c: char| c.is_ascii_digit()))
+                || ((c == '+' || c == '-') && !hex && (prev == 'e' || prev == 'E'));
+            if !accept {
+                break;
+            }
+            len += c.len_utf8();
+            prev = c;
+        }
+
+        Some((TokenKind::Number, len))
+    }
+
+    fn variable(&self, lang: &Language, c: char) -> Option<(TokenKind, usize)> {
+        if !lang.variables || c != '$' {
+            return None;
+        }
+
+        let after = &self.rest()[1..];
+        let len = match first_char(after)? {
+            '{' => after.find('}').map_or(line_len(after), |i| i + 1),
+            c if is_ident_start(c) => ident_len(after),
+            c if "@#?$!*-0123456789".contains(c) => 1,
+            _ => return None,
+        };
+
+        Some((TokenKind::Variable, 1 + len))
+    }
+
+    fn classify_ident(&self, lang: &Language, len: usize) -> (Option<TokenKind>, usize) {
+        let rest = self.rest();
+        let word = &rest[..len];
+        let contains = |words: &[&str]| {
+            if lang.case_insensitive {
+                words.iter().any(|w| w.eq_ignore_ascii_case(word))
+            } else {
+                words.contains(&word)
+            }
+        };
+        let after = &rest[len..];
+
+        let kind = if contains(lang.keywords) {
+            TokenKind::Keyword
+        } else if contains(lang.literals) {
+            TokenKind::Literal
+        } else if contains(lang.types) {
+            TokenKind::Type
+        } else if lang.rust && after.starts_with('!') && !after.starts_with("!=") {
+            // マクロ
+            return (Some(TokenKind::Function), len + 1);
+        } else if lang.functions && after.starts_with('(') {
+            TokenKind::Function
+        } else if lang.capitalized_types && word.starts_with(|c: char| c.is_uppercase()) {
+            TokenKind::Type
+        } else {
+            return (None, len);
+        };
+
+        (Some(kind), len)
+    }
+
+    fn lex_html(&mut self) {
+        while let Some(c) = first_char(self.rest()) {
+            let rest = self.rest();
+            if rest.starts_with("<!--") {
+                let len = rest.find("-->").map_or(rest.len(), |i| i + 3);
+                self.push(Some(TokenKind::Comment), len);
+            } else if rest.starts_with("<!") || rest.starts_with("<?") {
+                let len = rest.find('>').map_or(rest.len(), |i| i + 1);
+                self.push(Some(TokenKind::Meta), len);
+            } else if c == '<' && rest[1..].starts_with(|c: char| c == '/' || c.is_alphabetic()) {
+                self.lex_tag();
+            } else if c == '&'
+                && let Some(end) = rest.find(';')
+                && end <= 10
+                && rest[1..end]
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '#')
+            {
+                self.push(Some(TokenKind::Literal), end + 1);
+            } else {
+                let len = rest[1..].find(['<', '&']).map_or(rest.len(), |i| i + 1);
+                self.push(None, len);
+            }
+        }
+    }
+
+    fn lex_tag(&mut self) {
+        let closing = self.rest().starts_with("</");
+        self.push(None, if closing { 2 } else { 1 });
+        let name_len = self
+            .rest()
+            .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == ':'))
+            .unwrap_or(self.rest().len());
+        let name = self.rest()[..name_len].to_ascii_lowercase();
+        self.push(Some(TokenKind::Tag), name_len);
+
+        let mut self_closing = false;
+        let mut after_equals = false;
+        while let Some(c) = first_char(self.rest()) {
+            let rest = self.rest();
+            if c == '>' || rest.starts_with("/>") {
+                self_closing = c == '/';
+                self.push(None, if self_closing { 2 } else { 1 });
+                break;
+            } else if c == '"' || c == '\'' {
+                let len = rest[1..].find(c).map_or(rest.len(), |i| i + 2);
+                self.push(Some(TokenKind::String), len);
+                after_equals = false;
+            } else if c.is_whitespace() || c == '=' {
+                after_equals = c == '=';
+                self.push(None, c.len_utf8());
+            } else {
+                let len = rest
+                    .find(|c: char| c.is_whitespace() || "=>\"'".contains(c) || c == '/')
+                    .unwrap_or(rest.len())
+                    .max(c.len_utf8());
+                let kind = if after_equals {
+                    TokenKind::String
+                } else {
+                    TokenKind::Attribute
+                };
+                self.push(Some(kind), len);
+                after_equals = false;
+            }
+        }
+
+        // scriptとstyleの中身はJavaScriptとCSSとして読む
+        let embedded = match name.as_str() {
+            "script" => find_language("javascript"),
+            "style" => find_language("css"),
+            _ => None,
+        };
+        if let Some(language) = embedded
+            && !closing
+            && !self_closing
+        {
+            let rest = self.rest();
+            let end = rest
+                .to_ascii_lowercase()
+                .find(&format!("</{}", name))
+                .unwrap_or(rest.len());
+            self.push_embedded(end, language);
+        }
+    }
+
+    fn lex_css(&mut self) {
+        // 値を読んでいる間は、値が始まったときの括弧の深さ
+        let mut value_depth = None;
+        let mut depth = 0;
+        while let Some(c) = first_char(self.rest()) {
+            let rest = self.rest();
+            let css_ident_len = |s: &str| {
+                s.find(|c: char| !(is_ident_char(c) || c == '-'))
+                    .unwrap_or(s.len())
+            };
+
+            if let Some(body) = rest.strip_prefix("/*") {
+                let len = body.find("*/").map_or(rest.len(), |i| i + 4);
+                self.push(Some(TokenKind::Comment), len);
+            } else if c == '"' || c == '\'' {
+                let len = string_len(rest, 1, &c.to_string(), true, false);
+                self.push(Some(TokenKind::String), len);
+            } else if c == '@' {
+                self.push(Some(TokenKind::Keyword), 1 + css_ident_len(&rest[1..]));
+            } else if matches!(c, ';' | '{' | '}') {
+                value_depth = None;
+                self.push(None, 1);
+            } else if c == '(' || c == ')' {
+                if c == '(' {
+                    depth += 1;
+                } else {
+                    depth -= 1;
+                    if value_depth.is_some_and(|d| depth < d) {
+                        value_depth = None;
+                    }
+                }
+                self.push(None, 1);
+            } else if value_depth.is_some() {
+                if c == '#'
+                    || c.is_ascii_digit()
+                    || (c == '.' && rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+                {
+                    let len = 1 + rest[1..]
+                        .find(|c: char| !(c.is_alphanumeric() || c == '.' || c == '%'))
+                        .unwrap_or(rest.len() - 1);
+                    self.push(Some(TokenKind::Number), len);
+                } else if c == '!' {
+                    self.push(Some(TokenKind::Keyword), 1 + css_ident_len(&rest[1..]));
+                } else if is_ident_start(c) || c == '-' {
+                    let len = css_ident_len(rest).max(1);
+                    let kind = rest[len..].starts_with('(').then_some(TokenKind::Function);
+                    self.push(kind, len);
+                } else {
+                    self.push_char();
+                }
+            } else if is_ident_start(c) || c == '-' || c == '*' {
+                let len = css_ident_len(rest).max(1);
+                // 次の `{` `;` `}` までに `{` が来ればセレクター。`@media (width: 1px)` の中はプロパティ
+                let statement_end = rest.find(['{', ';', '}']).map(|i| rest.as_bytes()[i]);
+                let is_property = rest[len..].trim_start().starts_with(':')
+                    && (statement_end != Some(b'{') || depth > 0);
+                if is_property {
+                    self.push(Some(TokenKind::Property), len);
+                    let spaces = self.rest().len() - self.rest().trim_start().len();
+                    self.push(None, spaces + 1);
+                    value_depth = Some(depth);
+                } else {
+                    self.push(Some(TokenKind::Tag), len);
+                }
+            } else if c == '.' || c == '#' {
+                self.push(Some(TokenKind::Attribute), 1 + css_ident_len(&rest[1..]));
+            } else if c == ':' {
+                let colons = if rest.starts_with("::") { 2 } else { 1 };
+                self.push(
+                    Some(TokenKind::Keyword),
+                    colons + css_ident_len(&rest[colons..]),
+                );
+            } else {
+                self.push_char();
+            }
+        }
+    }
+}
+
+pub fn tokenize<'a>(code: &'a str, language: &Language) -> Vec<Token<'a>> {
+    let mut lexer = Lexer::new(code);
+    match language.markup {
+        Markup::None => lexer.lex_code(language),
+        Markup::Html => lexer.lex_html(),
+        Markup::Css => lexer.lex_css(),
+    }
+
+    lexer.tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (種類, 文字列) の組にする。色を付けない部分は除く
+    fn lex(language: &str, code: &str) -> Vec<(TokenKind, String)> {
+        let language = find_language(language).unwrap();
+        let tokens = tokenize(code, language);
+        assert_eq!(
+            tokens.iter().map(|t| t.text).collect::<String>(),
+            code,
+            "tokens must cover the input"
+        );
+        tokens
+            .into_iter()
+            .filter_map(|t| t.kind.map(|kind| (kind, t.text.to_string())))
+            .collect()
+    }
+
+    fn tokens(expected: &[(TokenKind, &str)]) -> Vec<(TokenKind, String)> {
+        expected
+            .iter()
+            .map(|(kind, text)| (*kind, text.to_string()))
+            .collect()
+    }
+
+    use TokenKind::{
+        Attribute, Comment, Function, Keyword, Literal, Meta, Number, Property, Tag, Type, Variable,
+    };
+
+    #[test]
+    fn test_rust() {
+        assert_eq!(
+            lex(
+                "rust",
+                "#[derive(Debug)]\nfn main(x: &'a str) -> &'a u8 { // hi\n    println!(\"{}\", r#\"raw\"#, '\\n', 0x1F, 1.5e-3); }\n"
+            ),
+            tokens(&[
+                (Meta, "#[derive(Debug)]"),
+                (Keyword, "fn"),
+                (Function, "main"),
+                (Variable, "'a"),
+                (Type, "str"),
+                (Variable, "'a"),
+                (Type, "u8"),
+                (Comment, "// hi"),
+                (Function, "println!"),
+                (TokenKind::String, "\"{}\""),
+                (TokenKind::String, "r#\"raw\"#"),
+                (TokenKind::String, "'\\n'"),
+                (Number, "0x1F"),
+                (Number, "1.5e-3"),
+            ])
+        );
+        assert_eq!(
+            lex("rs", "for i in 0..10 { Some(i) }"),
+            tokens(&[
+                (Keyword, "for"),
+                (Keyword, "in"),
+                (Number, "0"),
+                (Number, "10"),
+                (Function, "Some"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_scripts() {
+        assert_eq!(
+            lex(
+                "ts",
+                "@Component\nconst s: string = `a ${b}`; /* c */ new Map()"
+            ),
+            tokens(&[
+                (Meta, "@Component"),
+                (Keyword, "const"),
+                (Type, "string"),
+                (TokenKind::String, "`a ${b}`"),
+                (Comment, "/* c */"),
+                (Keyword, "new"),
+                (Function, "Map"),
+            ])
+        );
+        assert_eq!(
+            lex(
+                "python",
+                "def f(x=None):\n    return f\"{x}\" # done\n\"\"\"doc\nstring\"\"\""
+            ),
+            tokens(&[
+                (Keyword, "def"),
+                (Function, "f"),
+                (Literal, "None"),
+                (Keyword, "return"),
+                (TokenKind::String, "f\"{x}\""),
+                (Comment, "# done"),
+                (TokenKind::String, "\"\"\"doc\nstring\"\"\""),
+            ])
+        );
+        assert_eq!(
+            lex("go", "func main() { s := `raw\\n`; var n int = 42 }"),
+            tokens(&[
+                (Keyword, "func"),
+                (Function, "main"),
+                (TokenKind::String, "`raw\\n`"),
+                (Keyword, "var"),
+                (Type, "int"),
+                (Number, "42"),
+            ])
+        );
+        assert_eq!(
+            lex(
+                "bash",
+                "if [ -n \"$HOME\" ]; then echo ${PATH} $# 'x' # c\nfi"
+            ),
+            tokens(&[
+                (Keyword, "if"),
+                (TokenKind::String, "\"$HOME\""),
+                (Keyword, "then"),
+                (Type, "echo"),
+                (Variable, "${PATH}"),
+                (Variable, "$#"),
+                (TokenKind::String, "'x'"),
+                (Comment, "# c"),
+                (Keyword, "fi"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_data() {
+        assert_eq!(
+            lex("toml", "[build]\nout-dir = \"public\" # c\ndepth = 5\n"),
+            tokens(&[
+                (Type, "[build]"),
+                (Property, "out-dir"),
+                (TokenKind::String, "\"public\""),
+                (Comment, "# c"),
+                (Property, "depth"),
+                (Number, "5"),
+            ])
+        );
+        assert_eq!(
+            lex(
+                "yaml",
+                "title: Hello\ntags:\n  - name: a\nurl: http://x\ndraft: true\n"
+            ),
+            tokens(&[
+                (Property, "title"),
+                (Property, "tags"),
+                (Property, "name"),
+                (Property, "url"),
+                (Property, "draft"),
+                (Literal, "true"),
+            ])
+        );
+        assert_eq!(
+            lex("json", "{\"a\": [1, \"b\", null]}"),
+            tokens(&[
+                (Property, "\"a\""),
+                (Number, "1"),
+                (TokenKind::String, "\"b\""),
+                (Literal, "null"),
+            ])
+        );
+        assert_eq!(
+            lex("sql", "SELECT name FROM users WHERE id = 1 -- c\n"),
+            tokens(&[
+                (Keyword, "SELECT"),
+                (Keyword, "FROM"),
+                (Keyword, "WHERE"),
+                (Number, "1"),
+                (Comment, "-- c"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_markup() {
+        assert_eq!(
+            lex(
+                "html",
+                "<!DOCTYPE html>\n<a href=\"/x\" hidden>&amp;</a><!-- c --><style>p { color: red; }</style>"
+            ),
+            tokens(&[
+                (Meta, "<!DOCTYPE html>"),
+                (Tag, "a"),
+                (Attribute, "href"),
+                (TokenKind::String, "\"/x\""),
+                (Attribute, "hidden"),
+                (Literal, "&amp;"),
+                (Tag, "a"),
+                (Comment, "<!-- c -->"),
+                (Tag, "style"),
+                (Tag, "p"),
+                (Property, "color"),
+                (Tag, "style"),
+            ])
+        );
+        assert_eq!(
+            lex(
+                "css",
+                "@media (max-width: 600px) {\n  a:hover, .x #y { margin: 0 1.5em !important; color: rgb(0 0 0); }\n}"
+            ),
+            tokens(&[
+                (Keyword, "@media"),
+                (Property, "max-width"),
+                (Number, "600px"),
+                (Tag, "a"),
+                (Keyword, ":hover"),
+                (Attribute, ".x"),
+                (Attribute, "#y"),
+                (Property, "margin"),
+                (Number, "0"),
+                (Number, "1.5em"),
+                (Keyword, "!important"),
+                (Property, "color"),
+                (Function, "rgb"),
+                (Number, "0"),
+                (Number, "0"),
+                (Number, "0"),
+            ])
+        );
+    }
+}
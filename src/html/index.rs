@@ -0,0 +1,154 @@
+use crate::config::Config;
+use crate::html::tags::tags_context;
+use crate::html::template::engine::{TemplateError, Templates};
+use crate::html::template::render_page;
+use crate::html::to_html::HTMLDocument;
+use crate::parser::document::front_matter::Value;
+
+// テンプレートの `page.posts` の要素
+pub fn post_context(doc: &HTMLDocument) -> Value {
+    Value::Map(vec![
+        ("title".to_string(), Value::String(doc.title.to_string())),
+        ("url".to_string(), Value::String(doc.path.clone())),
+        (
+            "date".to_string(),
+            doc.date.map_or(Value::Null, Value::Date),
+        ),
+        (
+            "tags".to_string(),
+            tags_context(doc.tags.as_deref().unwrap_or_default()),
+        ),
+        ("excerpt".to_string(), Value::String(doc.excerpt.clone())),
+    ])
+}
+
+// 新しい順。日付のないページは最後に回す
+pub fn sort_posts(posts: &mut [&HTMLDocument]) {
+    posts.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| a.title.cmp(b.title)));
+}
+
+fn url_or_null(url: Option<String>) -> Value {
+    url.map_or(Value::Null, Value::String)
+}
+
+// 新しい順に並べた一覧のページを (URL, HTML) で返す
+pub fn index_pages(
+    docs: &[HTMLDocument],
+    templates: &Templates,
+    config: &Config,
+) -> Result<Vec<(String, String)>, TemplateError> {
+    let mut posts: Vec<&HTMLDocument> = docs.iter().collect();
+    sort_posts(&mut posts);
+
+    let per_page = if config.paginate == 0 {
+        posts.len().max(1)
+    } else {
+        config.paginate
+    };
+    let chunks: Vec<&[&HTMLDocument]> = if posts.is_empty() {
+        vec![&[]]
+    } else {
+        posts.chunks(per_page).collect()
+    };
+    let total = chunks.len();
+    let title = if config.title.is_empty() {
+        "Index"
+    } else {
+        &config.title
+    };
+
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let number = i + 1;
+            let url = config.index_url(number);
+            let pagination = Value::Map(vec![
+                ("current".to_string(), Value::Integer(number as i64)),
+                ("total".to_string(), Value::Integer(total as i64)),
+                (
+                    "prev_url".to_string(),
+                    url_or_null((number > 1).then(|| config.index_url(number - 1))),
+                ),
+                (
+                    "next_url".to_string(),
+                    url_or_null((number < total).then(|| config.index_url(number + 1))),
+                ),
+            ]);
+            let page = Value::Map(vec![
+                ("title".to_string(), Value::String(title.to_string())),
+                ("url".to_string(), Value::String(url.clone())),
+                (
+                    "posts".to_string(),
+                    Value::List(chunk.iter().map(|doc| post_context(doc)).collect()),
+                ),
+                ("pagination".to_string(), pagination),
+            ]);
+            let html = render_page(templates, "index.html", config, page)?;
+
+            Ok((url, html))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::template::builtin_templates;
+
+    #[test]
+    fn test_index_pages() {
+        let docs = vec![
+            HTMLDocument::for_test("old", vec!["rust"], Some(1)),
+            HTMLDocument::for_test("undated", vec!["rust"], None),
+            HTMLDocument::for_test("new", vec!["rust"], Some(9)),
+        ];
+        let config = Config {
+            paginate: 2,
+            ..Config::default()
+        };
+        let pages = index_pages(&docs, &builtin_templates(), &config).unwrap();
+
+        assert_eq!(pages.len(), 2);
+        let (url, html) = &pages[0];
+        assert_eq!(url, "/");
+        assert!(html.contains(concat!(
+            "<li>\n",
+            "<a href=\"/new.html\">new</a>\n",
+            "<time datetime=\"2024-03-09\">2024-03-09</time>\n",
+            "<span class=\"tags\"><a class=\"tag\" href=\"/tags/rust/\">#rust</a></span>\n",
+            "<p>About new</p>\n",
+            "</li>\n",
+        )));
+        assert!(html.find("/new.html") < html.find("/old.html"));
+        assert!(!html.contains("/undated.html"));
+        assert!(html.contains("<a rel=\"next\" href=\"/page/2/\">Older</a>"));
+        assert!(!html.contains("rel=\"prev\""));
+
+        let (url, html) = &pages[1];
+        assert_eq!(url, "/page/2/");
+        assert!(html.contains("<a href=\"/undated.html\">undated</a>\n<span"));
+        assert!(html.contains("<a rel=\"prev\" href=\"/\">Newer</a>"));
+    }
+
+    #[test]
+    fn test_index_pages_without_pagination() {
+        let docs = vec![
+            HTMLDocument::for_test("a", vec!["rust"], Some(1)),
+            HTMLDocument::for_test("b", vec!["rust"], Some(2)),
+            HTMLDocument::for_test("c", vec!["rust"], Some(3)),
+        ];
+        let config = Config {
+            paginate: 0,
+            ..Config::default()
+        };
+
+        let pages = index_pages(&docs, &builtin_templates(), &config).unwrap();
+        assert_eq!(pages.len(), 1);
+        assert!(!pages[0].1.contains("class=\"pagination\""));
+
+        let pages = index_pages(&[], &builtin_templates(), &config).unwrap();
+        assert_eq!(pages.len(), 1);
+        assert!(pages[0].1.contains("<ul class=\"post-list\">\n</ul>\n"));
+    }
+}
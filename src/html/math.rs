@@ -0,0 +1,906 @@
+use crate::html::convert::escape_html;
+use crate::parser::document::ast::{Block, Document, Inline};
+
+// TeXの数式の一部をビルド時にMathMLへ変換する
+// 分数・上付き/下付き・ギリシャ文字・主な演算子・根号・行列・aligned環境に対応
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Command(String),
+    Char(char),
+    Number(String),
+    Space,
+    BeginGroup,
+    EndGroup,
+    Sub,
+    Sup,
+    Align,
+    NewRow,
+}
+
+fn tokenize(tex: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = tex.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '\\' => match chars.next() {
+                Some('\\') => Token::NewRow,
+                Some(c) if c.is_ascii_alphabetic() => {
+                    let mut name = c.to_string();
+                    while let Some(&c) = chars.peek()
+                        && c.is_ascii_alphabetic()
+                    {
+                        name.push(c);
+                        chars.next();
+                    }
+                    Token::Command(name)
+                }
+                Some(c) => Token::Command(c.to_string()),
+                None => return Err("trailing backslash".to_string()),
+            },
+            '{' => Token::BeginGroup,
+            '}' => Token::EndGroup,
+            '_' => Token::Sub,
+            '^' => Token::Sup,
+            '&' => Token::Align,
+            c if c.is_whitespace() => {
+                while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                    chars.next();
+                }
+                Token::Space
+            }
+            c if c.is_ascii_digit() => {
+                let mut number = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    let is_decimal_point =
+                        c == '.' && chars.clone().nth(1).is_some_and(|c| c.is_ascii_digit());
+                    if !c.is_ascii_digit() && !is_decimal_point {
+                        break;
+                    }
+                    number.push(c);
+                    chars.next();
+                }
+                Token::Number(number)
+            }
+            c => Token::Char(c),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn greek_letter(name: &str) -> Option<char> {
+    let c = match name {
+        "alpha" => 'α',
+        "beta" => 'β',
+        "gamma" => 'γ',
+        "delta" => 'δ',
+        "epsilon" => 'ϵ',
+        "varepsilon" => 'ε',
+        "zeta" => 'ζ',
+        "eta" => 'η',
+        "theta" => 'θ',
+        "vartheta" => 'ϑ',
+        "iota" => 'ι',
+        "kappa" => 'κ',
+        "lambda" => 'λ',
+        "mu" => 'μ',
+        "nu" => 'ν',
+        "xi" => 'ξ',
+        "omicron" => 'ο',
+        "pi" => 'π',
+        "varpi" => 'ϖ',
+        "rho" => 'ρ',
+        "varrho" => 'ϱ',
+        "sigma" => 'σ',
+        "varsigma" => 'ς',
+        "tau" => 'τ',
+        "upsilon" => 'υ',
+        "phi" => 'ϕ',
+        "varphi" => 'φ',
+        "chi" => 'χ',
+        "psi" => 'ψ',
+        "omega" => 'ω',
+        "Gamma" => 'Γ',
+        "Delta" => 'Δ',
+        "Theta" => 'Θ',
+        "Lambda" => 'Λ',
+        "Xi" => 'Ξ',
+        "Pi" => 'Π',
+        "Sigma" => 'Σ',
+        "Upsilon" => 'Υ',
+        "Phi" => 'Φ',
+        "Psi" => 'Ψ',
+        "Omega" => 'Ω',
+        _ => return None,
+    };
+
+    Some(c)
+}
+
+fn operator_symbol(name: &str) -> Option<&'static str> {
+    let op = match name {
+        "times" => "×",
+        "cdot" | "cdotp" => "⋅",
+        "pm" => "±",
+        "mp" => "∓",
+        "div" => "÷",
+        "ast" => "∗",
+        "star" => "⋆",
+        "circ" => "∘",
+        "bullet" => "∙",
+        "leq" | "le" => "≤",
+        "geq" | "ge" => "≥",
+        "neq" | "ne" => "≠",
+        "ll" => "≪",
+        "gg" => "≫",
+        "approx" => "≈",
+        "equiv" => "≡",
+        "sim" => "∼",
+        "simeq" => "≃",
+        "cong" => "≅",
+        "propto" => "∝",
+        "in" => "∈",
+        "notin" => "∉",
+        "ni" => "∋",
+        "subset" => "⊂",
+        "subseteq" => "⊆",
+        "supset" => "⊃",
+        "supseteq" => "⊇",
+        "cup" => "∪",
+        "cap" => "∩",
+        "setminus" => "∖",
+        "land" | "wedge" => "∧",
+        "lor" | "vee" => "∨",
+        "neg" | "lnot" => "¬",
+        "forall" => "∀",
+        "exists" => "∃",
+        "to" | "rightarrow" => "→",
+        "leftarrow" | "gets" => "←",
+        "leftrightarrow" => "↔",
+        "Rightarrow" => "⇒",
+        "Leftarrow" => "⇐",
+        "Leftrightarrow" | "iff" => "⇔",
+        "implies" => "⟹",
+        "mapsto" => "↦",
+        "perp" => "⊥",
+        "parallel" => "∥",
+        "mid" => "∣",
+        "ldots" | "dots" => "…",
+        "cdots" => "⋯",
+        "vdots" => "⋮",
+        "ddots" => "⋱",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        "{" | "lbrace" => "{",
+        "}" | "rbrace" => "}",
+        "|" | "Vert" => "‖",
+        "vert" => "|",
+        "prime" => "′",
+        _ => return None,
+    };
+
+    Some(op)
+}
+
+fn identifier_symbol(name: &str) -> Option<&'static str> {
+    let symbol = match name {
+        "infty" => "∞",
+        "partial" => "∂",
+        "nabla" => "∇",
+        "emptyset" | "varnothing" => "∅",
+        "hbar" => "ℏ",
+        "ell" => "ℓ",
+        "angle" => "∠",
+        "aleph" => "ℵ",
+        "Re" => "ℜ",
+        "Im" => "ℑ",
+        "#" => "#",
+        "%" => "%",
+        "$" => "$",
+        "&" => "&",
+        "_" => "_",
+        _ => return None,
+    };
+
+    Some(symbol)
+}
+
+// (記号, 上下の添字をmunder/moverで付けるか)
+fn large_operator(name: &str) -> Option<(&'static str, bool)> {
+    let op = match name {
+        "sum" => ("∑", true),
+        "prod" => ("∏", true),
+        "coprod" => ("∐", true),
+        "bigcup" => ("⋃", true),
+        "bigcap" => ("⋂", true),
+        "bigoplus" => ("⨁", true),
+        "bigotimes" => ("⨂", true),
+        "int" => ("∫", false),
+        "iint" => ("∬", false),
+        "iiint" => ("∭", false),
+        "oint" => ("∮", false),
+        _ => return None,
+    };
+
+    Some(op)
+}
+
+// 関数名なら上下の添字をmunder/moverで付けるかを返す
+fn function_name(name: &str) -> Option<bool> {
+    match name {
+        "lim" | "max" | "min" | "sup" | "inf" | "det" | "gcd" | "Pr" | "liminf" | "limsup"
+        | "argmax" | "argmin" => Some(true),
+        "sin" | "cos" | "tan" | "cot" | "sec" | "csc" | "arcsin" | "arccos" | "arctan" | "sinh"
+        | "cosh" | "tanh" | "log" | "ln" | "lg" | "exp" | "arg" | "deg" | "dim" | "ker" | "hom"
+        | "mod" => Some(false),
+        _ => None,
+    }
+}
+
+fn space_width(name: &str) -> Option<&'static str> {
+    let width = match name {
+        "," | "thinspace" => "0.1667em",
+        ":" | ">" | "medspace" => "0.2222em",
+        ";" | "thickspace" => "0.2778em",
+        " " => "0.25em",
+        "quad" => "1em",
+        "qquad" => "2em",
+        "!" => "-0.1667em",
+        _ => return None,
+    };
+
+    Some(width)
+}
+
+fn accent(name: &str) -> Option<(&'static str, bool)> {
+    // (記号, 下に付けるか)
+    let accent = match name {
+        "hat" | "widehat" => ("^", false),
+        "bar" | "overline" => ("¯", false),
+        "vec" => ("→", false),
+        "dot" => ("˙", false),
+        "ddot" => ("¨", false),
+        "tilde" | "widetilde" => ("~", false),
+        "underline" => ("_", true),
+        _ => return None,
+    };
+
+    Some(accent)
+}
+
+// 数学用英数字記号のうち、先にLetterlike Symbolsへ割り当てられていて欠番になっている文字
+const LETTERLIKE: &[(&str, char, char)] = &[
+    ("mathbb", 'C', 'ℂ'),
+    ("mathbb", 'H', 'ℍ'),
+    ("mathbb", 'N', 'ℕ'),
+    ("mathbb", 'P', 'ℙ'),
+    ("mathbb", 'Q', 'ℚ'),
+    ("mathbb", 'R', 'ℝ'),
+    ("mathbb", 'Z', 'ℤ'),
+    ("mathcal", 'B', 'ℬ'),
+    ("mathcal", 'E', 'ℰ'),
+    ("mathcal", 'F', 'ℱ'),
+    ("mathcal", 'H', 'ℋ'),
+    ("mathcal", 'I', 'ℐ'),
+    ("mathcal", 'L', 'ℒ'),
+    ("mathcal", 'M', 'ℳ'),
+    ("mathcal", 'R', 'ℛ'),
+    ("mathcal", 'e', 'ℯ'),
+    ("mathcal", 'g', 'ℊ'),
+    ("mathcal", 'o', 'ℴ'),
+    ("mathfrak", 'C', 'ℭ'),
+    ("mathfrak", 'H', 'ℌ'),
+    ("mathfrak", 'I', 'ℑ'),
+    ("mathfrak", 'R', 'ℜ'),
+    ("mathfrak", 'Z', 'ℨ'),
+];
+
+// \mathbb や \mathcal などの書体をUnicodeの数学用英数字記号に変換する
+fn styled_char(style: &str, c: char) -> Option<char> {
+    let style = match style {
+        "boldsymbol" => "mathbf",
+        "mathscr" => "mathcal",
+        _ => style,
+    };
+    // (大文字Aの位置, 小文字aの位置, 数字0の位置)
+    let (upper, lower, digit) = match style {
+        "mathbf" => (0x1D400, 0x1D41A, Some(0x1D7CE)),
+        "mathbb" => (0x1D538, 0x1D552, Some(0x1D7D8)),
+        "mathcal" => (0x1D49C, 0x1D4B6, None),
+        "mathfrak" => (0x1D504, 0x1D51E, None),
+        _ => return None,
+    };
+
+    if let Some((_, _, mapped)) = LETTERLIKE
+        .iter()
+        .find(|(s, from, _)| *s == style && *from == c)
+    {
+        return Some(*mapped);
+    }
+
+    let code = match c {
+        'A'..='Z' => upper + (c as u32 - 'A' as u32),
+        'a'..='z' => lower + (c as u32 - 'a' as u32),
+        '0'..='9' => digit? + (c as u32 - '0' as u32),
+        _ => return Some(c),
+    };
+
+    char::from_u32(code)
+}
+
+fn fence(delimiter: &str) -> String {
+    if delimiter.is_empty() {
+        String::new()
+    } else {
+        format!("<mo>{}</mo>", escape_html(delimiter))
+    }
+}
+
+struct MathParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl MathParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek() == Some(&Token::Space) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect_end_group(&mut self) -> Result<(), String> {
+        self.skip_spaces();
+        match self.next() {
+            Some(Token::EndGroup) => Ok(()),
+            _ => Err("missing `}`".to_string()),
+        }
+    }
+
+    // `}`、`&`、`\\`、`]`(オプション引数中)、\right、\end の手前までを読む
+    fn parse_row(&mut self, in_optional: bool) -> Result<String, String> {
+        let mut row = String::new();
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                None | Some(Token::EndGroup) | Some(Token::Align) | Some(Token::NewRow) => break,
+                Some(Token::Char(']')) if in_optional => break,
+                Some(Token::Command(name)) if name == "right" || name == "end" => break,
+                _ => row.push_str(&self.parse_scripted()?),
+            }
+        }
+
+        Ok(row)
+    }
+
+    // 表(行列・aligned)の中身を \end か末尾まで読む
+    fn parse_table(&mut self) -> Result<Vec<Vec<String>>, String> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        loop {
+            row.push(self.parse_row(false)?);
+            match self.peek() {
+                Some(Token::Align) => {
+                    self.next();
+                }
+                Some(Token::NewRow) => {
+                    self.next();
+                    rows.push(std::mem::take(&mut row));
+                }
+                _ => {
+                    rows.push(row);
+                    break;
+                }
+            }
+        }
+
+        // 末尾の `\\` の後ろにできる空行は捨てる
+        if rows.len() > 1 && rows.last().is_some_and(|r| r.len() == 1 && r[0].is_empty()) {
+            rows.pop();
+        }
+
+        Ok(rows)
+    }
+
+    fn parse_scripted(&mut self) -> Result<String, String> {
+        let (base, limits) = self.parse_atom()?;
+        let mut sub = None;
+        let mut sup = None;
+
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                Some(Token::Sub) if sub.is_none() => {
+                    self.next();
+                    sub = Some(self.parse_argument()?);
+                }
+                Some(Token::Sup) if sup.is_none() => {
+                    self.next();
+                    sup = Some(self.parse_argument()?);
+                }
+                Some(Token::Char('\'')) if sup.is_none() => {
+                    let mut primes = String::new();
+                    while self.peek() == Some(&Token::Char('\'')) {
+                        self.next();
+                        primes.push('′');
+                    }
+                    sup = Some(format!("<mo>{}</mo>", primes));
+                }
+                Some(Token::Sub) => return Err("double subscript".to_string()),
+                Some(Token::Sup) => return Err("double superscript".to_string()),
+                _ => break,
+            }
+        }
+
+        let (sub_tag, sup_tag, both_tag) = if limits {
+            ("munder", "mover", "munderover")
+        } else {
+            ("msub", "msup", "msubsup")
+        };
+
+        let scripted = match (sub, sup) {
+            (None, None) => base,
+            (Some(sub), None) => format!("<{0}>{1}{2}</{0}>", sub_tag, base, sub),
+            (None, Some(sup)) => format!("<{0}>{1}{2}</{0}>", sup_tag, base, sup),
+            (Some(sub), Some(sup)) => format!("<{0}>{1}{2}{3}</{0}>", both_tag, base, sub, sup),
+        };
+
+        Ok(scripted)
+    }
+
+    // コマンドの引数や添字になる1要素
+    // `\frac12` のように数字が続く場合は先頭の1桁だけを取る
+    fn parse_argument(&mut self) -> Result<String, String> {
+        self.skip_spaces();
+        if let Some(Token::Number(number)) = self.peek()
+            && number.len() > 1
+        {
+            let (first, rest) = number.split_at(1);
+            let first = first.to_string();
+            self.tokens[self.pos] = Token::Number(rest.to_string());
+            return Ok(format!("<mn>{}</mn>", first));
+        }
+
+        self.parse_atom().map(|(atom, _limits)| atom)
+    }
+
+    // `{...}` の中身をそのまま文字列として読む(\text や環境名など)
+    fn parse_raw_group(&mut self) -> Result<String, String> {
+        self.skip_spaces();
+        if self.next() != Some(Token::BeginGroup) {
+            return Err("expected `{`".to_string());
+        }
+
+        let mut text = String::new();
+        let mut depth = 0;
+        loop {
+            match self.next() {
+                None => return Err("missing `}`".to_string()),
+                Some(Token::EndGroup) if depth == 0 => break,
+                Some(Token::EndGroup) => {
+                    depth -= 1;
+                    text.push('}');
+                }
+                Some(Token::BeginGroup) => {
+                    depth += 1;
+                    text.push('{');
+                }
+                Some(Token::Char(c)) => text.push(c),
+                Some(Token::Number(n)) => text.push_str(&n),
+                Some(Token::Space) => text.push(' '),
+                Some(Token::Sub) => text.push('_'),
+                Some(Token::Sup) => text.push('^'),
+                Some(Token::Align) => text.push('&'),
+                Some(Token::NewRow) => text.push_str("\\\\"),
+                Some(Token::Command(name)) => match identifier_symbol(&name) {
+                    Some(symbol) => text.push_str(symbol),
+                    None if space_width(&name).is_some() => text.push(' '),
+                    None => {
+                        text.push('\\');
+                        text.push_str(&name);
+                    }
+                },
+            }
+        }
+
+        Ok(text)
+    }
+
+    fn parse_delimiter(&mut self) -> Result<String, String> {
+        self.skip_spaces();
+        match self.next() {
+            Some(Token::Char('.')) => Ok(String::new()),
+            Some(Token::Char(c)) if "()[]|/".contains(c) => Ok(c.to_string()),
+            Some(Token::Command(name)) => operator_symbol(&name)
+                .map(str::to_string)
+                .ok_or_else(|| format!("unsupported delimiter \\{}", name)),
+            _ => Err("missing delimiter".to_string()),
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<(String, bool), String> {
+        self.skip_spaces();
+        let atom = match self.next() {
+            None => return Err("unexpected end of expression".to_string()),
+            Some(Token::BeginGroup) => {
+                let inner = self.parse_row(false)?;
+                self.expect_end_group()?;
+                format!("<mrow>{}</mrow>", inner)
+            }
+            Some(Token::Number(n)) => format!("<mn>{}</mn>", n),
+            Some(Token::Char(c)) if c.is_alphabetic() => format!("<mi>{}</mi>", c),
+            Some(Token::Char(c)) => {
+                let op = match c {
+                    '-' => "−".to_string(),
+                    '*' => "∗".to_string(),
+                    '\'' => "′".to_string(),
+                    '+' | '=' | '<' | '>' | ',' | ';' | ':' | '!' | '/' | '.' | '?' | '|' => {
+                        c.to_string()
+                    }
+                    // 括弧は \left \right のときだけ伸縮させる
+                    '(' | ')' | '[' | ']' => {
+                        return Ok((format!("<mo stretchy=\"false\">{}</mo>", c), false));
+                    }
+                    _ => return Err(format!("unsupported character `{}`", c)),
+                };
+                format!("<mo>{}</mo>", escape_html(&op))
+            }
+            Some(Token::Command(name)) => return self.parse_command(&name),
+            Some(Token::EndGroup) => return Err("unexpected `}`".to_string()),
+            Some(Token::Sub) | Some(Token::Sup) => {
+                return Err("missing base for script".to_string());
+            }
+            Some(Token::Align) => return Err("unexpected `&`".to_string()),
+            Some(Token::NewRow) => return Err("unexpected `\\\\`".to_string()),
+            Some(Token::Space) => unreachable!("spaces are skipped"),
+        };
+
+        Ok((atom, false))
+    }
+
+    fn parse_command(&mut self, name: &str) -> Result<(String, bool), String> {
+        if let Some(c) = greek_letter(name) {
+            // 大文字のギリシャ文字は立体
+            let variant = if c.is_uppercase() {
+                " mathvariant=\"normal\""
+            } else {
+                ""
+            };
+            return Ok((format!("<mi{}>{}</mi>", variant, c), false));
+        }
+        if let Some(op) = operator_symbol(name) {
+            return Ok((format!("<mo>{}</mo>", escape_html(op)), false));
+        }
+        if let Some(symbol) = identifier_symbol(name) {
+            return Ok((format!("<mi>{}</mi>", escape_html(symbol)), false));
+        }
+        if let Some((op, limits)) = large_operator(name) {
+            let attrs = if limits {
+                " largeop=\"true\" movablelimits=\"true\""
+            } else {
+                " largeop=\"true\""
+            };
+            return Ok((format!("<mo{}>{}</mo>", attrs, op), limits));
+        }
+        if let Some(limits) = function_name(name) {
+            return Ok((format!("<mi>{}</mi>", name), limits));
+        }
+        if let Some(width) = space_width(name) {
+            return Ok((format!("<mspace width=\"{}\" />", width), false));
+        }
+        if let Some((symbol, under)) = accent(name) {
+            let base = self.parse_argument()?;
+            let tag = if under { "munder" } else { "mover" };
+            let attr = if under { "accentunder" } else { "accent" };
+            return Ok((
+                format!(
+                    "<{0} {1}=\"true\">{2}<mo>{3}</mo></{0}>",
+                    tag, attr, base, symbol
+                ),
+                false,
+            ));
+        }
+
+        let atom = match name {
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.parse_argument()?;
+                let denominator = self.parse_argument()?;
+                format!("<mfrac>{}{}</mfrac>", numerator, denominator)
+            }
+            "binom" => {
+                let n = self.parse_argument()?;
+                let k = self.parse_argument()?;
+                format!(
+                    "<mrow><mo>(</mo><mfrac linethickness=\"0\">{}{}</mfrac><mo>)</mo></mrow>",
+                    n, k
+                )
+            }
+            "sqrt" => {
+                self.skip_spaces();
+                if self.peek() == Some(&Token::Char('[')) {
+                    self.next();
+                    let index = self.parse_row(true)?;
+                    if self.next() != Some(Token::Char(']')) {
+                        return Err("missing `]`".to_string());
+                    }
+                    let radicand = self.parse_argument()?;
+                    format!("<mroot>{}<mrow>{}</mrow></mroot>", radicand, index)
+                } else {
+                    format!("<msqrt>{}</msqrt>", self.parse_argument()?)
+                }
+            }
+            "text" | "textrm" | "textit" | "textbf" | "mbox" => {
+                format!("<mtext>{}</mtext>", escape_html(&self.parse_raw_group()?))
+            }
+            "mathrm" | "operatorname" => {
+                let text = self.parse_raw_group()?;
+                let variant = if text.chars().count() == 1 {
+                    " mathvariant=\"normal\""
+                } else {
+                    ""
+                };
+                format!("<mi{}>{}</mi>", variant, escape_html(&text))
+            }
+            "mathbf" | "boldsymbol" | "mathbb" | "mathcal" | "mathscr" | "mathfrak" => {
+                let text = self.parse_raw_group()?;
+                let styled = text
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .map(|c| styled_char(name, c))
+                    .collect::<Option<String>>()
+                    .ok_or_else(|| format!("unsupported character in \\{}", name))?;
+                format!("<mi>{}</mi>", escape_html(&styled))
+            }
+            "left" => {
+                let open = self.parse_delimiter()?;
+                let inner = self.parse_row(false)?;
+                self.skip_spaces();
+                if self.next() != Some(Token::Command("right".to_string())) {
+                    return Err("missing \\right".to_string());
+                }
+                let close = self.parse_delimiter()?;
+                format!("<mrow>{}{}{}</mrow>", fence(&open), inner, fence(&close))
+            }
+            "begin" => {
+                let env = self.parse_raw_group()?;
+                self.parse_environment(&env)?
+            }
+            "right" | "end" => return Err(format!("unexpected \\{}", name)),
+            _ => return Err(format!("unsupported command \\{}", name)),
+        };
+
+        Ok((atom, false))
+    }
+
+    fn parse_environment(&mut self, env: &str) -> Result<String, String> {
+        // (開き括弧, 閉じ括弧, 列の揃え方)
+        let (open, close, align) = match env {
+            "matrix" | "smallmatrix" => ("", "", TableAlign::Center),
+            "pmatrix" => ("(", ")", TableAlign::Center),
+            "bmatrix" => ("[", "]", TableAlign::Center),
+            "Bmatrix" => ("{", "}", TableAlign::Center),
+            "vmatrix" => ("|", "|", TableAlign::Center),
+            "Vmatrix" => ("‖", "‖", TableAlign::Center),
+            "cases" => ("{", "", TableAlign::Left),
+            "aligned" | "align" | "align*" | "split" => ("", "", TableAlign::Aligned),
+            "gathered" | "gather" | "gather*" => ("", "", TableAlign::Center),
+            "array" => {
+                // 列指定は読み捨てる
+                self.parse_raw_group()?;
+                ("", "", TableAlign::Center)
+            }
+            _ => return Err(format!("unsupported environment `{}`", env)),
+        };
+
+        let rows = self.parse_table()?;
+
+        self.skip_spaces();
+        if self.next() != Some(Token::Command("end".to_string())) {
+            return Err(format!("missing \\end{{{}}}", env));
+        }
+        if self.parse_raw_group()? != env {
+            return Err(format!("mismatched \\end for `{}`", env));
+        }
+
+        Ok(format!(
+            "<mrow>{}{}{}</mrow>",
+            fence(open),
+            table_html(&rows, align),
+            fence(close)
+        ))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum TableAlign {
+    Center,
+    Left,
+    // aligned環境: 右揃えと左揃えを交互に並べる
+    Aligned,
+}
+
+fn table_html(rows: &[Vec<String>], align: TableAlign) -> String {
+    let columns = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    let attrs = match align {
+        TableAlign::Center => String::new(),
+        TableAlign::Left => " columnalign=\"left\"".to_string(),
+        TableAlign::Aligned => format!(
+            " columnalign=\"{}\" columnspacing=\"0em\" displaystyle=\"true\"",
+            (0..columns)
+                .map(|i| if i % 2 == 0 { "right" } else { "left" })
+                .collect::<Vec<_>>()
+                .join(" ")
+        ),
+    };
+
+    let rows_html = rows
+        .iter()
+        .map(|row| {
+            let cells = row
+                .iter()
+                .map(|cell| format!("<mtd>{}</mtd>", cell))
+                .collect::<String>();
+            format!("<mtr>{}</mtr>", cells)
+        })
+        .collect::<String>();
+
+    format!("<mtable{}>{}</mtable>", attrs, rows_html)
+}
+
+pub fn tex_to_mathml(tex: &str, display: bool) -> Result<String, String> {
+    let mut parser = MathParser {
+        tokens: tokenize(tex)?,
+        pos: 0,
+    };
+
+    // `&` と `\\` は環境の中でしか使えない。勝手に表にせず、元のTeXのまま出す
+    let body = parser.parse_row(false)?;
+    match parser.peek() {
+        None => {}
+        Some(Token::Align) => return Err("`&` outside of an environment".to_string()),
+        Some(Token::NewRow) => return Err("`\\\\` outside of an environment".to_string()),
+        Some(token) => return Err(format!("unexpected {:?}", token)),
+    }
+
+    let display_attr = if display { " display=\"block\"" } else { "" };
+
+    Ok(format!(
+        "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"{}>{}</math>",
+        display_attr, body
+    ))
+}
+
+// 変換できない数式を警告として集める
+pub fn check_math(doc: &mut Document) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut check = |tex: &str, display: bool| {
+        if let Err(e) = tex_to_mathml(tex, display) {
+            warnings.push(format!("could not convert math `{}`: {}", tex, e));
+        }
+    };
+
+    for block in &mut doc.blocks {
+        block.walk_blocks_mut(&mut |block| {
+            if let Block::MathBlock(tex) = block {
+                check(tex, true);
+            }
+        });
+        block.walk_inlines_mut(&mut |inline| {
+            if let Inline::Math { tex, display } = inline {
+                check(tex, *display);
+            }
+        });
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inner(tex: &str) -> String {
+        tex_to_mathml(tex, false)
+            .unwrap()
+            .trim_start_matches("<math xmlns=\"http://www.w3.org/1998/Math/MathML\">")
+            .trim_end_matches("</math>")
+            .to_string()
+    }
+
+    #[test]
+    fn test_scripts_and_fractions() {
+        assert_eq!(inner("x^2"), "<msup><mi>x</mi><mn>2</mn></msup>");
+        assert_eq!(
+            inner("a_{ij}^{n+1}"),
+            "<msubsup><mi>a</mi><mrow><mi>i</mi><mi>j</mi></mrow><mrow><mi>n</mi><mo>+</mo><mn>1</mn></mrow></msubsup>"
+        );
+        assert_eq!(inner("\\frac12"), "<mfrac><mn>1</mn><mn>2</mn></mfrac>");
+        assert_eq!(
+            inner("\\frac{a}{b}"),
+            "<mfrac><mrow><mi>a</mi></mrow><mrow><mi>b</mi></mrow></mfrac>"
+        );
+        assert_eq!(inner("f'"), "<msup><mi>f</mi><mo>′</mo></msup>");
+    }
+
+    #[test]
+    fn test_symbols() {
+        assert_eq!(
+            inner("\\alpha \\leq \\Omega"),
+            "<mi>α</mi><mo>≤</mo><mi mathvariant=\"normal\">Ω</mi>"
+        );
+        assert_eq!(inner("3.14"), "<mn>3.14</mn>");
+        assert_eq!(inner("a<b"), "<mi>a</mi><mo>&lt;</mo><mi>b</mi>");
+        assert_eq!(inner("\\mathbb{R}"), "<mi>ℝ</mi>");
+        assert_eq!(
+            inner("\\sum_{i=1}^n"),
+            "<munderover><mo largeop=\"true\" movablelimits=\"true\">∑</mo><mrow><mi>i</mi><mo>=</mo><mn>1</mn></mrow><mi>n</mi></munderover>"
+        );
+    }
+
+    #[test]
+    fn test_roots_and_fences() {
+        assert_eq!(inner("\\sqrt{x}"), "<msqrt><mrow><mi>x</mi></mrow></msqrt>");
+        assert_eq!(
+            inner("\\sqrt[3]{x}"),
+            "<mroot><mrow><mi>x</mi></mrow><mrow><mn>3</mn></mrow></mroot>"
+        );
+        assert_eq!(
+            inner("\\left( x \\right."),
+            "<mrow><mo>(</mo><mi>x</mi></mrow>"
+        );
+    }
+
+    #[test]
+    fn test_environments() {
+        assert_eq!(
+            inner("\\begin{pmatrix} a & b \\\\ c & d \\end{pmatrix}"),
+            "<mrow><mo>(</mo><mtable><mtr><mtd><mi>a</mi></mtd><mtd><mi>b</mi></mtd></mtr><mtr><mtd><mi>c</mi></mtd><mtd><mi>d</mi></mtd></mtr></mtable><mo>)</mo></mrow>"
+        );
+        assert_eq!(
+            inner("\\begin{aligned} x &= 1 \\\\ \\end{aligned}"),
+            "<mrow><mtable columnalign=\"right left\" columnspacing=\"0em\" displaystyle=\"true\"><mtr><mtd><mi>x</mi></mtd><mtd><mo>=</mo><mn>1</mn></mtd></mtr></mtable></mrow>"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(tex_to_mathml("\\unknown", false).is_err());
+        assert!(tex_to_mathml("\\frac{a}", false).is_err());
+        assert!(tex_to_mathml("{a", false).is_err());
+        assert!(tex_to_mathml("a}", false).is_err());
+        assert!(tex_to_mathml("\\begin{pmatrix} a \\end{bmatrix}", false).is_err());
+        assert!(tex_to_mathml("x^1^2", false).is_err());
+        assert_eq!(
+            tex_to_mathml("a & b", true),
+            Err("`&` outside of an environment".to_string())
+        );
+        assert_eq!(
+            tex_to_mathml("a \\\\ b", true),
+            Err("`\\\\` outside of an environment".to_string())
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            tex_to_mathml("x", true),
+            Ok("<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"block\"><mi>x</mi></math>".to_string())
+        );
+    }
+}
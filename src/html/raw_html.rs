@@ -0,0 +1,562 @@
+use crate::parser::Parser;
+use crate::parser::document::ast::{Block, Document, Inline};
+use crate::parser::document::html::{HtmlTag, parse_html_special, parse_html_tag};
+use clap::ValueEnum;
+
+// Markdownに書かれた生のHTMLの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Default, ValueEnum)]
+pub enum HtmlPolicy {
+    // 文字として表示する
+    #[default]
+    Escape,
+    // そのまま出力する
+    Allow,
+    // 許可したタグと属性だけを残す
+    Sanitize,
+}
+
+const ALLOWED_TAGS: &[&str] = &[
+    "a",
+    "abbr",
+    "b",
+    "blockquote",
+    "br",
+    "caption",
+    "cite",
+    "code",
+    "col",
+    "colgroup",
+    "dd",
+    "del",
+    "details",
+    "dfn",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "mark",
+    "ol",
+    "p",
+    "pre",
+    "q",
+    "rp",
+    "rt",
+    "ruby",
+    "s",
+    "samp",
+    "small",
+    "span",
+    "strong",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "time",
+    "tr",
+    "u",
+    "ul",
+    "var",
+    "wbr",
+];
+
+// 中身ごと取り除くタグ
+const REMOVED_WITH_CONTENT: &[&str] = &[
+    "script", "style", "iframe", "object", "embed", "template", "noscript", "textarea",
+];
+
+const GLOBAL_ATTRIBUTES: &[&str] = &["class", "title", "lang", "dir"];
+
+const TAG_ATTRIBUTES: &[(&str, &[&str])] = &[
+    ("a", &["href"]),
+    ("img", &["src", "alt", "width", "height", "loading"]),
+    ("td", &["colspan", "rowspan", "align"]),
+    ("th", &["colspan", "rowspan", "align"]),
+    ("ol", &["start", "type", "reversed"]),
+    ("li", &["value"]),
+    ("details", &["open"]),
+    ("time", &["datetime"]),
+    ("q", &["cite"]),
+    ("blockquote", &["cite"]),
+    ("del", &["cite", "datetime"]),
+    ("ins", &["cite", "datetime"]),
+    ("col", &["span"]),
+    ("colgroup", &["span"]),
+];
+
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "cite"];
+
+// 終了タグのない要素
+const VOID_TAGS: &[&str] = &["br", "col", "hr", "img", "wbr"];
+
+// 描画前に生のHTMLをポリシーに合わせて書き換える
+pub fn apply_html_policy(doc: &mut Document, policy: HtmlPolicy) -> Vec<String> {
+    let mut rewriter = HtmlRewriter {
+        policy,
+        warnings: Vec::new(),
+        url_warnings: Vec::new(),
+    };
+    rewriter.blocks(&mut doc.blocks);
+
+    let HtmlRewriter {
+        mut warnings,
+        url_warnings,
+        ..
+    } = rewriter;
+    for warning in url_warnings {
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
+    warnings
+}
+
+struct HtmlRewriter {
+    policy: HtmlPolicy,
+    warnings: Vec<String>,
+    url_warnings: Vec<String>,
+}
+
+impl HtmlRewriter {
+    fn add_warnings(&mut self, removed: Vec<String>) {
+        for warning in removed {
+            if !self.warnings.contains(&warning) {
+                self.warnings.push(warning);
+            }
+        }
+    }
+
+    // openは開いたままのタグ。閉じていないscriptなどの名前も返す
+    fn rewrite(&mut self, html: &str, open: &mut Vec<String>) -> (String, Option<String>) {
+        let (sanitized, removed, unclosed) = sanitize_fragment(html, open);
+        self.add_warnings(removed);
+        (sanitized, unclosed)
+    }
+
+    // 開いたままのタグを閉じるHTML
+    fn close(&mut self, open: &mut Vec<String>) -> String {
+        let mut removed = Vec::new();
+        let html = close_tags(open, &mut removed);
+        self.add_warnings(removed);
+        html
+    }
+
+    // 同じ並びのHTMLブロックの間でタグの対応をとる
+    // `<details>` と `</details>` の間にMarkdownを書けるように、並びの終わりまで閉じずに待つ
+    fn blocks(&mut self, blocks: &mut Vec<Block>) {
+        let mut open = Vec::new();
+        for block in blocks.iter_mut() {
+            self.block(block, &mut open);
+        }
+        if !open.is_empty() {
+            let html = self.close(&mut open);
+            blocks.push(Block::Html(html));
+        }
+    }
+
+    fn block(&mut self, block: &mut Block, open: &mut Vec<String>) {
+        match block {
+            Block::Html(html) => match self.policy {
+                HtmlPolicy::Escape => *block = Block::Paragraph(vec![Inline::Text(html.clone())]),
+                HtmlPolicy::Allow => {}
+                HtmlPolicy::Sanitize => *html = self.rewrite(html, open).0,
+            },
+            Block::Heading { content, .. } | Block::Paragraph(content) => self.inlines(content),
+            Block::Callout { title, blocks, .. } => {
+                if let Some(title) = title {
+                    self.inlines(title);
+                }
+                self.blocks(blocks);
+            }
+            Block::Blockquote(blocks)
+            | Block::FootnoteDefinition { blocks, .. }
+            | Block::Transclusion { blocks, .. } => self.blocks(blocks),
+            Block::Identified { block, .. } => self.block(block, open),
+            Block::List(list) => {
+                for item in list.items_mut() {
+                    self.blocks(&mut item.blocks);
+                }
+            }
+            Block::Table { headers, rows, .. } => {
+                for cell in headers.iter_mut().chain(rows.iter_mut().flatten()) {
+                    self.inlines(cell);
+                }
+            }
+            Block::FencedCodeBlock { .. }
+            | Block::HorizontalRule
+            | Block::MathBlock(_)
+            | Block::Embed(_) => {}
+        }
+    }
+
+    // 段落などのインラインの並び。開いたままのタグは並びの最後で閉じる
+    fn inlines(&mut self, inlines: &mut Vec<Inline>) {
+        let policy = self.policy;
+        let mut open = Vec::new();
+        // インラインの `<script>` と `</script>` は別々のInline::Htmlになるので、間にあるものも消す
+        let mut skipping: Option<String> = None;
+        for inline in inlines.iter_mut() {
+            inline.walk_mut(&mut |inline| match inline {
+                _ if skipping.is_some() => {
+                    // 強調などの中に閉じタグがあるかもしれないので、子要素は残してたどる
+                    let container = matches!(
+                        inline,
+                        Inline::Strong(_)
+                            | Inline::Italic(_)
+                            | Inline::Strikethrough(_)
+                            | Inline::Link { .. }
+                    );
+                    let name = skipping.as_deref().unwrap_or_default();
+                    let after = match inline {
+                        Inline::Html(html) => skip_element_content(html, name),
+                        _ => None,
+                    };
+                    match after {
+                        Some(after) => {
+                            let (sanitized, unclosed) = self.rewrite(after, &mut open);
+                            skipping = unclosed;
+                            *inline = Inline::Html(sanitized);
+                        }
+                        None if container => {}
+                        None => *inline = Inline::Text(String::new()),
+                    }
+                }
+                Inline::Html(html) => match policy {
+                    HtmlPolicy::Escape => *inline = Inline::Text(html.clone()),
+                    HtmlPolicy::Allow => {}
+                    HtmlPolicy::Sanitize => {
+                        let (sanitized, unclosed) = self.rewrite(html, &mut open);
+                        *html = sanitized;
+                        skipping = unclosed;
+                    }
+                },
+                // Markdownのリンクと画像のURLも生のHTMLの属性と同じように確かめる
+                Inline::Link { url, .. } if policy == HtmlPolicy::Sanitize && !is_safe_url(url) => {
+                    self.url_warnings
+                        .push("removed unsafe URL of a link".to_string());
+                    *url = "#".to_string();
+                }
+                Inline::Image { alt, url }
+                    if policy == HtmlPolicy::Sanitize && !is_safe_url(url) =>
+                {
+                    self.url_warnings
+                        .push("removed unsafe URL of an image".to_string());
+                    *inline = Inline::Text(alt.clone());
+                }
+                _ => {}
+            });
+        }
+        if !open.is_empty() {
+            let html = self.close(&mut open);
+            inlines.push(Inline::Html(html));
+        }
+    }
+}
+
+// 許可リストにないタグと属性、コメントなどを取り除く
+// 対応しない閉じタグは捨て、開いたままのタグは最後に閉じる。取り除いたものを警告として返す
+pub fn sanitize_html(html: &str) -> (String, Vec<String>) {
+    let mut open = Vec::new();
+    let (mut output, mut warnings, _) = sanitize_fragment(html, &mut open);
+    output.push_str(&close_tags(&mut open, &mut warnings));
+    (output, warnings)
+}
+
+// 開いたままのタグを内側から順に閉じる
+fn close_tags(open: &mut Vec<String>, warnings: &mut Vec<String>) -> String {
+    open.drain(..)
+        .rev()
+        .map(|name| {
+            let warning = format!("closed unclosed <{}> in raw HTML", name);
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+            format!("</{}>", name)
+        })
+        .collect()
+}
+
+// sanitize_htmlのうち、タグを閉じずにopenに残すもの
+// 中身ごと取り除く要素が閉じないまま終わったらその名前を返す
+fn sanitize_fragment(html: &str, open: &mut Vec<String>) -> (String, Vec<String>, Option<String>) {
+    let mut output = String::new();
+    let mut warnings = Vec::new();
+    let mut rest = html;
+
+    while let Some(index) = rest.find('<') {
+        output.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        if let Some((tag, next)) = parse_html_tag().parse(rest) {
+            let name = tag.name.to_ascii_lowercase();
+            rest = next;
+
+            if REMOVED_WITH_CONTENT.contains(&name.as_str()) {
+                warnings.push(format!("removed <{}> from raw HTML", name));
+                if !tag.closing && !tag.self_closing {
+                    match skip_element_content(rest, &name) {
+                        Some(after) => rest = after,
+                        None => {
+                            warnings.dedup();
+                            return (output, warnings, Some(name));
+                        }
+                    }
+                }
+            } else if !ALLOWED_TAGS.contains(&name.as_str()) {
+                warnings.push(format!("removed <{}> from raw HTML", name));
+            } else if tag.closing {
+                // 内側で開いたままのタグもここで閉じる
+                match open.iter().rposition(|open| *open == name) {
+                    Some(i) => open
+                        .drain(i..)
+                        .rev()
+                        .for_each(|name| output.push_str(&format!("</{}>", name))),
+                    None => warnings.push(format!("removed unmatched </{}> from raw HTML", name)),
+                }
+            } else {
+                output.push_str(&sanitize_tag(&tag, &name, &mut warnings));
+                if !tag.self_closing && !VOID_TAGS.contains(&name.as_str()) {
+                    open.push(name);
+                }
+            }
+        } else if let Some((_, next)) = parse_html_special().parse(rest) {
+            rest = next;
+        } else {
+            output.push_str("&lt;");
+            rest = &rest[1..];
+        }
+    }
+    output.push_str(rest);
+
+    warnings.dedup();
+    (output, warnings, None)
+}
+
+// 閉じタグの直後まで読み飛ばす。閉じタグがなければNone
+fn skip_element_content<'a>(input: &'a str, name: &str) -> Option<&'a str> {
+    let lower = input.to_ascii_lowercase();
+    let close = format!("</{}", name);
+    let index = lower.find(close.as_str())?;
+    Some(match input[index..].find('>') {
+        Some(end) => &input[index + end + 1..],
+        None => "",
+    })
+}
+
+// 開始タグから許可した属性だけを残す
+fn sanitize_tag(tag: &HtmlTag, name: &str, warnings: &mut Vec<String>) -> String {
+    let tag_attributes = TAG_ATTRIBUTES
+        .iter()
+        .find(|(tag_name, _)| *tag_name == name)
+        .map_or(&[][..], |(_, attributes)| *attributes);
+
+    let mut html = format!("<{}", name);
+    for (attribute, value) in &tag.attributes {
+        let attribute = attribute.to_ascii_lowercase();
+        let allowed = GLOBAL_ATTRIBUTES.contains(&attribute.as_str())
+            || tag_attributes.contains(&attribute.as_str());
+        let safe = !URL_ATTRIBUTES.contains(&attribute.as_str()) || value.is_some_and(is_safe_url);
+        if !allowed || !safe {
+            warnings.push(format!(
+                "removed {} attribute of <{}> from raw HTML",
+                attribute, name
+            ));
+            continue;
+        }
+
+        match value {
+            Some(value) => html.push_str(&format!(
+                " {}=\"{}\"",
+                attribute,
+                value
+                    .replace('"', "&quot;")
+                    .replace('<', "&lt;")
+                    .replace('>', "&gt;")
+            )),
+            None => html.push_str(&format!(" {}", attribute)),
+        }
+    }
+    html.push_str(if tag.self_closing { " />" } else { ">" });
+
+    html
+}
+
+// http(s)とmailto、スキームのない相対URLだけを許可する
+// 文字参照でスキームを隠したものは許可しない
+fn is_safe_url(url: &str) -> bool {
+    let url = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    let head = &url[..url.find(['/', '?', '#']).unwrap_or(url.len())];
+    if head.contains('&') {
+        return false;
+    }
+
+    match head.split_once(':') {
+        Some((scheme, _)) => ["http", "https", "mailto"].contains(&scheme),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::convert::ToHtml;
+    use crate::parser::document::parse_document;
+
+    #[test]
+    fn test_sanitize_html() {
+        let (html, warnings) = sanitize_html(
+            "<details open onclick=\"x()\"><summary>A</summary>\n<script>alert('<b>')</script><kbd>K</kbd><!-- c --> 1 < 2</details>",
+        );
+
+        assert_eq!(
+            html,
+            "<details open><summary>A</summary>\n<kbd>K</kbd> 1 &lt; 2</details>"
+        );
+        assert_eq!(
+            warnings,
+            vec![
+                "removed onclick attribute of <details> from raw HTML".to_string(),
+                "removed <script> from raw HTML".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sanitize_urls() {
+        assert_eq!(
+            sanitize_html("<a href=\"https://example.com/?a=1&amp;b=2\" title=t>").0,
+            "<a href=\"https://example.com/?a=1&amp;b=2\" title=\"t\"></a>"
+        );
+        assert_eq!(
+            sanitize_html("<a href=\"JavaScript:alert(1)\">").0,
+            "<a></a>"
+        );
+        assert_eq!(
+            sanitize_html("<a href=\"jav&#x61;script:alert(1)\">").0,
+            "<a></a>"
+        );
+        assert_eq!(
+            sanitize_html("<img src='../images/a.png' alt=\"a\"/>").0,
+            "<img src=\"../images/a.png\" alt=\"a\" />"
+        );
+        assert_eq!(
+            sanitize_html("<iframe src=\"https://example.com\"></iframe>x").0,
+            "x"
+        );
+    }
+
+    #[test]
+    fn test_apply_html_policy() {
+        let input = "<div onclick=\"x()\">\n\nPress <kbd>K</kbd><script>\n";
+        let (doc, _) = parse_document()(input).unwrap();
+
+        let mut escaped = doc.clone();
+        apply_html_policy(&mut escaped, HtmlPolicy::Escape);
+        assert_eq!(
+            escaped.blocks[0],
+            Block::Paragraph(vec![Inline::Text("<div onclick=\"x()\">".to_string())])
+        );
+
+        let mut allowed = doc.clone();
+        apply_html_policy(&mut allowed, HtmlPolicy::Allow);
+        assert_eq!(allowed, doc);
+
+        let mut sanitized = doc.clone();
+        let warnings = apply_html_policy(&mut sanitized, HtmlPolicy::Sanitize);
+        assert_eq!(sanitized.blocks[0], Block::Html("<div>".to_string()));
+        assert_eq!(
+            sanitized.blocks[1],
+            Block::Paragraph(vec![
+                Inline::Text("Press ".to_string()),
+                Inline::Html("<kbd>".to_string()),
+                Inline::Text("K".to_string()),
+                Inline::Html("</kbd>".to_string()),
+                Inline::Html(String::new()),
+            ])
+        );
+        assert_eq!(sanitized.blocks[2], Block::Html("</div>".to_string()));
+        assert_eq!(warnings.len(), 3);
+    }
+
+    #[test]
+    fn test_sanitize_unbalanced_tags() {
+        let (html, warnings) = sanitize_html("</div><div><p>a<b>b</p></span>");
+        assert_eq!(html, "<div><p>a<b>b</b></p></div>");
+        assert_eq!(
+            warnings,
+            vec![
+                "removed unmatched </div> from raw HTML".to_string(),
+                "removed unmatched </span> from raw HTML".to_string(),
+                "closed unclosed <div> in raw HTML".to_string(),
+            ]
+        );
+
+        // 間にMarkdownを挟んだHTMLブロックの対応はとり、入れ子の中で開いたタグはその中で閉じる
+        let input = "<details>\n\ntext\n\n</details>\n</div>\n\n> <div>\n\nafter <span>x\n";
+        let (mut doc, _) = parse_document()(input).unwrap();
+        apply_html_policy(&mut doc, HtmlPolicy::Sanitize);
+        assert_eq!(
+            doc.to_html(),
+            "<details>\n<p>text</p></details>\n\n<blockquote><div>\n</div>\n</blockquote><p>after <span>x</span></p>"
+        );
+    }
+
+    #[test]
+    fn test_sanitize_inline_script() {
+        let input = "a <script>alert(1)</script> b <style>p{}</style><b>c</b> <iframe>**x** y\n";
+        let (mut doc, _) = parse_document()(input).unwrap();
+        apply_html_policy(&mut doc, HtmlPolicy::Sanitize);
+
+        assert_eq!(doc.to_html(), "<p>a  b <b>c</b> <strong></strong></p>");
+        assert_eq!(
+            sanitize_html("<script>x</script>y<style>").0,
+            "y".to_string()
+        );
+    }
+
+    #[test]
+    fn test_sanitize_markdown_urls() {
+        let input = "[x](javascript:alert%281%29) [y](data:text/html,hi) ![a](data:image/svg+xml,z) [ok](https://example.com) ![b](b.png)\n";
+        let (doc, _) = parse_document()(input).unwrap();
+
+        let mut allowed = doc.clone();
+        assert!(apply_html_policy(&mut allowed, HtmlPolicy::Allow).is_empty());
+        assert_eq!(allowed, doc);
+
+        let mut sanitized = doc.clone();
+        let warnings = apply_html_policy(&mut sanitized, HtmlPolicy::Sanitize);
+        assert_eq!(
+            sanitized.to_html(),
+            "<p><a href=\"#\">x</a> <a href=\"#\">y</a> a <a href=\"https://example.com\">ok</a> <img src=\"b.png\" alt=\"b\" /></p>"
+        );
+        assert_eq!(
+            warnings,
+            vec![
+                "removed unsafe URL of a link".to_string(),
+                "removed unsafe URL of an image".to_string(),
+            ]
+        );
+    }
+}
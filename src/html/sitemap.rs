@@ -0,0 +1,91 @@
+use crate::config::Config;
+use crate::html::feed::{absolute_url, escape_xml, rfc3339};
+use crate::parser::document::front_matter::Date;
+
+pub const SITEMAP_PATH: &str = "/sitemap.xml";
+
+// 日付だけのときは `2024-03-05`、時刻があれば `2024-03-05T10:20:30+09:00`
+fn lastmod(date: &Date) -> String {
+    match date.time {
+        Some(_) => rfc3339(date),
+        None => format!("{:04}-{:02}-{:02}", date.year, date.month, date.day),
+    }
+}
+
+// pagesは (URL, 更新日)。noindexのページは呼び出し側で除いておく
+pub fn sitemap(pages: &[(String, Option<Date>)], config: &Config) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for (url, date) in pages {
+        xml.push_str("<url>\n");
+        xml.push_str(&format!(
+            "<loc>{}</loc>\n",
+            escape_xml(&absolute_url(config, url))
+        ));
+        if let Some(date) = date {
+            xml.push_str(&format!("<lastmod>{}</lastmod>\n", lastmod(date)));
+        }
+        xml.push_str("</url>\n");
+    }
+    xml.push_str("</urlset>\n");
+
+    xml
+}
+
+pub fn robots_txt(config: &Config) -> String {
+    format!(
+        "User-agent: *\nAllow: /\n\nSitemap: {}\n",
+        absolute_url(config, SITEMAP_PATH)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sitemap() {
+        let config = Config {
+            base_url: "https://example.com".to_string(),
+            ..Config::default()
+        };
+        let date = Date {
+            year: 2024,
+            month: 3,
+            day: 5,
+            time: None,
+            offset: None,
+        };
+        let pages = vec![
+            ("/".to_string(), None),
+            ("/a&b.html".to_string(), Some(date)),
+            (
+                "/tags/日記/".to_string(),
+                Some(Date {
+                    time: Some((10, 20, 30)),
+                    offset: Some(540),
+                    ..date
+                }),
+            ),
+        ];
+
+        assert_eq!(
+            sitemap(&pages, &config),
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+                "<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+                "<url>\n<loc>https://example.com/</loc>\n</url>\n",
+                "<url>\n<loc>https://example.com/a&amp;b.html</loc>\n",
+                "<lastmod>2024-03-05</lastmod>\n</url>\n",
+                "<url>\n<loc>https://example.com/tags/%E6%97%A5%E8%A8%98/</loc>\n",
+                "<lastmod>2024-03-05T10:20:30+09:00</lastmod>\n</url>\n",
+                "</urlset>\n",
+            )
+        );
+        assert_eq!(
+            robots_txt(&config),
+            "User-agent: *\nAllow: /\n\nSitemap: https://example.com/sitemap.xml\n"
+        );
+    }
+}
@@ -0,0 +1,27 @@
+// 見出しやタグをURLのフラグメント・パスに使える形にする
+// 英数字(ASCII以外も含む)と `-` `_` だけを残し、空白は `-` にする
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars() {
+        if c.is_alphanumeric() || c == '_' {
+            slug.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    slug.trim_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  Rust -- 2024 edition "), "rust-2024-edition");
+        assert_eq!(slugify("snake_case と 日本語"), "snake_case-と-日本語");
+        assert_eq!(slugify("???"), "");
+    }
+}
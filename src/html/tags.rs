@@ -0,0 +1,213 @@
+use crate::config::Config;
+use crate::html::index::{post_context, sort_posts};
+use crate::html::slug::slugify;
+use crate::html::template::engine::{TemplateError, Templates};
+use crate::html::template::render_page;
+use crate::html::to_html::HTMLDocument;
+use crate::parser::document::front_matter::Value;
+use percent_encoding::{CONTROLS, utf8_percent_encode};
+use std::collections::BTreeMap;
+
+// タグの一覧のURL。タグごとのページはこの下に作る
+pub const TAGS_PATH: &str = "/tags/";
+
+// `project/biotite` は階層ごとにslugにする。slugが空になる階層は捨てる
+pub fn tag_slug(tag: &str) -> String {
+    tag.split('/')
+        .map(slugify)
+        .filter(|slug| !slug.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// タグのページのURL。ASCII以外の文字はパーセントエンコードする
+pub fn tag_url(tag: &str) -> Option<String> {
+    let slug = tag_slug(tag);
+    if slug.is_empty() {
+        return None;
+    }
+
+    Some(format!(
+        "{}{}/",
+        TAGS_PATH,
+        utf8_percent_encode(&slug, CONTROLS)
+    ))
+}
+
+// テンプレートの `page.tags` の要素。ページを作れないタグはNone
+pub fn tag_context(tag: &str) -> Option<Value> {
+    let url = tag_url(tag)?;
+
+    Some(Value::Map(vec![
+        ("name".to_string(), Value::String(tag.to_string())),
+        ("url".to_string(), Value::String(url)),
+    ]))
+}
+
+pub fn tags_context(tags: &[&str]) -> Value {
+    Value::List(tags.iter().filter_map(|tag| tag_context(tag)).collect())
+}
+
+#[derive(Debug)]
+pub struct Tag {
+    // 最初に見つかった書き方
+    pub name: String,
+    // docsの添字。下の階層のタグが付いたページも含む
+    pub docs: Vec<usize>,
+}
+
+// slugの階層をキーにして、親のタグが子のタグの直前に並ぶようにする
+pub fn collect_tags(docs: &[HTMLDocument]) -> BTreeMap<Vec<String>, Tag> {
+    let mut tags: BTreeMap<Vec<String>, Tag> = BTreeMap::new();
+    for (i, doc) in docs.iter().enumerate() {
+        for tag in doc.tags.iter().flatten() {
+            let segments: Vec<&str> = tag
+                .split('/')
+                .filter(|segment| !slugify(segment).is_empty())
+                .collect();
+            for depth in 1..=segments.len() {
+                let key: Vec<String> = segments[..depth].iter().map(|s| slugify(s)).collect();
+                let entry = tags.entry(key).or_insert_with(|| Tag {
+                    name: segments[..depth].join("/"),
+                    docs: Vec::new(),
+                });
+                if entry.docs.last() != Some(&i) {
+                    entry.docs.push(i);
+                }
+            }
+        }
+    }
+
+    tags
+}
+
+fn tag_summary(key: &[String], tag: &Tag) -> Value {
+    Value::Map(vec![
+        ("name".to_string(), Value::String(tag.name.clone())),
+        (
+            "label".to_string(),
+            Value::String(tag.name.rsplit('/').next().unwrap_or_default().to_string()),
+        ),
+        (
+            "url".to_string(),
+            Value::String(tag_url(&tag.name).unwrap_or_default()),
+        ),
+        ("count".to_string(), Value::Integer(tag.docs.len() as i64)),
+        ("depth".to_string(), Value::Integer(key.len() as i64 - 1)),
+    ])
+}
+
+// タグの一覧とタグごとのページを (出力するパス, HTML) で返す。パスはエンコードしない
+pub fn tag_pages(
+    docs: &[HTMLDocument],
+    templates: &Templates,
+    config: &Config,
+) -> Result<Vec<(String, String)>, TemplateError> {
+    let tags = collect_tags(docs);
+    let mut pages = Vec::new();
+
+    let page = Value::Map(vec![
+        ("title".to_string(), Value::String("Tags".to_string())),
+        ("url".to_string(), Value::String(TAGS_PATH.to_string())),
+        (
+            "tags".to_string(),
+            Value::List(
+                tags.iter()
+                    .map(|(key, tag)| tag_summary(key, tag))
+                    .collect(),
+            ),
+        ),
+    ]);
+    pages.push((
+        TAGS_PATH.to_string(),
+        render_page(templates, "tags.html", config, page)?,
+    ));
+
+    for (key, tag) in &tags {
+        let children = tags
+            .iter()
+            .filter(|(child, _)| child.len() == key.len() + 1 && child.starts_with(key))
+            .map(|(child, tag)| tag_summary(child, tag))
+            .collect();
+        let mut posts: Vec<&HTMLDocument> = tag.docs.iter().map(|&i| &docs[i]).collect();
+        sort_posts(&mut posts);
+
+        let page = Value::Map(vec![
+            ("title".to_string(), Value::String(format!("#{}", tag.name))),
+            (
+                "url".to_string(),
+                Value::String(tag_url(&tag.name).unwrap_or_default()),
+            ),
+            ("tag".to_string(), tag_summary(key, tag)),
+            ("children".to_string(), Value::List(children)),
+            (
+                "posts".to_string(),
+                Value::List(posts.iter().map(|doc| post_context(doc)).collect()),
+            ),
+        ]);
+        pages.push((
+            format!("{}{}/", TAGS_PATH, key.join("/")),
+            render_page(templates, "tag.html", config, page)?,
+        ));
+    }
+
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::template::builtin_templates;
+
+    #[test]
+    fn test_tag_url() {
+        assert_eq!(tag_url("Rust"), Some("/tags/rust/".to_string()));
+        assert_eq!(
+            tag_url("project/Biotite"),
+            Some("/tags/project/biotite/".to_string())
+        );
+        assert_eq!(
+            tag_url("日記"),
+            Some("/tags/%E6%97%A5%E8%A8%98/".to_string())
+        );
+        assert_eq!(tag_url("a//b/"), Some("/tags/a/b/".to_string()));
+        assert_eq!(tag_url("???"), None);
+    }
+
+    #[test]
+    fn test_tag_pages() {
+        let docs = vec![
+            HTMLDocument::for_test("a", vec!["project/biotite", "Rust"], None),
+            HTMLDocument::for_test("b", vec!["project", "rust"], None),
+            HTMLDocument::for_test("c", vec!["日記"], None),
+        ];
+        let pages = tag_pages(&docs, &builtin_templates(), &Config::default()).unwrap();
+        let paths: Vec<&str> = pages.iter().map(|(path, _)| path.as_str()).collect();
+
+        assert_eq!(
+            paths,
+            vec![
+                "/tags/",
+                "/tags/project/",
+                "/tags/project/biotite/",
+                "/tags/rust/",
+                "/tags/日記/",
+            ]
+        );
+
+        let index = &pages[0].1;
+        assert!(index.contains(concat!(
+            "<li style=\"margin-left: 0em\"><a href=\"/tags/project/\">#project</a> (2)</li>\n",
+            "<li style=\"margin-left: 1em\"><a href=\"/tags/project/biotite/\">#biotite</a> (1)</li>\n",
+            "<li style=\"margin-left: 0em\"><a href=\"/tags/rust/\">#Rust</a> (2)</li>\n",
+        )));
+        assert!(index.contains("<a href=\"/tags/%E6%97%A5%E8%A8%98/\">#日記</a> (1)"));
+
+        let project = &pages[1].1;
+        assert!(project.contains("<title>#project</title>"));
+        assert!(project.contains("<a href=\"/tags/project/biotite/\">#biotite</a> (1)"));
+        assert!(project.contains("<a href=\"/a.html\">a</a>"));
+        assert!(project.contains("<a href=\"/b.html\">b</a>"));
+        assert!(!project.contains("<a href=\"/c.html\">c</a>"));
+    }
+}
@@ -1,66 +1,210 @@
-pub fn wrap_template(title: &str, body_content: &str) -> String {
-    format!(
-        r#"<!DOCTYPE html>
-<html lang="ja">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>{}</title>
-    <style>
-        body {{
-            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Helvetica, Arial, sans-serif;
-            line-height: 1.6;
-            color: #333;
-            max-width: 800px;
-            margin: 0 auto;
-            padding: 20px;
-        }}
-        pre {{
-            background-color: #f6f8fa;
-            padding: 16px;
-            border-radius: 6px;
-            overflow: auto;
-        }}
-        code {{
-            font-family: "SFMono-Regular", Consolas, "Liberation Mono", Menlo, Courier, monospace;
-            background-color: #f6f8fa;
-            padding: 0.2em 0.4em;
-            border-radius: 3px;
-        }}
-        pre code {{
-            padding: 0; /* preの中のcodeはpaddingなし */
-        }}
-        blockquote {{
-            margin: 0;
-            padding-left: 1em;
-            border-left: 4px solid #dfe2e5;
-            color: #6a737d;
-        }}
-        table {{
-            border-collapse: collapse;
-            width: 100%;
-        }}
-        th, td {{
-            border: 1px solid #dfe2e5;
-            padding: 6px 13px;
-        }}
-        hr {{
-            border: none;
-            border-bottom: 1px solid #dfe2e5;
-        }}
-        input[type="checkbox"] {{
-            margin-right: 0.5em;
-        }}
-        ul.task-list {{
-            list-style-type: none;
-            padding-left: 0;
-        }}
-    </style>
-</head>
-<body>
-{}
-</body>
-</html>"#,
-        title, body_content
-    )
+pub mod engine;
+
+use crate::config::Config;
+use crate::html::highlight::{HIGHLIGHT_CSS_PATH, HighlightMode};
+use crate::html::template::engine::{TemplateError, Templates};
+use crate::parser::document::front_matter::Value;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+// 組み込みのテンプレート。サイトのtemplatesディレクトリに同じ名前のファイルがあればそちらを使う
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    ("base.html", include_str!("template/base.html")),
+    ("page.html", include_str!("template/page.html")),
+    ("wide.html", include_str!("template/wide.html")),
+    ("index.html", include_str!("template/index.html")),
+    ("tags.html", include_str!("template/tags.html")),
+    ("tag.html", include_str!("template/tag.html")),
+    (
+        "partials/header.html",
+        include_str!("template/partials/header.html"),
+    ),
+    (
+        "partials/nav.html",
+        include_str!("template/partials/nav.html"),
+    ),
+    (
+        "partials/footer.html",
+        include_str!("template/partials/footer.html"),
+    ),
+    (
+        "partials/backlinks.html",
+        include_str!("template/partials/backlinks.html"),
+    ),
+];
+
+pub fn builtin_templates() -> Templates {
+    let mut templates = Templates::default();
+    for (name, source) in BUILTIN_TEMPLATES {
+        templates
+            .add(name, source)
+            .expect("built-in templates are valid");
+    }
+
+    templates
+}
+
+// templates_dirの `.html` を、ディレクトリからの相対パスを名前にして読み込む
+pub fn load_templates(templates_dir: &Path) -> Result<Templates, std::io::Error> {
+    let mut templates = builtin_templates();
+    if !templates_dir.is_dir() {
+        return Ok(templates);
+    }
+
+    for entry in WalkDir::new(templates_dir) {
+        let entry = entry.map_err(std::io::Error::other)?;
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension().is_none_or(|ext| ext != "html") {
+            continue;
+        }
+        let Ok(relative) = path.strip_prefix(templates_dir) else {
+            continue;
+        };
+        let name = relative.to_string_lossy().replace('\\', "/");
+        templates
+            .add(&name, &fs::read_to_string(path)?)
+            .map_err(std::io::Error::other)?;
+    }
+
+    Ok(templates)
+}
+
+// テンプレートの `site`
+pub fn site_context(config: &Config) -> Value {
+    // フィードは絶対URLが必要なので、base_urlがあるときだけ作る
+    let feeds = if config.base_url.is_empty() {
+        Vec::new()
+    } else {
+        config
+            .feed_formats
+            .iter()
+            .map(|format| {
+                Value::Map(vec![
+                    (
+                        "type".to_string(),
+                        Value::String(format.media_type().to_string()),
+                    ),
+                    (
+                        "url".to_string(),
+                        Value::String(format!("/{}", format.file_name())),
+                    ),
+                ])
+            })
+            .collect()
+    };
+
+    let highlight_css = match config.highlight {
+        HighlightMode::Class => HIGHLIGHT_CSS_PATH,
+        HighlightMode::Inline | HighlightMode::None => "",
+    };
+
+    Value::Map(vec![
+        ("feeds".to_string(), Value::List(feeds)),
+        ("title".to_string(), Value::String(config.title.clone())),
+        (
+            "base_url".to_string(),
+            Value::String(config.base_url.clone()),
+        ),
+        (
+            "language".to_string(),
+            Value::String(config.language.clone()),
+        ),
+        (
+            "highlight_css".to_string(),
+            Value::String(highlight_css.to_string()),
+        ),
+    ])
+}
+
+// pageはテンプレートの `page`
+pub fn render_page(
+    templates: &Templates,
+    name: &str,
+    config: &Config,
+    page: Value,
+) -> Result<String, TemplateError> {
+    let context = Value::Map(vec![
+        ("site".to_string(), site_context(config)),
+        ("page".to_string(), page),
+    ]);
+
+    templates.render(name, &context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_page() {
+        let page = Value::Map(vec![
+            ("title".to_string(), Value::String("A & B".to_string())),
+            ("url".to_string(), Value::String("/a.html".to_string())),
+            (
+                "content".to_string(),
+                Value::String("<p>body</p>".to_string()),
+            ),
+            (
+                "backlinks".to_string(),
+                Value::List(vec![Value::Map(vec![
+                    ("title".to_string(), Value::String("C".to_string())),
+                    ("url".to_string(), Value::String("/c.html".to_string())),
+                    (
+                        "contexts".to_string(),
+                        Value::List(vec![Value::String("x < y".to_string())]),
+                    ),
+                ])]),
+            ),
+        ]);
+        let config = Config {
+            title: "Site".to_string(),
+            base_url: "https://example.com".to_string(),
+            ..Config::default()
+        };
+        let html = render_page(&builtin_templates(), "page.html", &config, page).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>\n<html lang=\"ja\">\n"));
+        assert!(!html.contains("noindex"));
+        assert!(html.contains("    <title>A &amp; B | Site</title>\n"));
+        assert!(html.contains("<link rel=\"canonical\" href=\"https://example.com/a.html\">"));
+        assert!(
+            html.contains(
+                "<link rel=\"alternate\" type=\"application/atom+xml\" href=\"/feed.xml\">"
+            )
+        );
+        assert!(html.contains("    <link rel=\"stylesheet\" href=\"/highlight.css\">\n"));
+        assert!(html.contains("<a class=\"site-title\" href=\"/\">Site</a>\n</header>\n"));
+        assert!(html.contains(concat!(
+            "<p>body</p>\n",
+            "<section class=\"backlinks\">\n<h2>Linked from</h2>\n<ul>\n",
+            "<li><a href=\"/c.html\">C</a><p class=\"backlink-context\">x &lt; y</p></li>\n",
+            "</ul>\n</section>\n",
+        )));
+        assert!(html.ends_with("</section>\n</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn test_builtin_wide() {
+        let page = Value::Map(vec![
+            ("title".to_string(), Value::String("Slides".to_string())),
+            ("url".to_string(), Value::String("/slides.html".to_string())),
+            ("noindex".to_string(), Value::Bool(true)),
+            (
+                "content".to_string(),
+                Value::String("<p>body</p>".to_string()),
+            ),
+        ]);
+        let html =
+            render_page(&builtin_templates(), "wide.html", &Config::default(), page).unwrap();
+
+        assert!(html.contains("    <meta name=\"robots\" content=\"noindex\">\n"));
+        assert!(html.contains(concat!(
+            "    </style>\n",
+            "    <style>\n",
+            "        body {\n",
+            "            max-width: none;\n",
+        )));
+        assert!(html.contains("<body>\n<p>body</p>\n</body>\n"));
+    }
 }
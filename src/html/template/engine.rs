@@ -0,0 +1,1187 @@
+use crate::html::convert::escape_html;
+use crate::html::slug::slugify;
+use crate::parser::document::front_matter::{Date, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+// include・extendsの入れ子の上限。循環しているテンプレートを止める
+const MAX_DEPTH: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateError {
+    pub template: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 行番号が0のときはテンプレート全体についてのエラー
+        if self.line == 0 {
+            write!(f, "template {}: {}", self.template, self.message)
+        } else {
+            write!(
+                f,
+                "template {} at line {}: {}",
+                self.template, self.line, self.message
+            )
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Literal(Value),
+    // `page.tags.0`
+    Path(Vec<String>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(CompareOp, Box<Expr>, Box<Expr>),
+    Filter {
+        expr: Box<Expr>,
+        name: String,
+        args: Vec<Expr>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Output {
+        expr: Expr,
+        line: usize,
+    },
+    If {
+        branches: Vec<(Expr, Vec<Node>)>,
+        otherwise: Vec<Node>,
+        line: usize,
+    },
+    For {
+        // `for key, value in map` は2つ
+        vars: Vec<String>,
+        iterable: Expr,
+        body: Vec<Node>,
+        // 空のときに出力する
+        otherwise: Vec<Node>,
+        line: usize,
+    },
+    Include {
+        name: String,
+        line: usize,
+    },
+    Block {
+        name: String,
+        body: Vec<Node>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Template {
+    // `{% extends "base.html" %}` の親テンプレート
+    extends: Option<(String, usize)>,
+    nodes: Vec<Node>,
+}
+
+// 名前(テンプレートディレクトリからの相対パス)で引けるテンプレートの集まり
+#[derive(Debug, Clone, Default)]
+pub struct Templates {
+    templates: HashMap<String, Template>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token<'a> {
+    Text(String),
+    // `{{ }}` の中身
+    Output(&'a str, usize),
+    // `{% %}` の中身
+    Tag(&'a str, usize),
+}
+
+// `{% %}` と `{# #}` だけの行は、行頭の空白と行末の改行も取り除く
+fn tokenize<'a>(name: &str, source: &'a str) -> Result<Vec<Token<'a>>, TemplateError> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    let mut line = 1;
+    // restが行の先頭から始まっているか
+    let mut at_line_start = true;
+    let mut trim_next = false;
+
+    while !rest.is_empty() {
+        let start = ["{{", "{%", "{#"]
+            .iter()
+            .filter_map(|open| rest.find(open))
+            .min()
+            .unwrap_or(rest.len());
+        let mut text = &rest[..start];
+        if trim_next {
+            text = text.trim_start();
+            trim_next = false;
+        }
+        if start == rest.len() {
+            tokens.push(Token::Text(text.to_string()));
+            break;
+        }
+
+        let open = &rest[start..start + 2];
+        let close = match open {
+            "{{" => "}}",
+            "{%" => "%}",
+            _ => "#}",
+        };
+        let tag_line = line + rest[..start].matches('\n').count();
+        let Some(end) = rest[start + 2..].find(close).map(|i| start + 2 + i) else {
+            return Err(TemplateError {
+                template: name.to_string(),
+                line: tag_line,
+                message: format!("`{}` is not closed with `{}`", open, close),
+            });
+        };
+        let mut inner = &rest[start + 2..end];
+
+        // タグだけの行は、インデントと行末の改行ごと取り除く
+        let indent_start = match text.rfind('\n') {
+            Some(i) => Some(i + 1),
+            None if at_line_start => Some(0),
+            None => None,
+        }
+        .filter(|&i| text[i..].chars().all(|c| c == ' ' || c == '\t'));
+        let own_line = open != "{{" && indent_start.is_some();
+
+        // `{%-` `-%}` は前後の空白を取り除く
+        if let Some(stripped) = inner.strip_prefix('-') {
+            inner = stripped;
+            text = text.trim_end();
+        } else if let Some(i) = indent_start
+            && own_line
+        {
+            text = &text[..i];
+        }
+        if let Some(stripped) = inner.strip_suffix('-') {
+            inner = stripped;
+            trim_next = true;
+        }
+
+        if !text.is_empty() {
+            tokens.push(Token::Text(text.to_string()));
+        }
+        match open {
+            "{{" => tokens.push(Token::Output(inner.trim(), tag_line)),
+            "{%" => tokens.push(Token::Tag(inner.trim(), tag_line)),
+            _ => {}
+        }
+
+        line += rest[..end + 2].matches('\n').count();
+        rest = &rest[end + 2..];
+        at_line_start = false;
+        if own_line
+            && !trim_next
+            && let Some(after) = rest
+                .strip_prefix("\r\n")
+                .or_else(|| rest.strip_prefix('\n'))
+        {
+            rest = after;
+            line += 1;
+            at_line_start = true;
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ExprToken {
+    Ident(String),
+    Str(String),
+    Number(Value),
+    Symbol(&'static str),
+}
+
+fn tokenize_expr(source: &str) -> Result<Vec<ExprToken>, String> {
+    const SYMBOLS: &[&str] = &["==", "!=", "<=", ">=", "<", ">", "|", "(", ")", ",", "."];
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        if c == '"' || c == '\'' {
+            let end = rest[1..]
+                .find(c)
+                .ok_or_else(|| "unterminated string".to_string())?;
+            tokens.push(ExprToken::Str(rest[1..1 + end].to_string()));
+            rest = &rest[end + 2..];
+        } else if c.is_ascii_digit()
+            || c == '-' && rest[1..].starts_with(|c: char| c.is_ascii_digit())
+        {
+            // `items.0.title` の添字には小数点を含めない
+            let after_dot = tokens.last() == Some(&ExprToken::Symbol("."));
+            let len = rest[1..]
+                .find(|c: char| !c.is_ascii_digit() && (c != '.' || after_dot))
+                .map_or(rest.len(), |i| i + 1);
+            let number = &rest[..len];
+            let value = match number.parse::<i64>() {
+                Ok(n) => Value::Integer(n),
+                Err(_) => Value::Float(
+                    number
+                        .parse()
+                        .map_err(|_| format!("invalid number `{}`", number))?,
+                ),
+            };
+            tokens.push(ExprToken::Number(value));
+            rest = &rest[len..];
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(ExprToken::Ident(rest[..len].to_string()));
+            rest = &rest[len..];
+        } else if let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(**s)) {
+            tokens.push(ExprToken::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        } else {
+            return Err(format!("unexpected `{}` in expression", c));
+        }
+        rest = rest.trim_start();
+    }
+
+    Ok(tokens)
+}
+
+struct ExprParser {
+    tokens: Vec<ExprToken>,
+    pos: usize,
+}
+
+impl ExprParser {
+    fn peek(&self) -> Option<&ExprToken> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<ExprToken> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        if matches!(self.peek(), Some(ExprToken::Symbol(s)) if *s == symbol) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(ExprToken::Ident(ident)) if ident == keyword) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_and()?;
+        while self.eat_keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_not()?;
+        while self.eat_keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, String> {
+        if self.eat_keyword("not") {
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_compare()
+    }
+
+    fn parse_compare(&mut self) -> Result<Expr, String> {
+        let left = self.parse_filtered()?;
+        let op = match self.peek() {
+            Some(ExprToken::Symbol("==")) => CompareOp::Eq,
+            Some(ExprToken::Symbol("!=")) => CompareOp::Ne,
+            Some(ExprToken::Symbol("<")) => CompareOp::Lt,
+            Some(ExprToken::Symbol("<=")) => CompareOp::Le,
+            Some(ExprToken::Symbol(">")) => CompareOp::Gt,
+            Some(ExprToken::Symbol(">=")) => CompareOp::Ge,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        let right = self.parse_filtered()?;
+        Ok(Expr::Compare(op, Box::new(left), Box::new(right)))
+    }
+
+    fn parse_filtered(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_primary()?;
+        while self.eat_symbol("|") {
+            let Some(ExprToken::Ident(name)) = self.next() else {
+                return Err("expected a filter name after `|`".to_string());
+            };
+            let mut args = Vec::new();
+            if self.eat_symbol("(") && !self.eat_symbol(")") {
+                loop {
+                    args.push(self.parse_or()?);
+                    if self.eat_symbol(")") {
+                        break;
+                    }
+                    if !self.eat_symbol(",") {
+                        return Err("expected `,` or `)` in filter arguments".to_string());
+                    }
+                }
+            }
+            expr = Expr::Filter {
+                expr: Box::new(expr),
+                name,
+                args,
+            };
+        }
+        Ok(expr)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(ExprToken::Str(s)) => Ok(Expr::Literal(Value::String(s))),
+            Some(ExprToken::Number(n)) => Ok(Expr::Literal(n)),
+            Some(ExprToken::Symbol("(")) => {
+                let expr = self.parse_or()?;
+                if !self.eat_symbol(")") {
+                    return Err("expected `)`".to_string());
+                }
+                Ok(expr)
+            }
+            Some(ExprToken::Ident(ident)) => match ident.as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                "null" | "none" => Ok(Expr::Literal(Value::Null)),
+                _ => {
+                    let mut path = vec![ident];
+                    while self.eat_symbol(".") {
+                        match self.next() {
+                            Some(ExprToken::Ident(key)) => path.push(key),
+                            Some(ExprToken::Number(Value::Integer(i))) => path.push(i.to_string()),
+                            _ => return Err("expected a key after `.`".to_string()),
+                        }
+                    }
+                    Ok(Expr::Path(path))
+                }
+            },
+            Some(token) => Err(format!("unexpected {:?} in expression", token)),
+            None => Err("expected an expression".to_string()),
+        }
+    }
+}
+
+fn parse_expr(source: &str) -> Result<Expr, String> {
+    let mut parser = ExprParser {
+        tokens: tokenize_expr(source)?,
+        pos: 0,
+    };
+    let expr = parser.parse_or()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(format!(
+            "unexpected {:?} in expression",
+            parser.tokens[parser.pos]
+        ));
+    }
+    Ok(expr)
+}
+
+// `"name"` の引用符を外す
+fn parse_name(source: &str) -> Option<String> {
+    match parse_expr(source) {
+        Ok(Expr::Literal(Value::String(name))) => Some(name),
+        _ => None,
+    }
+}
+
+// 読み終えたタグの (キーワード, 残り, 行)
+type EndTag<'a> = Option<(&'a str, &'a str, usize)>;
+
+struct TemplateParser<'a> {
+    name: &'a str,
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+}
+
+impl<'a> TemplateParser<'a> {
+    fn error<T>(&self, line: usize, message: impl Into<String>) -> Result<T, TemplateError> {
+        Err(TemplateError {
+            template: self.name.to_string(),
+            line,
+            message: message.into(),
+        })
+    }
+
+    fn expr(&self, source: &str, line: usize) -> Result<Expr, TemplateError> {
+        parse_expr(source).or_else(|message| self.error(line, message))
+    }
+
+    // endsのどれかのタグまでを読み、そのタグを返す
+    fn parse_nodes(&mut self, ends: &[&str]) -> Result<(Vec<Node>, EndTag<'a>), TemplateError> {
+        let mut nodes = Vec::new();
+
+        while let Some(token) = self.tokens.get(self.pos).cloned() {
+            self.pos += 1;
+            match token {
+                Token::Text(text) => nodes.push(Node::Text(text)),
+                Token::Output(source, line) => nodes.push(Node::Output {
+                    expr: self.expr(source, line)?,
+                    line,
+                }),
+                Token::Tag(source, line) => {
+                    let (keyword, rest) = source
+                        .split_once(char::is_whitespace)
+                        .map_or((source, ""), |(k, r)| (k, r.trim()));
+                    if ends.contains(&keyword) {
+                        return Ok((nodes, Some((keyword, rest, line))));
+                    }
+                    nodes.push(self.parse_tag(keyword, rest, line)?);
+                }
+            }
+        }
+
+        Ok((nodes, None))
+    }
+
+    fn expect_end(
+        &self,
+        end: EndTag<'a>,
+        keyword: &str,
+        line: usize,
+    ) -> Result<(&'a str, &'a str), TemplateError> {
+        match end {
+            Some((end, rest, _)) => Ok((end, rest)),
+            None => self.error(line, format!("`{{% {} %}}` is not closed", keyword)),
+        }
+    }
+
+    fn parse_tag(&mut self, keyword: &str, rest: &str, line: usize) -> Result<Node, TemplateError> {
+        match keyword {
+            "if" => {
+                let mut branches = Vec::new();
+                let mut otherwise = Vec::new();
+                let mut condition = self.expr(rest, line)?;
+                loop {
+                    let (body, end) = self.parse_nodes(&["elif", "else", "endif"])?;
+                    let (end, end_rest) = self.expect_end(end, "if", line)?;
+                    branches.push((condition, body));
+                    match end {
+                        "elif" => condition = self.expr(end_rest, line)?,
+                        "else" => {
+                            let (body, end) = self.parse_nodes(&["endif"])?;
+                            self.expect_end(end, "if", line)?;
+                            otherwise = body;
+                            break;
+                        }
+                        _ => break,
+                    }
+                }
+                Ok(Node::If {
+                    branches,
+                    otherwise,
+                    line,
+                })
+            }
+            "for" => {
+                let Some((vars, iterable)) = rest.split_once(" in ") else {
+                    return self.error(line, "expected `for item in items`");
+                };
+                let vars: Vec<String> = vars.split(',').map(|v| v.trim().to_string()).collect();
+                if vars.len() > 2
+                    || vars.iter().any(|v| {
+                        v.is_empty() || !v.chars().all(|c| c.is_alphanumeric() || c == '_')
+                    })
+                {
+                    return self.error(line, "expected `for item in items`");
+                }
+                let iterable = self.expr(iterable, line)?;
+                let (body, end) = self.parse_nodes(&["else", "endfor"])?;
+                let (end, _) = self.expect_end(end, "for", line)?;
+                let otherwise = if end == "else" {
+                    let (body, end) = self.parse_nodes(&["endfor"])?;
+                    self.expect_end(end, "for", line)?;
+                    body
+                } else {
+                    Vec::new()
+                };
+                Ok(Node::For {
+                    vars,
+                    iterable,
+                    body,
+                    otherwise,
+                    line,
+                })
+            }
+            "include" => match parse_name(rest) {
+                Some(name) => Ok(Node::Include { name, line }),
+                None => self.error(line, "expected `include \"name\"`"),
+            },
+            "block" => {
+                if rest.is_empty() || !rest.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                    return self.error(line, "expected `block name`");
+                }
+                let (body, end) = self.parse_nodes(&["endblock"])?;
+                self.expect_end(end, "block", line)?;
+                Ok(Node::Block {
+                    name: rest.to_string(),
+                    body,
+                })
+            }
+            "extends" => self.error(line, "`extends` must be the first tag"),
+            // 開きのタグのない終わりのタグ
+            "elif" | "else" | "endif" | "endfor" | "endblock" => {
+                self.error(line, format!("unexpected `{}`", keyword))
+            }
+            _ => self.error(line, format!("unknown tag `{}`", keyword)),
+        }
+    }
+
+    fn parse_template(&mut self) -> Result<Template, TemplateError> {
+        // extendsの前には空白しか書けない
+        let mut extends = None;
+        while let Some(token) = self.tokens.get(self.pos) {
+            match token {
+                Token::Text(text) if text.trim().is_empty() => self.pos += 1,
+                Token::Tag(source, line) if source.starts_with("extends") => {
+                    let line = *line;
+                    match parse_name(&source["extends".len()..]) {
+                        Some(name) => extends = Some((name, line)),
+                        None => return self.error(line, "expected `extends \"name\"`"),
+                    }
+                    self.pos += 1;
+                    break;
+                }
+                _ => {
+                    self.pos = 0;
+                    break;
+                }
+            }
+        }
+
+        let (nodes, end) = self.parse_nodes(&[])?;
+        if let Some((keyword, _, line)) = end {
+            return self.error(line, format!("unexpected `{}`", keyword));
+        }
+
+        Ok(Template { extends, nodes })
+    }
+}
+
+fn parse_template(name: &str, source: &str) -> Result<Template, TemplateError> {
+    let mut parser = TemplateParser {
+        name,
+        tokens: tokenize(name, source)?,
+        pos: 0,
+    };
+    parser.parse_template()
+}
+
+pub fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Integer(n) => *n != 0,
+        Value::Float(f) => *f != 0.0,
+        Value::String(s) => !s.is_empty(),
+        Value::Date(_) => true,
+        Value::List(values) => !values.is_empty(),
+        Value::Map(entries) => !entries.is_empty(),
+    }
+}
+
+fn format_date(date: &Date, format: &str) -> String {
+    const MONTHS: [&str; 12] = [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ];
+    let (hour, minute, second) = date.time.unwrap_or((0, 0, 0));
+    let month = MONTHS[(date.month as usize).clamp(1, 12) - 1];
+
+    let mut output = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => output.push_str(&format!("{:04}", date.year)),
+            Some('m') => output.push_str(&format!("{:02}", date.month)),
+            Some('d') => output.push_str(&format!("{:02}", date.day)),
+            Some('e') => output.push_str(&date.day.to_string()),
+            Some('H') => output.push_str(&format!("{:02}", hour)),
+            Some('M') => output.push_str(&format!("{:02}", minute)),
+            Some('S') => output.push_str(&format!("{:02}", second)),
+            Some('B') => output.push_str(month),
+            Some('b') => output.push_str(&month[..3]),
+            Some('%') => output.push('%'),
+            Some(c) => {
+                output.push('%');
+                output.push(c);
+            }
+            None => output.push('%'),
+        }
+    }
+
+    output
+}
+
+pub fn to_display_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Integer(n) => n.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::String(s) => s.clone(),
+        Value::Date(date) => match date.time {
+            Some(_) => format_date(date, "%Y-%m-%dT%H:%M:%S"),
+            None => format_date(date, "%Y-%m-%d"),
+        },
+        Value::List(values) => values
+            .iter()
+            .map(to_display_string)
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::Map(_) => String::new(),
+    }
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(n) => Some(*n as f64),
+        Value::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+fn compare(op: CompareOp, left: &Value, right: &Value) -> bool {
+    let ordering = match (left, right) {
+        (Value::String(l), Value::String(r)) => l.partial_cmp(r),
+        (Value::Date(l), Value::Date(r)) => l.partial_cmp(r),
+        _ => match (as_number(left), as_number(right)) {
+            (Some(l), Some(r)) => l.partial_cmp(&r),
+            _ => None,
+        },
+    };
+
+    match op {
+        CompareOp::Eq => ordering.map_or(left == right, |o| o.is_eq()),
+        CompareOp::Ne => ordering.map_or(left != right, |o| o.is_ne()),
+        CompareOp::Lt => ordering.is_some_and(|o| o.is_lt()),
+        CompareOp::Le => ordering.is_some_and(|o| o.is_le()),
+        CompareOp::Gt => ordering.is_some_and(|o| o.is_gt()),
+        CompareOp::Ge => ordering.is_some_and(|o| o.is_ge()),
+    }
+}
+
+struct Renderer<'t> {
+    templates: &'t Templates,
+    context: &'t Value,
+    // forの変数。内側のものから探す
+    scopes: Vec<Vec<(String, Value)>>,
+    // 子テンプレートで上書きされたblock
+    blocks: HashMap<String, &'t [Node]>,
+    // エラーに出すテンプレートの名前
+    current: String,
+    depth: usize,
+}
+
+impl<'t> Renderer<'t> {
+    fn error<T>(&self, line: usize, message: impl Into<String>) -> Result<T, TemplateError> {
+        Err(TemplateError {
+            template: self.current.clone(),
+            line,
+            message: message.into(),
+        })
+    }
+
+    fn lookup(&self, path: &[String]) -> Value {
+        let (first, rest) = path.split_first().expect("paths are never empty");
+        let mut value = self
+            .scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter())
+            .find(|(name, _)| name == first)
+            .map(|(_, value)| value)
+            .or_else(|| self.context.get(first));
+
+        for key in rest {
+            value = match value {
+                Some(Value::List(values)) => key.parse::<usize>().ok().and_then(|i| values.get(i)),
+                Some(value) => value.get(key),
+                None => None,
+            };
+        }
+
+        value.cloned().unwrap_or(Value::Null)
+    }
+
+    fn eval(&self, expr: &Expr, line: usize) -> Result<Value, TemplateError> {
+        Ok(match expr {
+            Expr::Literal(value) => value.clone(),
+            Expr::Path(path) => self.lookup(path),
+            Expr::Not(expr) => Value::Bool(!is_truthy(&self.eval(expr, line)?)),
+            Expr::And(left, right) => {
+                let left = self.eval(left, line)?;
+                if is_truthy(&left) {
+                    self.eval(right, line)?
+                } else {
+                    left
+                }
+            }
+            Expr::Or(left, right) => {
+                let left = self.eval(left, line)?;
+                if is_truthy(&left) {
+                    left
+                } else {
+                    self.eval(right, line)?
+                }
+            }
+            Expr::Compare(op, left, right) => Value::Bool(compare(
+                *op,
+                &self.eval(left, line)?,
+                &self.eval(right, line)?,
+            )),
+            Expr::Filter { expr, name, args } => {
+                let value = self.eval(expr, line)?;
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg, line))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.apply_filter(name, value, &args, line)?
+            }
+        })
+    }
+
+    fn apply_filter(
+        &self,
+        name: &str,
+        value: Value,
+        args: &[Value],
+        line: usize,
+    ) -> Result<Value, TemplateError> {
+        let string_arg = |i: usize, default: &str| {
+            args.get(i)
+                .map(to_display_string)
+                .unwrap_or_else(|| default.to_string())
+        };
+
+        Ok(match name {
+            "safe" => value,
+            "escape" => Value::String(escape_html(&to_display_string(&value))),
+            "upper" => Value::String(to_display_string(&value).to_uppercase()),
+            "lower" => Value::String(to_display_string(&value).to_lowercase()),
+            "slugify" => Value::String(slugify(&to_display_string(&value))),
+            "length" => Value::Integer(match &value {
+                Value::List(values) => values.len(),
+                Value::Map(entries) => entries.len(),
+                Value::Null => 0,
+                value => to_display_string(value).chars().count(),
+            } as i64),
+            "first" => match value {
+                Value::List(values) => values.into_iter().next().unwrap_or(Value::Null),
+                _ => Value::Null,
+            },
+            "last" => match value {
+                Value::List(values) => values.into_iter().last().unwrap_or(Value::Null),
+                _ => Value::Null,
+            },
+            "join" => match value {
+                Value::List(values) => Value::String(
+                    values
+                        .iter()
+                        .map(to_display_string)
+                        .collect::<Vec<_>>()
+                        .join(&string_arg(0, ", ")),
+                ),
+                value => value,
+            },
+            "default" => {
+                if is_truthy(&value) {
+                    value
+                } else {
+                    args.first().cloned().unwrap_or(Value::Null)
+                }
+            }
+            "date" => match &value {
+                Value::Date(date) => Value::String(format_date(date, &string_arg(0, "%Y-%m-%d"))),
+                _ => value,
+            },
+            _ => return self.error(line, format!("unknown filter `{}`", name)),
+        })
+    }
+
+    fn render_nodes(
+        &mut self,
+        nodes: &'t [Node],
+        output: &mut String,
+    ) -> Result<(), TemplateError> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => output.push_str(text),
+                Node::Output { expr, line } => {
+                    let value = self.eval(expr, *line)?;
+                    // `| safe` `| escape` で終わるもの以外はエスケープする
+                    let raw = matches!(expr, Expr::Filter { name, .. } if name == "safe" || name == "escape");
+                    let text = to_display_string(&value);
+                    if raw {
+                        output.push_str(&text);
+                    } else {
+                        output.push_str(&escape_html(&text));
+                    }
+                }
+                Node::If {
+                    branches,
+                    otherwise,
+                    line,
+                } => {
+                    let mut body = otherwise;
+                    for (condition, branch) in branches {
+                        if is_truthy(&self.eval(condition, *line)?) {
+                            body = branch;
+                            break;
+                        }
+                    }
+                    self.render_nodes(body, output)?;
+                }
+                Node::For {
+                    vars,
+                    iterable,
+                    body,
+                    otherwise,
+                    line,
+                } => {
+                    let items: Vec<Vec<Value>> = match self.eval(iterable, *line)? {
+                        Value::List(values) => values.into_iter().map(|v| vec![v]).collect(),
+                        Value::Map(entries) => entries
+                            .into_iter()
+                            .map(|(k, v)| vec![Value::String(k), v])
+                            .collect(),
+                        Value::Null => Vec::new(),
+                        _ => return self.error(*line, "`for` needs a list or a map"),
+                    };
+                    if items.is_empty() {
+                        self.render_nodes(otherwise, output)?;
+                        continue;
+                    }
+
+                    let length = items.len();
+                    for (i, item) in items.into_iter().enumerate() {
+                        let mut scope: Vec<(String, Value)> =
+                            match (vars.as_slice(), item.as_slice()) {
+                                ([var], [key, value]) => vec![(
+                                    var.clone(),
+                                    Value::Map(vec![
+                                        ("key".to_string(), key.clone()),
+                                        ("value".to_string(), value.clone()),
+                                    ]),
+                                )],
+                                ([var], [value]) => vec![(var.clone(), value.clone())],
+                                ([key_var, value_var], [key, value]) => vec![
+                                    (key_var.clone(), key.clone()),
+                                    (value_var.clone(), value.clone()),
+                                ],
+                                ([index_var, value_var], [value]) => vec![
+                                    (index_var.clone(), Value::Integer(i as i64)),
+                                    (value_var.clone(), value.clone()),
+                                ],
+                                _ => Vec::new(),
+                            };
+                        scope.push((
+                            "loop".to_string(),
+                            Value::Map(vec![
+                                ("index".to_string(), Value::Integer(i as i64 + 1)),
+                                ("index0".to_string(), Value::Integer(i as i64)),
+                                ("first".to_string(), Value::Bool(i == 0)),
+                                ("last".to_string(), Value::Bool(i + 1 == length)),
+                                ("length".to_string(), Value::Integer(length as i64)),
+                            ]),
+                        ));
+
+                        self.scopes.push(scope);
+                        let result = self.render_nodes(body, output);
+                        self.scopes.pop();
+                        result?;
+                    }
+                }
+                Node::Include { name, line } => {
+                    let Some(template) = self.templates.templates.get(name) else {
+                        return self.error(*line, format!("template `{}` not found", name));
+                    };
+                    if template.extends.is_some() {
+                        return self.error(
+                            *line,
+                            format!("cannot include `{}` that extends another template", name),
+                        );
+                    }
+                    if self.depth >= MAX_DEPTH {
+                        return self.error(*line, "templates are included too deeply");
+                    }
+
+                    let parent = std::mem::replace(&mut self.current, name.clone());
+                    self.depth += 1;
+                    let result = self.render_nodes(&template.nodes, output);
+                    self.depth -= 1;
+                    self.current = parent;
+                    result?;
+                }
+                Node::Block { name, body } => {
+                    let body = self.blocks.get(name).copied().unwrap_or(body);
+                    self.render_nodes(body, output)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// 子テンプレートのblockを集める。入れ子のblockも上書きできる
+fn collect_blocks<'t>(nodes: &'t [Node], blocks: &mut HashMap<String, &'t [Node]>) {
+    for node in nodes {
+        if let Node::Block { name, body } = node {
+            blocks.entry(name.clone()).or_insert(body);
+            collect_blocks(body, blocks);
+        }
+    }
+}
+
+impl Templates {
+    // 同じ名前のテンプレートは置き換える
+    pub fn add(&mut self, name: &str, source: &str) -> Result<(), TemplateError> {
+        let template = parse_template(name, source)?;
+        self.templates.insert(name.to_string(), template);
+
+        Ok(())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.templates.contains_key(name)
+    }
+
+    pub fn render(&self, name: &str, context: &Value) -> Result<String, TemplateError> {
+        let not_found = |name: &str| TemplateError {
+            template: name.to_string(),
+            line: 0,
+            message: "template not found".to_string(),
+        };
+        let mut template = self.templates.get(name).ok_or_else(|| not_found(name))?;
+        let mut current = name.to_string();
+        let mut blocks = HashMap::new();
+
+        // extendsをたどり、一番上のレイアウトを子のblockで描画する
+        let mut depth = 0;
+        while let Some((parent, line)) = &template.extends {
+            collect_blocks(&template.nodes, &mut blocks);
+            depth += 1;
+            template = match self.templates.get(parent) {
+                Some(parent) if depth < MAX_DEPTH => parent,
+                Some(_) => {
+                    return Err(TemplateError {
+                        template: current,
+                        line: *line,
+                        message: "templates extend each other too deeply".to_string(),
+                    });
+                }
+                None => {
+                    return Err(TemplateError {
+                        template: current,
+                        line: *line,
+                        message: format!("template `{}` not found", parent),
+                    });
+                }
+            };
+            current = parent.clone();
+        }
+
+        let mut renderer = Renderer {
+            templates: self,
+            context,
+            scopes: Vec::new(),
+            blocks,
+            current,
+            depth: 0,
+        };
+        let mut output = String::new();
+        renderer.render_nodes(&template.nodes, &mut output)?;
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(templates: &[(&str, &str)], context: Value) -> Result<String, TemplateError> {
+        let mut set = Templates::default();
+        for (name, source) in templates {
+            set.add(name, source)?;
+        }
+        set.render(templates[0].0, &context)
+    }
+
+    fn map(entries: &[(&str, Value)]) -> Value {
+        Value::Map(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        )
+    }
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn test_output_and_filters() {
+        let context = map(&[
+            ("title", string("A & B")),
+            ("body", string("<p>x</p>")),
+            ("tags", Value::List(vec![string("rust"), string("Web Dev")])),
+            (
+                "date",
+                Value::Date(Date {
+                    year: 2024,
+                    month: 3,
+                    day: 5,
+                    time: None,
+                    offset: None,
+                }),
+            ),
+        ]);
+
+        assert_eq!(
+            render(
+                &[(
+                    "t",
+                    "{{ title }}|{{ body | safe }}|{{ tags | join(\" / \") | upper }}|{{ tags.1 | slugify }}|{{ missing | default(\"none\") }}|{{ date | date(\"%e %b %Y\") }}|{{ tags | length }}"
+                )],
+                context
+            ),
+            Ok("A &amp; B|<p>x</p>|RUST / WEB DEV|web-dev|none|5 Mar 2024|2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_if_and_for() {
+        let context = map(&[
+            (
+                "items",
+                Value::List(vec![string("a"), string("b"), string("c")]),
+            ),
+            ("count", Value::Integer(3)),
+            ("meta", map(&[("x", Value::Integer(1))])),
+        ]);
+        let source = concat!(
+            "<ul>\n",
+            "{% for item in items %}\n",
+            "  <li>{{ loop.index }}:{{ item }}{% if not loop.last %},{% endif %}</li>\n",
+            "{% endfor %}\n",
+            "</ul>\n",
+            "{% if count > 5 %}many{% elif count == 3 and items %}three{% else %}few{% endif %}\n",
+            "{% for key, value in meta %}{{ key }}={{ value }}{% endfor %}\n",
+            "{% for x in nothing %}x{% else %}empty{% endfor %}\n",
+            "{# comment #}\n",
+            "{{- \" trimmed \" -}}\n",
+            "!\n",
+        );
+
+        assert_eq!(
+            render(&[("t", source)], context),
+            Ok(concat!(
+                "<ul>\n",
+                "  <li>1:a,</li>\n",
+                "  <li>2:b,</li>\n",
+                "  <li>3:c</li>\n",
+                "</ul>\n",
+                "three\n",
+                "x=1\n",
+                "empty\n",
+                " trimmed !\n",
+            )
+            .to_string())
+        );
+    }
+
+    #[test]
+    fn test_extends_and_include() {
+        let templates = [
+            (
+                "page.html",
+                "{% extends \"base.html\" %}\n{% block content %}<main>{{ title }}</main>{% endblock %}\nignored",
+            ),
+            (
+                "base.html",
+                "{% include \"partials/header.html\" %}{% block content %}default{% endblock %}{% block footer %}<footer></footer>{% endblock %}",
+            ),
+            ("partials/header.html", "<header>{{ site }}</header>"),
+        ];
+        let context = map(&[("title", string("T")), ("site", string("S"))]);
+
+        assert_eq!(
+            render(&templates, context),
+            Ok("<header>S</header><main>T</main><footer></footer>".to_string())
+        );
+    }
+
+    #[test]
+    fn test_template_errors() {
+        let error = |source: &str| render(&[("t.html", source)], map(&[])).unwrap_err();
+
+        assert_eq!(
+            error("a\n{% if x %}\nb\n"),
+            TemplateError {
+                template: "t.html".to_string(),
+                line: 2,
+                message: "`{% if %}` is not closed".to_string(),
+            }
+        );
+        assert_eq!(error("\n\n{{ x | nope }}").line, 3);
+        assert_eq!(error("{{ x").line, 1);
+        assert_eq!(error("a\n{% endfor %}").line, 2);
+        assert_eq!(error("{% unknown %}").message, "unknown tag `unknown`");
+        assert_eq!(
+            error("{% include \"missing.html\" %}").message,
+            "template `missing.html` not found"
+        );
+        assert_eq!(
+            render(&[("loop.html", "{% include \"loop.html\" %}")], map(&[]))
+                .unwrap_err()
+                .message,
+            "templates are included too deeply"
+        );
+    }
+}
@@ -1,52 +1,246 @@
 use crate::{
-    html::{convert::ToHtml, template::wrap_template},
-    parser::document::ast::MdDocument,
+    config::Config,
+    file::modified_date,
+    html::{
+        backlink::Backlink,
+        block_id::assign_block_ids,
+        convert::ToHtml,
+        footnote::number_footnotes,
+        heading::{TocEntry, assign_heading_ids, build_toc, insert_toc, toc_html},
+        highlight::highlight_code_blocks,
+        math::check_math,
+        raw_html::apply_html_policy,
+        tags::tags_context,
+        template::{
+            engine::{TemplateError, Templates},
+            render_page,
+        },
+    },
+    parser::document::{
+        ast::{Block, Document, Inline, MdDocument},
+        front_matter::{Date, Value},
+    },
 };
 
+// 一覧に出す抜粋の最大の文字数
+const EXCERPT_LENGTH: usize = 160;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct HTMLDocument<'a> {
-    pub path: &'a str,
+    // ページのURL
+    pub path: String,
     pub title: &'a str,
     pub tags: Option<Vec<&'a str>>,
+    // front matterの日付。なければファイルの更新日時
+    pub date: Option<Date>,
+    // front matterの更新日か、date
+    pub updated: Option<Date>,
+    pub noindex: bool,
+    pub excerpt: String,
+    // テンプレートに入れる前の本文
+    pub body: String,
     pub content: String,
 }
 
-pub fn md_to_html<'a>(md_doc: &'a MdDocument) -> HTMLDocument<'a> {
-    let original_path = md_doc
+#[cfg(test)]
+impl<'a> HTMLDocument<'a> {
+    // テスト用の記事。dayは2024年3月の日付
+    pub fn for_test(title: &'a str, tags: Vec<&'a str>, day: Option<u32>) -> Self {
+        HTMLDocument {
+            path: format!("/{}.html", title),
+            title,
+            tags: Some(tags),
+            date: day.map(|day| Date {
+                year: 2024,
+                month: 3,
+                day,
+                time: None,
+                offset: None,
+            }),
+            updated: None,
+            noindex: false,
+            excerpt: format!("About {}", title),
+            body: String::new(),
+            content: String::new(),
+        }
+    }
+}
+
+fn file_stem<'a>(md_doc: &MdDocument<'a>) -> &'a str {
+    md_doc
         .path
         .file_stem()
         .and_then(|s| s.to_str())
-        .unwrap_or_else(|| "".into());
+        .unwrap_or("")
+}
 
-    let path = md_doc
+// 出力するHTMLファイルの名前(拡張子なし)。front matterのslugがあればそちらを使う
+pub fn output_path<'a>(md_doc: &'a MdDocument) -> &'a str {
+    md_doc
         .front_matter
         .as_ref()
-        .and_then(|fm| fm.get("slug"))
-        .map_or(original_path, |s| s);
+        .and_then(|fm| fm.slug())
+        .unwrap_or(file_stem(md_doc))
+}
 
-    let title = if let Some(title) = md_doc.front_matter.as_ref().and_then(|fm| fm.get("title"))
-        && title != ""
-    {
-        title
-    } else {
-        original_path
-    };
+// front matterのtitleがなければファイル名を使う
+pub fn document_title<'a>(md_doc: &'a MdDocument) -> &'a str {
+    md_doc
+        .front_matter
+        .as_ref()
+        .and_then(|fm| fm.title())
+        .unwrap_or(file_stem(md_doc))
+}
 
-    let tags = if let Some(tags) = md_doc.front_matter.as_ref().and_then(|fm| fm.get("tags"))
-        && tags != ""
+// テンプレートの `page.toc`
+fn toc_context(toc: &[TocEntry]) -> Value {
+    Value::List(
+        toc.iter()
+            .map(|entry| {
+                Value::Map(vec![
+                    ("level".to_string(), Value::Integer(entry.level as i64)),
+                    ("id".to_string(), Value::String(entry.id.clone())),
+                    ("title".to_string(), Value::String(entry.title.clone())),
+                    ("children".to_string(), toc_context(&entry.children)),
+                ])
+            })
+            .collect(),
+    )
+}
+
+// front matterの `description` か、本文の最初の段落
+fn excerpt(md_doc: &MdDocument, body: &Document) -> String {
+    let text = match md_doc
+        .front_matter
+        .as_ref()
+        .and_then(|fm| fm.get_str("description"))
     {
-        Some(tags.split(", ").collect())
-    } else {
-        None
+        Some(description) => description.to_string(),
+        None => body
+            .blocks
+            .iter()
+            .find_map(|block| match block {
+                Block::Paragraph(content) => Some(Inline::plain_text(content)),
+                Block::Identified { block, .. } => match block.as_ref() {
+                    Block::Paragraph(content) => Some(Inline::plain_text(content)),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .unwrap_or_default(),
     };
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    match text.char_indices().nth(EXCERPT_LENGTH) {
+        Some((i, _)) => format!("{}…", text[..i].trim_end()),
+        None => text,
+    }
+}
+
+pub fn md_to_html<'a>(
+    md_doc: &'a MdDocument,
+    templates: &Templates,
+    config: &Config,
+    url: &str,
+    backlinks: &[Backlink],
+) -> Result<HTMLDocument<'a>, TemplateError> {
+    let title = document_title(md_doc);
+
+    let tags = md_doc
+        .front_matter
+        .as_ref()
+        .map(|fm| fm.tags())
+        .filter(|tags| !tags.is_empty());
 
     // mainでis_someを使って確認してるのでここはunwrapしてOK
-    let content = wrap_template(title, md_doc.body.as_ref().unwrap().to_html().as_str());
+    let mut body = md_doc.body.clone().unwrap();
+    let mut warnings = number_footnotes(&mut body);
+    warnings.extend(check_math(&mut body));
+    warnings.extend(apply_html_policy(&mut body, config.html));
+    warnings.extend(highlight_code_blocks(
+        &mut body,
+        config.highlight,
+        config.line_numbers,
+    ));
+    for warning in warnings {
+        println!("Warning: {} in {:?}", warning, md_doc.path);
+    }
 
-    HTMLDocument {
-        path,
+    // 生のHTMLの扱いを決めた後に、ブロックと見出しのidと目次を足す
+    assign_block_ids(&mut body);
+    assign_heading_ids(&mut body, config.heading_anchors);
+    let toc = build_toc(&body, config.toc_min_level, config.toc_max_level);
+    insert_toc(
+        &mut body,
+        &toc,
+        md_doc
+            .front_matter
+            .as_ref()
+            .is_some_and(|fm| fm.flag("toc")),
+    );
+
+    let body_html = body.to_html();
+    let layout = config.layout(md_doc);
+    if !templates.contains(&layout) {
+        return Err(TemplateError {
+            template: layout,
+            line: 0,
+            message: format!("layout of {:?} not found", md_doc.path),
+        });
+    }
+
+    let front_matter = md_doc.front_matter.as_ref();
+    let mut page = vec![
+        ("title".to_string(), Value::String(title.to_string())),
+        ("url".to_string(), Value::String(url.to_string())),
+        ("layout".to_string(), Value::String(layout.clone())),
+        (
+            "noindex".to_string(),
+            Value::Bool(front_matter.is_some_and(|fm| fm.noindex())),
+        ),
+        ("content".to_string(), Value::String(body_html.clone())),
+        ("toc".to_string(), toc_context(&toc)),
+        ("toc_html".to_string(), Value::String(toc_html(&toc))),
+        (
+            "tags".to_string(),
+            tags_context(tags.as_deref().unwrap_or_default()),
+        ),
+        (
+            "date".to_string(),
+            front_matter
+                .and_then(|fm| fm.date())
+                .map_or(Value::Null, Value::Date),
+        ),
+        (
+            "front_matter".to_string(),
+            front_matter.map_or(Value::Map(Vec::new()), |fm| fm.to_value()),
+        ),
+        (
+            "backlinks".to_string(),
+            Value::List(backlinks.iter().map(Backlink::to_value).collect()),
+        ),
+    ];
+    // front matterの独自のキーは `page.subtitle` のようにも参照できる。組み込みのキーが優先
+    for (key, value) in front_matter.map_or(&[][..], |fm| fm.values()) {
+        if !page.iter().any(|(k, _)| k == key) {
+            page.push((key.clone(), value.clone()));
+        }
+    }
+    let content = render_page(templates, &layout, config, Value::Map(page))?;
+
+    let date = front_matter
+        .and_then(|fm| fm.date())
+        .or_else(|| modified_date(md_doc.path));
+
+    Ok(HTMLDocument {
+        path: url.to_string(),
         title,
         tags,
+        date,
+        updated: front_matter.and_then(|fm| fm.updated()).or(date),
+        noindex: front_matter.is_some_and(|fm| fm.noindex()),
+        excerpt: excerpt(md_doc, &body),
+        body: body_html,
         content,
-    }
+    })
 }
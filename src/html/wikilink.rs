@@ -0,0 +1,206 @@
+use crate::config::Config;
+use crate::html::slug::slugify;
+use crate::parser::document::ast::{Document, Inline, MdDocument};
+use clap::ValueEnum;
+use std::collections::HashMap;
+
+// 公開されていない・存在しないノートへのリンクの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Default, ValueEnum)]
+pub enum BrokenLinkPolicy {
+    // リンクを外して文字だけにする
+    Text,
+    // broken-linkクラスのspanにする
+    #[default]
+    Span,
+    // ビルドを失敗させる
+    Fail,
+}
+
+// ノート名から出力先のURLを引く索引
+#[derive(Debug, Default)]
+pub struct LinkIndex {
+    // 名前 -> ドキュメントの番号
+    names: HashMap<String, usize>,
+    urls: Vec<String>,
+}
+
+// Obsidianと同じく大文字小文字を区別せず、拡張子は無視する
+fn normalize(name: &str) -> String {
+    let name = name.trim().replace('\\', "/").to_lowercase();
+    match name.strip_suffix(".md") {
+        Some(stem) => stem.to_string(),
+        None => name,
+    }
+}
+
+impl LinkIndex {
+    // 名前が重なったときは、入力ディレクトリからの相対パス、ファイル名、title・aliasesの順に優先する
+    pub fn new(docs: &[MdDocument], config: &Config) -> Self {
+        let mut names = HashMap::new();
+        let urls = docs.iter().map(|doc| config.page_url(doc)).collect();
+        let base_dir = config.content_dir.as_path();
+
+        for (i, doc) in docs.iter().enumerate() {
+            let relative = doc.path.strip_prefix(base_dir).unwrap_or(doc.path);
+            let relative = relative.with_extension("");
+            names
+                .entry(normalize(&relative.to_string_lossy()))
+                .or_insert(i);
+        }
+        for (i, doc) in docs.iter().enumerate() {
+            if let Some(stem) = doc.path.file_stem() {
+                names.entry(normalize(&stem.to_string_lossy())).or_insert(i);
+            }
+        }
+        for (i, doc) in docs.iter().enumerate() {
+            let Some(front_matter) = doc.front_matter.as_ref() else {
+                continue;
+            };
+            for name in front_matter
+                .title()
+                .into_iter()
+                .chain(front_matter.aliases())
+            {
+                if !name.trim().is_empty() {
+                    names.entry(normalize(name)).or_insert(i);
+                }
+            }
+        }
+
+        LinkIndex { names, urls }
+    }
+
+    // リンク先のドキュメントの番号(索引を作ったときの並び)
+    pub fn find(&self, target: &str) -> Option<usize> {
+        self.names.get(&normalize(target)).copied()
+    }
+
+    pub fn url(&self, doc: usize) -> &str {
+        &self.urls[doc]
+    }
+
+    // 出力先のURL(`/path.html`)からドキュメントの番号を引く
+    pub fn find_url(&self, url: &str) -> Option<usize> {
+        self.urls.iter().position(|u| u == url)
+    }
+
+    pub fn resolve(&self, target: &str) -> Option<&str> {
+        self.find(target).map(|i| self.url(i))
+    }
+}
+
+// `Heading` は見出しのスラッグに、`^block` はブロックIDのまま、アンカーにする
+// `A#B` のように見出しが重なっていれば最後のものを使う
+pub fn fragment_anchor(fragment: &str) -> String {
+    let last = fragment.rsplit('#').next().unwrap_or(fragment).trim();
+    if last.starts_with('^') {
+        format!("#{}", last)
+    } else {
+        format!("#{}", slugify(last))
+    }
+}
+
+// WikiリンクをLinkに置き換える。解決できなかったリンクを返す
+pub fn resolve_wikilinks(
+    doc: &mut Document,
+    index: &LinkIndex,
+    policy: BrokenLinkPolicy,
+) -> Vec<String> {
+    let mut broken = Vec::new();
+
+    for block in &mut doc.blocks {
+        block.walk_inlines_mut(&mut |inline| {
+            if let Inline::WikiLink {
+                target,
+                fragment,
+                text,
+            } = inline
+            {
+                let anchor = fragment.as_deref().map(fragment_anchor).unwrap_or_default();
+                // `[[#Heading]]` は同じページ内へのリンク
+                let url = if target.is_empty() {
+                    Some(anchor)
+                } else {
+                    index
+                        .resolve(target)
+                        .map(|url| format!("{}{}", url, anchor))
+                };
+
+                match url {
+                    Some(url) => {
+                        *inline = Inline::Link {
+                            text: vec![Inline::Text(text.clone())],
+                            url,
+                        }
+                    }
+                    None => {
+                        broken.push(format!("broken wikilink [[{}]]", target));
+                        if policy == BrokenLinkPolicy::Text {
+                            *inline = Inline::Text(text.clone());
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    broken
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::document::ast::Block;
+    use crate::parser::document::parse_document;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_link_index() {
+        let note = PathBuf::from("contents/dir/Note.md");
+        let other = PathBuf::from("contents/Other.md");
+        let docs = vec![
+            MdDocument::for_test(
+                &note,
+                &[("slug", "my-note"), ("aliases", "First, Second")],
+                "",
+            ),
+            MdDocument::for_test(&other, &[("title", "Another Title")], ""),
+        ];
+        let index = LinkIndex::new(&docs, &Config::default());
+
+        assert_eq!(index.resolve("Note"), Some("/my-note.html"));
+        assert_eq!(index.resolve("dir/note.md"), Some("/my-note.html"));
+        assert_eq!(index.resolve("second"), Some("/my-note.html"));
+        assert_eq!(index.resolve("Another Title"), Some("/Other.html"));
+        assert_eq!(index.resolve("Missing"), None);
+    }
+
+    #[test]
+    fn test_resolve_wikilinks() {
+        let note = PathBuf::from("contents/Note.md");
+        let docs = vec![MdDocument::for_test(&note, &[], "")];
+        let index = LinkIndex::new(&docs, &Config::default());
+        let input = "[[Note#Some Heading]] [[Note#^abc|block]] [[#Top]] [[Missing]]\n";
+
+        let (mut doc, _) = parse_document()(input).unwrap();
+        let broken = resolve_wikilinks(&mut doc, &index, BrokenLinkPolicy::Text);
+
+        assert_eq!(broken, vec!["broken wikilink [[Missing]]".to_string()]);
+        let link = |text: &str, url: &str| Inline::Link {
+            text: vec![Inline::Text(text.to_string())],
+            url: url.to_string(),
+        };
+        assert_eq!(
+            doc.blocks[0],
+            Block::Paragraph(vec![
+                link("Note#Some Heading", "/Note.html#some-heading"),
+                Inline::Text(" ".to_string()),
+                link("block", "/Note.html#^abc"),
+                Inline::Text(" ".to_string()),
+                link("#Top", "#top"),
+                Inline::Text(" ".to_string()),
+                Inline::Text("Missing".to_string()),
+            ])
+        );
+    }
+}
@@ -1,4 +1,5 @@
 pub mod cli;
+pub mod config;
 pub mod file;
 pub mod html;
 pub mod parser;
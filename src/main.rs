@@ -1,6 +1,7 @@
 use biotite::cli::build::build;
 use biotite::cli::serve::start_server;
 use biotite::cli::{Cli, Commands};
+use biotite::config::Config;
 use clap::Parser;
 
 #[tokio::main]
@@ -12,11 +13,29 @@ async fn main() -> Result<(), std::io::Error> {
             serve,
             directory,
             output,
+            breaks,
+            html,
+            broken_links,
+            embed_depth,
         }) => {
-            build(directory, output)?;
+            // CLIの引数はbiotite.tomlの設定より優先する
+            let mut config = Config::load(cli.config.as_deref())?;
+            if let Some(directory) = directory {
+                config.content_dir = directory.clone();
+            }
+            if let Some(output) = output {
+                config.output_dir = output.clone();
+            }
+            config.breaks |= *breaks;
+            config.html = html.unwrap_or(config.html);
+            config.broken_links = broken_links.unwrap_or(config.broken_links);
+            config.embed_depth = embed_depth.unwrap_or(config.embed_depth);
+            config.validate()?;
+
+            build(&config)?;
 
             if *serve {
-                start_server(output).await?;
+                start_server(&config.output_dir, &config.server_address).await?;
             }
 
             Ok(())
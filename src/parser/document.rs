@@ -1,6 +1,8 @@
 pub mod ast;
 pub mod block;
+pub mod entity;
 pub mod front_matter;
+pub mod html;
 pub mod inline;
 
 use super::{Parser, blank_line, many, some};
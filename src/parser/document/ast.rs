@@ -1,12 +1,32 @@
-use std::{collections::HashMap, path::PathBuf};
+use super::front_matter::FrontMatter;
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct MdDocument<'a> {
     pub path: &'a PathBuf,
-    pub front_matter: Option<HashMap<String, String>>,
+    pub front_matter: Option<FrontMatter>,
     pub body: Option<Document>,
 }
 
+#[cfg(test)]
+impl<'a> MdDocument<'a> {
+    // テスト用のノート。front matterの値はすべて文字列
+    pub fn for_test(path: &'a PathBuf, front_matter: &[(&str, &str)], input: &str) -> Self {
+        use super::front_matter::Value;
+
+        MdDocument {
+            path,
+            front_matter: Some(FrontMatter::new(
+                front_matter
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), Value::String(v.to_string())))
+                    .collect(),
+            )),
+            body: super::parse_document()(input).map(|(doc, _)| doc),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Document {
     pub blocks: Vec<Block>,
@@ -17,37 +37,323 @@ pub enum Block {
     Heading {
         level: u8, // 1 to 6
         content: Vec<Inline>,
+        // 描画前の処理(html::heading)で埋まる
+        id: Option<String>,
     },
     Paragraph(Vec<Inline>),
     Blockquote(Vec<Block>),
+    Callout {
+        kind: String,
+        title: Option<Vec<Inline>>,
+        foldable: bool,
+        default_open: bool,
+        blocks: Vec<Block>,
+    },
     List(ListType),
+    // インデントによるコードブロックもlanguageなしのFencedCodeBlockとして扱う
     FencedCodeBlock {
         language: Option<String>,
+        // 情報文字列の言語より後ろ。`{3-5}` は ("highlight", "3-5") になる
+        attributes: Vec<(String, String)>,
         code: String,
+        // 描画前の処理(html::highlight)で埋まる `<code>` の中身
+        highlighted: Option<String>,
     },
     HorizontalRule,
+    MathBlock(String),
+    Table {
+        headers: Vec<Vec<Inline>>,
+        alignments: Vec<Alignment>,
+        rows: Vec<Vec<Vec<Inline>>>,
+    },
+    // numberとreferencesは描画前の採番処理(html::footnote)で埋まる
+    FootnoteDefinition {
+        label: String,
+        number: Option<usize>,
+        references: usize,
+        blocks: Vec<Block>,
+    },
+    // 生のHTML。出力のしかたは描画前の処理(html::raw_html)で決まる
+    Html(String),
+    // 段落に `![[...]]` だけが書かれたもの
+    Embed(Embed),
+    // ノートの埋め込みを展開したもの(html::embed)
+    Transclusion {
+        title: String,
+        url: String,
+        blocks: Vec<Block>,
+    },
+    // `^id` を付けたブロック(html::block_id)。idは外側の要素に付く
+    Identified {
+        id: String,
+        block: Box<Block>,
+    },
+}
+
+// `![[target#fragment|label]]`。labelの代わりに `|300` `|300x200` で大きさを指定できる
+#[derive(Debug, Clone, PartialEq)]
+pub struct Embed {
+    pub target: String,
+    pub fragment: Option<String>,
+    pub label: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alignment {
+    None,
+    Left,
+    Center,
+    Right,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ListType {
-    Unordered(Vec<ListItem>),
-    Ordered(Vec<ListItem>),
+    Unordered {
+        items: Vec<ListItem>,
+        // 項目の間や項目内のブロックの間に空行があるリスト
+        loose: bool,
+    },
+    Ordered {
+        start: u64,
+        items: Vec<ListItem>,
+        loose: bool,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ListItem {
-    pub indent: usize,
     pub checked: Option<bool>,
-    pub content: Vec<Inline>,
+    pub blocks: Vec<Block>,
+    // 項目の末尾の `^id`。描画前の処理(html::block_id)で埋まる
+    pub id: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Inline {
     Text(String),
-    Link { text: Vec<Inline>, url: String },
-    Image { alt: String, url: String },
+    Link {
+        text: Vec<Inline>,
+        url: String,
+    },
+    Image {
+        alt: String,
+        url: String,
+    },
+    // `[[target#fragment|text]]`。ビルド時のリンク解決(html::wikilink)でLinkに置き換わる
+    WikiLink {
+        target: String,
+        fragment: Option<String>,
+        text: String,
+    },
     Strong(Vec<Inline>),
     Italic(Vec<Inline>),
     Strikethrough(Vec<Inline>),
     Code(String),
+    SoftBreak,
+    HardBreak,
+    Math {
+        tex: String,
+        display: bool,
+    },
+    // numberとoccurrenceは描画前の採番処理(html::footnote)で埋まる
+    FootnoteRef {
+        label: String,
+        number: Option<usize>,
+        occurrence: usize,
+    },
+    Html(String),
+    Embed(Embed),
+}
+
+impl Document {
+    // 段落内の改行をすべて<br>として扱う(Obsidianの「厳密な改行」がオフの状態)
+    pub fn convert_soft_breaks(&mut self) {
+        for block in &mut self.blocks {
+            block.walk_inlines_mut(&mut |inline| {
+                if *inline == Inline::SoftBreak {
+                    *inline = Inline::HardBreak;
+                }
+            });
+        }
+    }
+}
+
+impl ListType {
+    pub fn items(&self) -> &[ListItem] {
+        match self {
+            ListType::Unordered { items, .. } | ListType::Ordered { items, .. } => items,
+        }
+    }
+
+    pub fn items_mut(&mut self) -> &mut Vec<ListItem> {
+        match self {
+            ListType::Unordered { items, .. } | ListType::Ordered { items, .. } => items,
+        }
+    }
+}
+
+impl Embed {
+    fn extension(&self) -> Option<String> {
+        self.target
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.extension().is_some_and(|ext| {
+            ["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp", "avif"].contains(&ext.as_str())
+        })
+    }
+
+    pub fn is_pdf(&self) -> bool {
+        self.extension().is_some_and(|ext| ext == "pdf")
+    }
+
+    // 画像やPDFはノートではなくファイルとして埋め込む
+    pub fn is_asset(&self) -> bool {
+        self.is_image() || self.is_pdf()
+    }
+}
+
+impl Block {
+    // ネストしたブロックも含め、このブロック内の全インライン要素を先行順にたどる
+    pub fn walk_inlines_mut(&mut self, f: &mut impl FnMut(&mut Inline)) {
+        match self {
+            Block::Heading { content, .. } | Block::Paragraph(content) => {
+                content.iter_mut().for_each(|i| i.walk_mut(f));
+            }
+            Block::Blockquote(blocks)
+            | Block::FootnoteDefinition { blocks, .. }
+            | Block::Transclusion { blocks, .. } => {
+                blocks.iter_mut().for_each(|b| b.walk_inlines_mut(f));
+            }
+            Block::Identified { block, .. } => block.walk_inlines_mut(f),
+            Block::Callout { title, blocks, .. } => {
+                title.iter_mut().flatten().for_each(|i| i.walk_mut(f));
+                blocks.iter_mut().for_each(|b| b.walk_inlines_mut(f));
+            }
+            Block::List(list) => {
+                list.items_mut()
+                    .iter_mut()
+                    .flat_map(|item| item.blocks.iter_mut())
+                    .for_each(|b| b.walk_inlines_mut(f));
+            }
+            Block::Table { headers, rows, .. } => {
+                headers
+                    .iter_mut()
+                    .chain(rows.iter_mut().flatten())
+                    .flatten()
+                    .for_each(|i| i.walk_mut(f));
+            }
+            Block::FencedCodeBlock { .. }
+            | Block::HorizontalRule
+            | Block::MathBlock(_)
+            | Block::Html(_)
+            | Block::Embed(_) => {}
+        }
+    }
+
+    // 自身とネストした子ブロックを先行順にたどる
+    pub fn walk_blocks_mut(&mut self, f: &mut impl FnMut(&mut Block)) {
+        f(self);
+        match self {
+            Block::Blockquote(blocks)
+            | Block::Callout { blocks, .. }
+            | Block::FootnoteDefinition { blocks, .. }
+            | Block::Transclusion { blocks, .. } => {
+                blocks.iter_mut().for_each(|b| b.walk_blocks_mut(f));
+            }
+            Block::Identified { block, .. } => block.walk_blocks_mut(f),
+            Block::List(list) => {
+                list.items_mut()
+                    .iter_mut()
+                    .flat_map(|item| item.blocks.iter_mut())
+                    .for_each(|b| b.walk_blocks_mut(f));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Inline {
+    // 末尾の空白と改行を取り除く
+    fn trim_end(inlines: &mut Vec<Inline>) {
+        while let Some(last) = inlines.last_mut() {
+            match last {
+                Inline::Text(text) => {
+                    let len = text.trim_end().len();
+                    if len > 0 {
+                        text.truncate(len);
+                        return;
+                    }
+                    inlines.pop();
+                }
+                Inline::SoftBreak | Inline::HardBreak => {
+                    inlines.pop();
+                }
+                _ => return,
+            }
+        }
+    }
+
+    // 末尾のTextからsuffixを取り除く。suffixは複数のTextにまたがってもよい
+    // 末尾がsuffixで終わるTextでなければ何も変えずにfalseを返す
+    pub fn strip_text_suffix(inlines: &mut Vec<Inline>, suffix: &str) -> bool {
+        let mut stripped = inlines.clone();
+        Inline::trim_end(&mut stripped);
+        let mut rest = suffix;
+        while !rest.is_empty() {
+            let Some(Inline::Text(last)) = stripped.last_mut() else {
+                return false;
+            };
+            if let Some(head) = rest.strip_suffix(last.as_str()) {
+                rest = head;
+                stripped.pop();
+            } else if let Some(kept) = last.strip_suffix(rest) {
+                *last = kept.to_string();
+                rest = "";
+            } else {
+                return false;
+            }
+        }
+        Inline::trim_end(&mut stripped);
+        *inlines = stripped;
+
+        true
+    }
+
+    // 装飾を外した文字列
+    pub fn plain_text(inlines: &[Inline]) -> String {
+        inlines
+            .iter()
+            .map(|inline| match inline {
+                Inline::Text(s) | Inline::Code(s) => s.clone(),
+                Inline::Strong(children)
+                | Inline::Italic(children)
+                | Inline::Strikethrough(children)
+                | Inline::Link { text: children, .. } => Inline::plain_text(children),
+                Inline::WikiLink { text, .. } => text.clone(),
+                Inline::Image { alt, .. } => alt.clone(),
+                Inline::Math { tex, .. } => tex.clone(),
+                Inline::SoftBreak | Inline::HardBreak => " ".to_string(),
+                _ => String::new(),
+            })
+            .collect()
+    }
+
+    // 自身と子要素を先行順にたどる
+    pub fn walk_mut(&mut self, f: &mut impl FnMut(&mut Inline)) {
+        f(self);
+        match self {
+            Inline::Strong(children)
+            | Inline::Italic(children)
+            | Inline::Strikethrough(children)
+            | Inline::Link { text: children, .. } => {
+                children.iter_mut().for_each(|i| i.walk_mut(f));
+            }
+            _ => {}
+        }
+    }
 }
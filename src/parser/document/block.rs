@@ -1,9 +1,10 @@
-use super::ast::{Block, ListItem, ListType};
+use super::ast::{Alignment, Block, Inline, ListItem, ListType};
+use super::html::parse_html_tag;
 use super::inline::parse_inlines;
-use super::{Parser, many, some};
+use super::{Parser, blank_line, many, some};
 use crate::choice;
-use crate::parser::lex::{character, digit, string};
-use crate::parser::{id, newline, parse_line, take_until};
+use crate::parser::lex::{character, string};
+use crate::parser::{id, newline, parse_line};
 
 // 水平線
 fn parse_horizontal_rule<'a>() -> impl Parser<'a, Block> {
@@ -32,6 +33,7 @@ fn parse_heading<'a>() -> impl Parser<'a, Block> {
                     Block::Heading {
                         level: level as u8,
                         content: inlines,
+                        id: None,
                     },
                     rest,
                 ))
@@ -43,29 +45,216 @@ fn parse_heading<'a>() -> impl Parser<'a, Block> {
     }
 }
 
+// コードブロックのフェンス行(3つ以上の ` か ~)
+// (フェンスの文字, フェンスの長さ, インデント幅, フェンスの後ろの文字列)を返す
+fn parse_code_fence<'a>() -> impl Parser<'a, (char, usize, usize, &'a str)> {
+    move |input: &'a str| {
+        let (line, rest) = parse_line().parse(input)?;
+        let indent = indent_width(line);
+        if indent > 3 {
+            return None;
+        }
+
+        let trimmed = line.trim_start();
+        let fence_char = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+        let len = trimmed.chars().take_while(|c| *c == fence_char).count();
+        if len < 3 {
+            return None;
+        }
+
+        Some(((fence_char, len, indent, &trimmed[len..]), rest))
+    }
+}
+
+// 情報文字列 `rust title="main.rs" {3-5}` を言語と属性に分ける
+// `{3-5}` は ("highlight", "3-5")、値のない属性は空文字列の値として扱う
+// 言語の `diff-rust` は言語のrustと属性の ("diff", "") になる
+fn parse_info_string(info: &str) -> (Option<String>, Vec<(String, String)>) {
+    let mut tokens = Vec::new();
+    let mut chars = info.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut token = String::new();
+        let mut quote = None;
+        let close_brace = c == '{';
+        while let Some(&c) = chars.peek() {
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None if c == '"' || c == '\'' => quote = Some(c),
+                None if close_brace && c == '}' => {
+                    token.push(c);
+                    chars.next();
+                    break;
+                }
+                None if !close_brace && c.is_whitespace() => break,
+                None => {}
+            }
+            token.push(c);
+            chars.next();
+        }
+        tokens.push(token);
+    }
+
+    let mut tokens = tokens.into_iter().peekable();
+    let mut language = tokens
+        .next_if(|t| !t.starts_with('{') && !t.contains('='))
+        .map(|t| t.to_string());
+
+    // `diff` と `diff-rust` は差分。`diff-rust` はrustとして色を付ける
+    let mut attributes = Vec::new();
+    if let Some(lang) = &language
+        && (lang == "diff" || lang.starts_with("diff-"))
+    {
+        if let Some(inner) = lang.strip_prefix("diff-") {
+            language = Some(inner.to_string());
+        }
+        attributes.push(("diff".to_string(), String::new()));
+    }
+
+    attributes.extend(tokens.map(|token| {
+        if let Some(ranges) = token.strip_prefix('{') {
+            let ranges = ranges.strip_suffix('}').unwrap_or(ranges);
+            return ("highlight".to_string(), ranges.trim().to_string());
+        }
+        match token.split_once('=') {
+            Some((key, value)) => {
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                    .unwrap_or(value);
+                (key.to_string(), value.to_string())
+            }
+            None => (token, String::new()),
+        }
+    }));
+
+    (language, attributes)
+}
+
 // コードブロック
+// 閉じのフェンスは開きと同じ文字で同じ長さ以上。閉じられていなければ入力の最後までとする
 fn parse_fenced_code_block<'a>() -> impl Parser<'a, Block> {
-    let start = string("```");
-    let end_marker = "```";
-    let language = many(character(|c| c != '\n')).map(|chars| {
-        if chars.is_empty() {
-            None
-        } else {
-            Some(chars.into_iter().collect())
+    move |input: &'a str| {
+        let ((fence_char, fence_len, indent, info), mut rest) = parse_code_fence().parse(input)?;
+        if fence_char == '`' && info.contains('`') {
+            return None;
         }
-    });
 
-    start
-        .and(language)
-        .and(newline())
-        .and(take_until(end_marker))
-        .and(string("```\n").or(string(end_marker)))
-        .map(
-            |((((_start, lang), _), code), _end)| Block::FencedCodeBlock {
-                language: lang,
-                code: code.to_string(),
+        let mut code = String::new();
+        while !rest.is_empty() {
+            let (line, next) = parse_line().parse(rest)?;
+            rest = next;
+
+            if let Some(((c, len, _, after), _)) = parse_code_fence().parse(line)
+                && c == fence_char
+                && len >= fence_len
+                && after.trim().is_empty()
+            {
+                break;
+            }
+
+            code.push_str(strip_indent(line, indent));
+            code.push('\n');
+        }
+
+        let (language, attributes) = parse_info_string(info);
+
+        Some((
+            Block::FencedCodeBlock {
+                language,
+                attributes,
+                code,
+                highlighted: None,
             },
-        )
+            rest,
+        ))
+    }
+}
+
+// インデント(4桁以上)によるコードブロック
+// 段落の途中では始まらない
+fn parse_indented_code_block<'a>() -> impl Parser<'a, Block> {
+    move |input: &'a str| {
+        let (first, mut rest) = parse_line().parse(input)?;
+        if first.trim().is_empty() || indent_width(first) < 4 {
+            return None;
+        }
+
+        let mut lines = vec![strip_indent(first, 4)];
+        while !rest.is_empty() {
+            let (blanks, candidate) = many(blank_line()).parse(rest)?;
+            let Some((line, next)) = parse_line().parse(candidate) else {
+                break;
+            };
+            if line.trim().is_empty() || indent_width(line) < 4 {
+                break;
+            }
+            // 途中の空行はコードの一部
+            lines.extend(blanks.iter().map(|_| ""));
+            lines.push(strip_indent(line, 4));
+            rest = next;
+        }
+
+        let mut code = lines.join("\n");
+        code.push('\n');
+
+        Some((
+            Block::FencedCodeBlock {
+                language: None,
+                attributes: Vec::new(),
+                code,
+                highlighted: None,
+            },
+            rest,
+        ))
+    }
+}
+
+// Obsidianのコールアウトの1行目 `[!kind]+ title`
+// (kind, title, foldable, default_open)を返す
+fn parse_callout_marker<'a>() -> impl Parser<'a, (String, Option<Vec<Inline>>, bool, bool)> {
+    let kind = some(character(|c| c.is_alphanumeric() || c == '-' || c == '_'))
+        .map(|chars| chars.into_iter().collect::<String>().to_lowercase());
+    let fold = choice![
+        string("+").map(|_| (true, true)),
+        string("-").map(|_| (true, false)),
+        id().map(|_: ()| (false, true))
+    ];
+
+    string("[!")
+        .and(kind)
+        .and(string("]"))
+        .and(fold)
+        .and(many(character(|c| c == ' ' || c == '\t')))
+        .map(|((((_start, kind), _end), fold), _spaces)| (kind, fold))
+        .and(move |input: &'a str| match parse_inlines().parse(input) {
+            Some((inlines, rest)) => Some((Some(inlines), rest)),
+            None => Some((None, input)),
+        })
+        .map(|((kind, (foldable, default_open)), title)| (kind, title, foldable, default_open))
+}
+
+// 数式ブロック `$$ ... $$`
+// 閉じの`$$`の後ろに文字が続く場合は段落中の数式として扱う
+fn parse_math_block<'a>() -> impl Parser<'a, Block> {
+    move |input: &'a str| {
+        let rest = input.strip_prefix("$$")?;
+        let end = rest.find("$$")?;
+        let tex = rest[..end].trim();
+        let (trailing, rest) = parse_line().parse(&rest[end + 2..])?;
+
+        if tex.is_empty() || !trailing.trim().is_empty() {
+            return None;
+        }
+
+        Some((Block::MathBlock(tex.to_string()), rest))
+    }
 }
 
 // 引用
@@ -76,17 +265,41 @@ fn parse_blockquote<'a>() -> impl Parser<'a, Block> {
     move |input: &'a str| {
         match quote_lines.parse(input) {
             Some((lines, rest)) => {
+                // 1行目がコールアウトの記法ならコールアウトとして扱う
+                let callout = match parse_callout_marker().parse(lines[0]) {
+                    Some((marker, "")) => Some(marker),
+                    _ => None,
+                };
+                let lines = if callout.is_some() {
+                    &lines[1..]
+                } else {
+                    &lines[..]
+                };
+
                 let capacity = lines.iter().map(|s| s.len() + 1).sum();
                 let mut content = String::with_capacity(capacity);
                 lines.iter().for_each(|s| {
                     content.push_str(s);
                     content.push('\n');
                 });
-                // restは絶対空になるはず
-                if let Some((blocks, _rest)) = some(parse_block()).parse(&content) {
-                    Some((Block::Blockquote(blocks), rest))
-                } else {
-                    None
+                // 空行(`>` だけの行)で区切られた複数のブロックも読む
+                let blocks = match super::parse_document().parse(&content) {
+                    Some((doc, _rest)) => doc.blocks,
+                    _ => Vec::new(),
+                };
+
+                match callout {
+                    Some((kind, title, foldable, default_open)) => Some((
+                        Block::Callout {
+                            kind,
+                            title,
+                            foldable,
+                            default_open,
+                            blocks,
+                        },
+                        rest,
+                    )),
+                    None => Some((Block::Blockquote(blocks), rest)),
                 }
             }
             _ => None,
@@ -94,18 +307,153 @@ fn parse_blockquote<'a>() -> impl Parser<'a, Block> {
     }
 }
 
-// リスト
-fn parse_list_item<'a>(marker_parser: impl Parser<'a, usize>) -> impl Parser<'a, ListItem> {
-    marker_parser
-        .and(string(" "))
-        .and(parse_checkbox().or(id()))
-        .and(parse_inlines())
-        .and(newline())
-        .map(|((((indent, _), checked), inlines), _)| ListItem {
-            indent,
-            checked,
-            content: inlines,
-        })
+// 行頭のインデント幅(タブは4桁として数える)
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+// 行頭のインデントをwidth桁分だけ取り除く
+fn strip_indent(line: &str, width: usize) -> &str {
+    let mut removed = 0;
+    for (i, c) in line.char_indices() {
+        if removed >= width {
+            return &line[i..];
+        }
+        match c {
+            ' ' => removed += 1,
+            '\t' => removed += 4,
+            _ => return &line[i..],
+        }
+    }
+
+    ""
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ListMarker {
+    indent: usize,
+    // 目印の後ろの本文が始まる桁
+    content_indent: usize,
+    // 番号付きリストの番号
+    number: Option<u64>,
+    // `-` `*` `+` または番号の後ろの `.` `)`
+    delimiter: char,
+}
+
+// リストの目印
+fn parse_list_marker<'a>(ordered: bool) -> impl Parser<'a, ListMarker> {
+    let indent = many(character(|c| c == ' ' || c == '\t')).map(|chars| {
+        chars
+            .iter()
+            .map(|c| if *c == '\t' { 4 } else { 1 })
+            .sum::<usize>()
+    });
+    let bullet = character(|c| c == '*' || c == '-' || c == '+').map(|c| (None, c, 1));
+    // CommonMarkに合わせて番号は9桁まで
+    let number = some(character(|c| c.is_ascii_digit()))
+        .and(character(|c| c == '.' || c == ')'))
+        .map(|(digits, delimiter)| {
+            let width = digits.len() + 1;
+            let number = if digits.len() <= 9 {
+                digits.into_iter().collect::<String>().parse().ok()
+            } else {
+                None
+            };
+            (number, delimiter, width)
+        });
+
+    move |input: &'a str| {
+        let (indent, rest) = indent.parse(input)?;
+        let ((number, delimiter, width), rest) = if ordered {
+            number.parse(rest)?
+        } else {
+            bullet.parse(rest)?
+        };
+        if ordered && number.is_none() {
+            return None;
+        }
+        let (_space, rest) = string(" ").parse(rest)?;
+
+        Some((
+            ListMarker {
+                indent,
+                content_indent: indent + width + 1,
+                number,
+                delimiter,
+            },
+            rest,
+        ))
+    }
+}
+
+// リストの項目
+// 目印より深くインデントされた行と遅延継続行を項目の中身として集め、ブロックとして解析し直す
+// 項目の中のブロックが空行で区切られていればlooseとして(項目, 目印, loose)を返す
+fn parse_list_item<'a>(ordered: bool) -> impl Parser<'a, (ListItem, ListMarker, bool)> {
+    let head = parse_list_marker(ordered).and(parse_line());
+
+    move |input: &'a str| {
+        let ((marker, first), mut rest) = head.parse(input)?;
+        let (checked, first) = match parse_checkbox().parse(first) {
+            Some((checked, first)) => (checked, first),
+            None => (None, first),
+        };
+
+        // `1. a` の下に2桁だけ字下げした `  - b` も子要素として扱う
+        let child_indent = marker.content_indent.min(marker.indent + 2);
+        let mut strip = None;
+        let mut lines = vec![first];
+
+        while !rest.is_empty()
+            && let Some((line, next)) = parse_line().parse(rest)
+        {
+            if line.trim().is_empty() {
+                // 空行の後ろに項目の続きがあるか先読みする
+                let (_blanks, following) = many(blank_line()).parse(rest)?;
+                if following.is_empty() || indent_width(following) < child_indent {
+                    break;
+                }
+                lines.push("");
+            } else if indent_width(line) >= child_indent {
+                let width = *strip.get_or_insert(indent_width(line).min(marker.content_indent));
+                lines.push(strip_indent(line, width));
+            } else if lines.last().is_some_and(|l| !l.trim().is_empty()) && !starts_block(rest) {
+                // 遅延継続行(インデントされていない段落の続き)
+                lines.push(line.trim_start());
+            } else {
+                break;
+            }
+            rest = next;
+        }
+
+        let content = lines.join("\n") + "\n";
+        let mut blocks = Vec::new();
+        let mut loose = false;
+        let mut remaining = content.as_str();
+        while let Some((blanks, next)) = many(blank_line()).parse(remaining)
+            && let Some((block, next)) = parse_block().parse(next)
+        {
+            loose |= !blanks.is_empty() && !blocks.is_empty();
+            blocks.push(block);
+            remaining = next;
+        }
+
+        Some((
+            (
+                ListItem {
+                    checked,
+                    blocks,
+                    id: None,
+                },
+                marker,
+                loose,
+            ),
+            rest,
+        ))
+    }
 }
 
 fn parse_checkbox<'a>() -> impl Parser<'a, Option<bool>> {
@@ -115,41 +463,462 @@ fn parse_checkbox<'a>() -> impl Parser<'a, Option<bool>> {
     choice![checked, unchecked]
 }
 
-fn parse_unorderd_list<'a>() -> impl Parser<'a, Block> {
-    let indent = many(character(|c| c == ' ' || c == '\t')).map(|chars| chars.len());
-    let marker_char = choice![string("*"), string("-"), string("+")];
-    let marker = indent.and(marker_char).map(|(len, _)| len);
+// 同じ目印が最初の項目と同じかより深いインデントで続く限り、同じリストの項目とする
+// (items, 最初の番号, loose)を返す
+fn parse_list<'a>(ordered: bool) -> impl Parser<'a, (Vec<ListItem>, Option<u64>, bool)> {
+    let item = parse_list_item(ordered);
+
+    move |input: &'a str| {
+        let ((first, first_marker, mut loose), mut rest) = item.parse(input)?;
+        let mut items = vec![first];
+
+        loop {
+            let (blanks, candidate) = many(blank_line()).parse(rest)?;
+            if indent_width(candidate) < first_marker.indent {
+                break;
+            }
+            match item.parse(candidate) {
+                Some(((next_item, marker, item_loose), next))
+                    if marker.delimiter == first_marker.delimiter =>
+                {
+                    // 項目の間に空行があればloose
+                    loose |= item_loose || !blanks.is_empty();
+                    items.push(next_item);
+                    rest = next;
+                }
+                _ => break,
+            }
+        }
+
+        Some(((items, first_marker.number, loose), rest))
+    }
+}
 
-    some(parse_list_item(marker))
-        .map(ListType::Unordered)
+fn parse_unorderd_list<'a>() -> impl Parser<'a, Block> {
+    parse_list(false)
+        .map(|(items, _number, loose)| ListType::Unordered { items, loose })
         .map(Block::List)
 }
 
 fn parse_orderd_list<'a>() -> impl Parser<'a, Block> {
-    let indent = many(character(|c| c == ' ' || c == '\t')).map(|chars| chars.len());
-    let marker_content = digit().and(string("."));
-    let marker = indent.and(marker_content).map(|(len, _)| len);
-
-    some(parse_list_item(marker))
-        .map(ListType::Ordered)
+    parse_list(true)
+        .map(|(items, number, loose)| ListType::Ordered {
+            start: number.unwrap_or(1),
+            items,
+            loose,
+        })
         .map(Block::List)
 }
 
+// テーブル
+fn split_table_row(line: &str) -> Option<Vec<&str>> {
+    let trimmed = line.trim();
+    if !trimmed.contains('|') {
+        return None;
+    }
+
+    let trimmed = trimmed.strip_prefix('|').unwrap_or(trimmed);
+    let trimmed = match trimmed.strip_suffix('|') {
+        Some(inner) if !inner.ends_with('\\') => inner,
+        _ => trimmed,
+    };
+
+    let mut cells = Vec::new();
+    let mut start = 0;
+    let mut chars = trimmed.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            // エスケープされた`|`はセル内の文字として扱う
+            '\\' => {
+                chars.next();
+            }
+            '|' => {
+                cells.push(trimmed[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    cells.push(trimmed[start..].trim());
+
+    Some(cells)
+}
+
+fn parse_table_alignment(cell: &str) -> Option<Alignment> {
+    let left = cell.starts_with(':');
+    let right = cell.ends_with(':');
+    let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+    if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+        return None;
+    }
+
+    let alignment = match (left, right) {
+        (true, true) => Alignment::Center,
+        (true, false) => Alignment::Left,
+        (false, true) => Alignment::Right,
+        (false, false) => Alignment::None,
+    };
+
+    Some(alignment)
+}
+
+fn parse_table_cell(cell: &str) -> Vec<Inline> {
+    parse_inlines()
+        .parse(cell)
+        .map(|(inlines, _rest)| inlines)
+        .unwrap_or_default()
+}
+
+fn parse_table<'a>() -> impl Parser<'a, Block> {
+    move |input: &'a str| {
+        let (header_line, rest) = parse_line().parse(input)?;
+        let headers = split_table_row(header_line)?;
+
+        let (delimiter_line, mut rest) = parse_line().parse(rest)?;
+        let alignments = split_table_row(delimiter_line)?
+            .into_iter()
+            .map(parse_table_alignment)
+            .collect::<Option<Vec<_>>>()?;
+
+        if headers.len() != alignments.len() {
+            return None;
+        }
+
+        // 列数はヘッダーに合わせ、足りないセルは空で埋める
+        let mut rows = Vec::new();
+        while let Some((line, next)) = parse_line().parse(rest)
+            && let Some(cells) = split_table_row(line)
+        {
+            let mut row: Vec<Vec<Inline>> = cells.into_iter().map(parse_table_cell).collect();
+            row.resize(alignments.len(), Vec::new());
+            rows.push(row);
+            rest = next;
+        }
+
+        Some((
+            Block::Table {
+                headers: headers.into_iter().map(parse_table_cell).collect(),
+                alignments,
+                rows,
+            },
+            rest,
+        ))
+    }
+}
+
+// 脚注定義(2行目以降はインデントされた行を継続とみなす)
+fn parse_footnote_definition<'a>() -> impl Parser<'a, Block> {
+    let label = some(character(|c| c != ']' && !c.is_whitespace()))
+        .map(|chars| chars.into_iter().collect::<String>());
+    let head = string("[^")
+        .and(label)
+        .and(string("]:"))
+        .and(many(character(|c| c == ' ' || c == '\t')))
+        .and(parse_line())
+        .map(|((((_start, label), _colon), _spaces), first)| (label, first));
+
+    move |input: &'a str| {
+        let ((label, first), mut rest) = head.parse(input)?;
+
+        let mut content = format!("{}\n", first);
+        while let Some((line, next)) = parse_line().parse(rest)
+            && let Some(line) = line.strip_prefix("    ").or(line.strip_prefix('\t'))
+        {
+            content.push_str(line);
+            content.push('\n');
+            rest = next;
+        }
+
+        let blocks = match super::parse_document().parse(&content) {
+            Some((doc, _rest)) => doc.blocks,
+            _ => Vec::new(),
+        };
+
+        Some((
+            Block::FootnoteDefinition {
+                label,
+                number: None,
+                references: 0,
+                blocks,
+            },
+            rest,
+        ))
+    }
+}
+
+// CommonMarkでブロックレベルとして扱うタグ
+const HTML_BLOCK_TAGS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "base",
+    "basefont",
+    "blockquote",
+    "body",
+    "caption",
+    "center",
+    "col",
+    "colgroup",
+    "dd",
+    "details",
+    "dialog",
+    "dir",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "frame",
+    "frameset",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hr",
+    "html",
+    "iframe",
+    "legend",
+    "li",
+    "link",
+    "main",
+    "menu",
+    "menuitem",
+    "nav",
+    "noframes",
+    "ol",
+    "optgroup",
+    "option",
+    "p",
+    "param",
+    "search",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "track",
+    "ul",
+];
+
+// 中身をMarkdownとして解析しないタグ
+const HTML_RAW_TAGS: &[&str] = &["pre", "script", "style", "textarea"];
+
+// HTMLブロックの開始行から終わりの条件を決める
+// Some(終わりの文字列を含む行まで) / None(空行の手前まで)
+fn html_block_end(line: &str, interrupting: bool) -> Option<Option<String>> {
+    let lower = line.to_ascii_lowercase();
+    let tag_name = |prefix: &str| {
+        let rest = lower.strip_prefix(prefix)?;
+        let len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+            .unwrap_or(rest.len());
+        let after = &rest[len..];
+        Some((rest[..len].to_string(), after.to_string()))
+    };
+
+    if let Some((name, after)) = tag_name("<")
+        && HTML_RAW_TAGS.contains(&name.as_str())
+        && (after.is_empty() || after.starts_with([' ', '\t', '>']))
+    {
+        return Some(Some(format!("</{}>", name)));
+    }
+
+    for (open, close) in [("<!--", "-->"), ("<?", "?>"), ("<![CDATA[", "]]>")] {
+        if lower.starts_with(open) {
+            return Some(Some(close.to_string()));
+        }
+    }
+    if lower.starts_with("<!") && lower[2..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Some(Some(">".to_string()));
+    }
+
+    if let Some((name, after)) = tag_name("</").or_else(|| tag_name("<"))
+        && HTML_BLOCK_TAGS.contains(&name.as_str())
+        && (after.is_empty() || after.starts_with([' ', '\t', '>']) || after.starts_with("/>"))
+    {
+        return Some(None);
+    }
+
+    // 任意のタグだけの行は段落の途中では始まらない
+    if !interrupting
+        && let Some((tag, rest)) = parse_html_tag().parse(line)
+        && !HTML_RAW_TAGS.contains(&tag.name.to_ascii_lowercase().as_str())
+        && rest.trim().is_empty()
+    {
+        return Some(None);
+    }
+
+    None
+}
+
+// 生のHTMLのブロック
+// 開始の条件と終わりの条件はCommonMarkのHTMLブロックに合わせる
+fn parse_html_block<'a>(interrupting: bool) -> impl Parser<'a, Block> {
+    move |input: &'a str| {
+        let (first, _) = parse_line().parse(input)?;
+        if indent_width(first) > 3 {
+            return None;
+        }
+        let end = html_block_end(first.trim_start(), interrupting)?;
+
+        let mut lines = Vec::new();
+        let mut rest = input;
+        while !rest.is_empty() {
+            let (line, next) = parse_line().parse(rest)?;
+            match &end {
+                Some(close) => {
+                    lines.push(line);
+                    rest = next;
+                    if line.to_ascii_lowercase().contains(close.as_str()) {
+                        break;
+                    }
+                }
+                None if line.trim().is_empty() => break,
+                None => {
+                    lines.push(line);
+                    rest = next;
+                }
+            }
+        }
+
+        Some((Block::Html(lines.join("\n")), rest))
+    }
+}
+
 // 段落
+// 空行か、他のブロックが始まる行までを1つの段落とする
+// 段落の直後の行が下線ならSetext形式の見出しとする
 fn parse_paragraph<'a>() -> impl Parser<'a, Block> {
-    parse_inlines()
-        // .and(newline())
-        .map(|inlines| Block::Paragraph(inlines))
+    move |input: &'a str| {
+        let (first, mut rest) = parse_line().parse(input)?;
+        if first.trim().is_empty() {
+            return None;
+        }
+
+        let mut lines = vec![first];
+        let mut setext_level = None;
+        while !rest.is_empty()
+            && let Some((line, next)) = parse_line().parse(rest)
+            && !line.trim().is_empty()
+        {
+            // 下線(`===` か `---`)が続けば見出しになる
+            if let Some(level) = setext_underline_level(line) {
+                setext_level = Some(level);
+                rest = next;
+                break;
+            }
+            // 4桁以上字下げした行は、リストの目印などがあっても段落の続きになる
+            if indent_width(line) < 4 && interrupts_paragraph(rest) {
+                break;
+            }
+            lines.push(line);
+            rest = next;
+        }
+
+        // 行頭の空白は段落の中身に含めない(リスト項目の中で字下げが余った行も同じ)
+        // 行末の2つ以上の空白は `\` に揃えてからインラインの解析に渡す
+        let last = lines.len() - 1;
+        let mut content = String::new();
+        for (i, line) in lines.iter().enumerate() {
+            let line = line.trim_start();
+            content.push_str(line.trim_end());
+            if i == last {
+                break;
+            }
+            if line.ends_with("  ") {
+                content.push('\\');
+            }
+            content.push('\n');
+        }
+
+        let (inlines, _rest) = parse_inlines().parse(&content)?;
+
+        // 埋め込みだけの段落はブロックとして埋め込む
+        if setext_level.is_none()
+            && let [Inline::Embed(embed)] = inlines.as_slice()
+        {
+            return Some((Block::Embed(embed.clone()), rest));
+        }
+
+        match setext_level {
+            Some(level) => Some((
+                Block::Heading {
+                    level,
+                    content: inlines,
+                    id: None,
+                },
+                rest,
+            )),
+            None => Some((Block::Paragraph(inlines), rest)),
+        }
+    }
 }
 
-pub fn parse_block<'a>() -> impl Parser<'a, Block> {
+// Setext形式の見出しの下線 (`===` はレベル1、`---` はレベル2)
+fn setext_underline_level(line: &str) -> Option<u8> {
+    if indent_width(line) > 3 {
+        return None;
+    }
+
+    let underline = line.trim();
+    match underline.chars().next()? {
+        '=' if underline.chars().all(|c| c == '=') => Some(1),
+        '-' if underline.chars().all(|c| c == '-') => Some(2),
+        _ => None,
+    }
+}
+
+// 段落の途中でも始まることができるブロック
+fn parse_interrupting_block<'a>() -> impl Parser<'a, Block> {
     choice![
         parse_horizontal_rule(),
         parse_fenced_code_block(),
+        parse_math_block(),
         parse_heading(),
         parse_blockquote(),
         parse_unorderd_list(),
         parse_orderd_list(),
+        parse_footnote_definition(),
+        parse_html_block(true),
+        parse_table()
+    ]
+}
+
+// 段落を中断するブロックがinputの先頭から始まるか
+// 後ろのリスト全体などを読み直さないよう、表の区切り行まで含めた2行だけで判断する
+fn starts_block(input: &str) -> bool {
+    let end = input
+        .match_indices('\n')
+        .nth(1)
+        .map_or(input.len(), |(i, _)| i + 1);
+    parse_interrupting_block().parse(&input[..end]).is_some()
+}
+
+// 1以外から始まる番号付きリストは段落を中断せず、段落の続きになる
+fn interrupts_paragraph(input: &str) -> bool {
+    let numbered = parse_list_marker(true)
+        .parse(input)
+        .is_some_and(|(marker, _)| marker.number != Some(1));
+    !numbered && starts_block(input)
+}
+
+pub fn parse_block<'a>() -> impl Parser<'a, Block> {
+    choice![
+        parse_indented_code_block(),
+        parse_interrupting_block(),
+        parse_html_block(false),
         parse_paragraph()
     ]
 }
@@ -170,7 +939,8 @@ mod tests {
             Some((
                 Block::Heading {
                     level: 1,
-                    content: vec![Inline::Text("Title One".to_string())]
+                    content: vec![Inline::Text("Title One".to_string())],
+                    id: None
                 },
                 ""
             ))
@@ -183,7 +953,8 @@ mod tests {
                     content: vec![
                         Inline::Text("Title ".to_string()),
                         Inline::Strong(vec![Inline::Text("Three".to_string())])
-                    ]
+                    ],
+                    id: None
                 },
                 ""
             ))
@@ -210,7 +981,9 @@ mod tests {
             Some((
                 Block::FencedCodeBlock {
                     language: Some("rust".to_string()),
-                    code: "fn main() {}\n".to_string()
+                    attributes: vec![],
+                    code: "fn main() {}\n".to_string(),
+                    highlighted: None
                 },
                 ""
             ))
@@ -220,7 +993,9 @@ mod tests {
             Some((
                 Block::FencedCodeBlock {
                     language: None,
-                    code: "line 1\nline 2\n".to_string()
+                    attributes: vec![],
+                    code: "line 1\nline 2\n".to_string(),
+                    highlighted: None
                 },
                 ""
             ))
@@ -228,37 +1003,540 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_unordered_list() {
-        let parser = parse_unorderd_list();
-        let input = "- Item 1\n- Item **2**\n";
+    fn test_parse_fenced_code_block_variants() {
+        let parser = parse_fenced_code_block();
 
-        match parser(input) {
-            Some((Block::List(ListType::Unordered(items)), rest)) => {
-                assert_eq!(rest, "");
-                assert_eq!(items.len(), 2);
-                assert_eq!(items[0].content, vec![Inline::Text("Item 1".to_string())]);
-                match &items[1].content[1] {
-                    Inline::Strong(_) => assert!(true),
-                    _ => panic!("Expected Strong inline"),
-                }
-            }
-            _ => panic!("Failed to parse unordered list"),
-        }
+        assert_eq!(
+            parser("~~~\n```\nnot closed\n~~~\nrest"),
+            Some((
+                Block::FencedCodeBlock {
+                    language: None,
+                    attributes: vec![],
+                    code: "```\nnot closed\n".to_string(),
+                    highlighted: None
+                },
+                "rest"
+            ))
+        );
+        assert_eq!(
+            parser("````md\n```rust\n```\n`````\n"),
+            Some((
+                Block::FencedCodeBlock {
+                    language: Some("md".to_string()),
+                    attributes: vec![],
+                    code: "```rust\n```\n".to_string(),
+                    highlighted: None
+                },
+                ""
+            ))
+        );
+        assert_eq!(
+            parser("  ```\n  indented\n    more\n  ```\n"),
+            Some((
+                Block::FencedCodeBlock {
+                    language: None,
+                    attributes: vec![],
+                    code: "indented\n  more\n".to_string(),
+                    highlighted: None
+                },
+                ""
+            ))
+        );
+        assert_eq!(parser("``` a`b\n```\n"), None);
+        assert_eq!(parser("``\ncode\n``\n"), None);
     }
 
     #[test]
-    fn test_parse_ordered_list() {
-        let parser = parse_orderd_list();
-        let input = "1. First\n2. Second\n";
-
-        match parser(input) {
-            Some((Block::List(ListType::Ordered(items)), rest)) => {
-                assert_eq!(rest, "");
+    fn test_parse_info_string() {
+        assert_eq!(
+            parse_info_string("rust title=\"src/main.rs\" {3-5,7} linenos"),
+            (
+                Some("rust".to_string()),
+                vec![
+                    ("title".to_string(), "src/main.rs".to_string()),
+                    ("highlight".to_string(), "3-5,7".to_string()),
+                    ("linenos".to_string(), String::new()),
+                ]
+            )
+        );
+        assert_eq!(
+            parse_info_string("title='a b.txt'"),
+            (None, vec![("title".to_string(), "a b.txt".to_string())])
+        );
+        assert_eq!(parse_info_string(""), (None, vec![]));
+        assert_eq!(
+            parse_info_string("diff-rust collapse"),
+            (
+                Some("rust".to_string()),
+                vec![
+                    ("diff".to_string(), String::new()),
+                    ("collapse".to_string(), String::new()),
+                ]
+            )
+        );
+        assert_eq!(
+            parse_info_string("diff"),
+            (
+                Some("diff".to_string()),
+                vec![("diff".to_string(), String::new())]
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_indented_code_block() {
+        let parser = parse_indented_code_block();
+
+        assert_eq!(
+            parser("    let a = 1;\n\n\tlet b = 2;\n\nafter\n"),
+            Some((
+                Block::FencedCodeBlock {
+                    language: None,
+                    attributes: vec![],
+                    code: "let a = 1;\n\nlet b = 2;\n".to_string(),
+                    highlighted: None
+                },
+                "\nafter\n"
+            ))
+        );
+        assert_eq!(parser("   not code\n"), None);
+    }
+
+    #[test]
+    fn test_parse_html_block() {
+        let parser = parse_block();
+
+        assert_eq!(
+            parser("<details>\n<summary>More</summary>\n\n*body*\n"),
+            Some((
+                Block::Html("<details>\n<summary>More</summary>".to_string()),
+                "\n*body*\n"
+            ))
+        );
+        assert_eq!(
+            parser("<script>\nlet a = 1;\n\nlet b = 2;\n</script> after\nnext\n"),
+            Some((
+                Block::Html("<script>\nlet a = 1;\n\nlet b = 2;\n</script> after".to_string()),
+                "next\n"
+            ))
+        );
+        assert_eq!(
+            parser("<!-- a\nb -->\ntext\n"),
+            Some((Block::Html("<!-- a\nb -->".to_string()), "text\n"))
+        );
+        assert_eq!(
+            parser("<kbd class=\"key\">\nCtrl\n"),
+            Some((Block::Html("<kbd class=\"key\">\nCtrl".to_string()), ""))
+        );
+        // 任意のタグの行は段落を中断しない
+        assert_eq!(
+            parser("text\n<kbd>\n<div>\n"),
+            Some((
+                Block::Paragraph(vec![
+                    Inline::Text("text".to_string()),
+                    Inline::SoftBreak,
+                    Inline::Html("<kbd>".to_string())
+                ]),
+                "<div>\n"
+            ))
+        );
+        // 行の途中から始まるタグはインライン
+        assert!(matches!(
+            parser("<kbd>a</kbd> b\n"),
+            Some((Block::Paragraph(_), ""))
+        ));
+    }
+
+    #[test]
+    fn test_parse_setext_heading() {
+        let parser = parse_block();
+
+        assert_eq!(
+            parser("Title\n=====\n"),
+            Some((
+                Block::Heading {
+                    level: 1,
+                    content: vec![Inline::Text("Title".to_string())],
+                    id: None
+                },
+                ""
+            ))
+        );
+        assert_eq!(
+            parser("Sub *title*\n---\nbody\n"),
+            Some((
+                Block::Heading {
+                    level: 2,
+                    content: vec![
+                        Inline::Text("Sub ".to_string()),
+                        Inline::Italic(vec![Inline::Text("title".to_string())])
+                    ],
+                    id: None
+                },
+                "body\n"
+            ))
+        );
+        // 段落の前になければ水平線
+        assert_eq!(parser("---\n"), Some((Block::HorizontalRule, "")));
+        // 段落の途中ではインデントされた行もコードにならない
+        assert_eq!(
+            parser("text\n    more\n"),
+            Some((
+                Block::Paragraph(vec![
+                    Inline::Text("text".to_string()),
+                    Inline::SoftBreak,
+                    Inline::Text("more".to_string())
+                ]),
+                ""
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_math_block() {
+        let parser = parse_math_block();
+
+        assert_eq!(
+            parser("$$\nx^2 + y^2\n$$\nrest"),
+            Some((Block::MathBlock("x^2 + y^2".to_string()), "rest"))
+        );
+        assert_eq!(
+            parser("$$ a = b $$\n"),
+            Some((Block::MathBlock("a = b".to_string()), ""))
+        );
+        assert_eq!(parser("$$a$$ and text\n"), None);
+        assert_eq!(parser("$$\nunclosed\n"), None);
+    }
+
+    #[test]
+    fn test_parse_paragraph() {
+        let parser = parse_paragraph();
+        let input = "First line\n  second *line*\nthird\n\nNext\n";
+
+        assert_eq!(
+            parser(input),
+            Some((
+                Block::Paragraph(vec![
+                    Inline::Text("First line".to_string()),
+                    Inline::SoftBreak,
+                    Inline::Text("second ".to_string()),
+                    Inline::Italic(vec![Inline::Text("line".to_string())]),
+                    Inline::SoftBreak,
+                    Inline::Text("third".to_string()),
+                ]),
+                "\nNext\n"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_paragraph_hard_break() {
+        let parser = parse_paragraph();
+        let input = "spaces  \nbackslash\\\nend  \n";
+
+        assert_eq!(
+            parser(input),
+            Some((
+                Block::Paragraph(vec![
+                    Inline::Text("spaces".to_string()),
+                    Inline::HardBreak,
+                    Inline::Text("backslash".to_string()),
+                    Inline::HardBreak,
+                    Inline::Text("end".to_string()),
+                ]),
+                ""
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_paragraph_interrupted() {
+        let parser = parse_paragraph();
+
+        assert_eq!(
+            parser("Text\n# Heading\n"),
+            Some((
+                Block::Paragraph(vec![Inline::Text("Text".to_string())]),
+                "# Heading\n"
+            ))
+        );
+        assert_eq!(
+            parser("Text\n- item\n"),
+            Some((
+                Block::Paragraph(vec![Inline::Text("Text".to_string())]),
+                "- item\n"
+            ))
+        );
+        assert_eq!(
+            parser("Text\n```\ncode\n```\n"),
+            Some((
+                Block::Paragraph(vec![Inline::Text("Text".to_string())]),
+                "```\ncode\n```\n"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_unordered_list() {
+        let parser = parse_unorderd_list();
+        let input = "- Item 1\n- Item **2**\n";
+
+        match parser(input) {
+            Some((Block::List(ListType::Unordered { items, .. }), rest)) => {
+                assert_eq!(rest, "");
+                assert_eq!(items.len(), 2);
+                assert_eq!(
+                    items[0].blocks,
+                    vec![Block::Paragraph(vec![Inline::Text("Item 1".to_string())])]
+                );
+                match &items[1].blocks[0] {
+                    Block::Paragraph(inlines) => match &inlines[1] {
+                        Inline::Strong(_) => {}
+                        _ => panic!("Expected Strong inline"),
+                    },
+                    _ => panic!("Expected Paragraph"),
+                }
+            }
+            _ => panic!("Failed to parse unordered list"),
+        }
+    }
+
+    #[test]
+    fn test_parse_ordered_list() {
+        let parser = parse_orderd_list();
+        let input = "1. First\n2. Second\n";
+
+        match parser(input) {
+            Some((Block::List(ListType::Ordered { items, .. }), rest)) => {
+                assert_eq!(rest, "");
+                assert_eq!(items.len(), 2);
+                assert_eq!(
+                    items[0].blocks,
+                    vec![Block::Paragraph(vec![Inline::Text("First".to_string())])]
+                );
+            }
+            _ => panic!("Failed to parse ordered list"),
+        }
+    }
+
+    #[test]
+    fn test_parse_list_paragraph_indent() {
+        let text = |s: &str| Block::Paragraph(vec![Inline::Text(s.to_string())]);
+
+        for (input, expected) in [
+            ("- a\n\n   indented\n", vec![text("a"), text("indented")]),
+            ("1.  a\n\n     b\n", vec![text("a"), text("b")]),
+        ] {
+            match parse_block().parse(input) {
+                Some((Block::List(list), "")) => assert_eq!(list.items()[0].blocks, expected),
+                result => panic!("Failed to parse list item {:?}: {:?}", input, result),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_list_over_indented_marker() {
+        // 字下げが深すぎる目印はコードブロックではなく段落の続き
+        let input = "- a\n      - b\n";
+        match parse_block().parse(input) {
+            Some((Block::List(list), "")) => assert_eq!(
+                list.items()[0].blocks,
+                vec![Block::Paragraph(vec![
+                    Inline::Text("a".to_string()),
+                    Inline::SoftBreak,
+                    Inline::Text("- b".to_string()),
+                ])]
+            ),
+            result => panic!("Failed to parse list {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_parse_long_list() {
+        // 項目ごとに残りのリスト全体を読み直すと、項目数に対して指数的に遅くなる
+        let flat: String = (0..300).map(|i| format!("- item {}\n", i)).collect();
+        match parse_block().parse(&flat) {
+            Some((Block::List(list), "")) => assert_eq!(list.items().len(), 300),
+            result => panic!("Failed to parse long list: {:?}", result),
+        }
+
+        let nested: String = (0..200)
+            .map(|i| format!("- item {}\n  - child {}\n", i, i))
+            .collect();
+        match parse_block().parse(&nested) {
+            Some((Block::List(list), "")) => {
+                assert_eq!(list.items().len(), 200);
+                assert!(matches!(list.items()[199].blocks[1], Block::List(_)));
+            }
+            result => panic!("Failed to parse nested list: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_ordered_list_interrupts_paragraph_only_from_one() {
+        let doc = |input| {
+            crate::parser::document::parse_document()(input)
+                .unwrap()
+                .0
+                .blocks
+        };
+
+        assert_eq!(
+            doc("text\n2. x\n"),
+            vec![Block::Paragraph(vec![
+                Inline::Text("text".to_string()),
+                Inline::SoftBreak,
+                Inline::Text("2. x".to_string()),
+            ])]
+        );
+        assert!(matches!(
+            doc("text\n1. x\n").as_slice(),
+            [
+                Block::Paragraph(_),
+                Block::List(ListType::Ordered { start: 1, .. })
+            ]
+        ));
+        assert!(matches!(
+            doc("text\n\n2. x\n").as_slice(),
+            [
+                Block::Paragraph(_),
+                Block::List(ListType::Ordered { start: 2, .. })
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_parse_nested_list() {
+        let parser = parse_unorderd_list();
+        let input = "- a\n  - b\n    1. c\n- d\n\tcontinued\n\t```\n\tcode\n\t```\nlazy\n\nafter\n";
+
+        match parser(input) {
+            Some((Block::List(ListType::Unordered { items, .. }), rest)) => {
+                assert_eq!(rest, "\nafter\n");
+                assert_eq!(items.len(), 2);
+
+                let text = |s: &str| Inline::Text(s.to_string());
+                let b = ListItem {
+                    checked: None,
+                    blocks: vec![
+                        Block::Paragraph(vec![text("b")]),
+                        Block::List(ListType::Ordered {
+                            start: 1,
+                            items: vec![ListItem {
+                                checked: None,
+                                blocks: vec![Block::Paragraph(vec![text("c")])],
+                                id: None,
+                            }],
+                            loose: false,
+                        }),
+                    ],
+                    id: None,
+                };
+                assert_eq!(
+                    items[0].blocks,
+                    vec![
+                        Block::Paragraph(vec![text("a")]),
+                        Block::List(ListType::Unordered {
+                            items: vec![b],
+                            loose: false
+                        }),
+                    ]
+                );
+                assert_eq!(
+                    items[1].blocks,
+                    vec![
+                        Block::Paragraph(vec![text("d"), Inline::SoftBreak, text("continued")]),
+                        Block::FencedCodeBlock {
+                            language: None,
+                            attributes: vec![],
+                            code: "code\n".to_string(),
+                            highlighted: None
+                        },
+                        Block::Paragraph(vec![text("lazy")]),
+                    ]
+                );
+            }
+            _ => panic!("Failed to parse nested list"),
+        }
+    }
+
+    #[test]
+    fn test_parse_list_dedent_by_several_levels() {
+        let parser = parse_unorderd_list();
+        let input = "- a\n  - b\n    - c\n- d\n";
+
+        match parser(input) {
+            Some((Block::List(ListType::Unordered { items, .. }), rest)) => {
+                assert_eq!(rest, "");
                 assert_eq!(items.len(), 2);
-                assert_eq!(items[0].content, vec![Inline::Text("First".to_string())]);
+                assert_eq!(
+                    items[1].blocks,
+                    vec![Block::Paragraph(vec![Inline::Text("d".to_string())])]
+                );
+            }
+            _ => panic!("Failed to parse list"),
+        }
+    }
+
+    #[test]
+    fn test_parse_list_ends_at_other_kind() {
+        let parser = parse_unorderd_list();
+
+        match parser("- a\n1. b\n") {
+            Some((Block::List(ListType::Unordered { items, .. }), rest)) => {
+                assert_eq!(items.len(), 1);
+                assert_eq!(rest, "1. b\n");
+            }
+            _ => panic!("Failed to parse list"),
+        }
+    }
+
+    #[test]
+    fn test_parse_ordered_list_start_and_delimiter() {
+        let parser = parse_orderd_list();
+
+        match parser("3) Third\n4) Fourth\n1. Other list\n") {
+            Some((Block::List(ListType::Ordered { start, items, .. }), rest)) => {
+                assert_eq!(start, 3);
+                assert_eq!(items.len(), 2);
+                assert_eq!(rest, "1. Other list\n");
             }
             _ => panic!("Failed to parse ordered list"),
         }
+
+        assert_eq!(parser("1234567890. Too long\n"), None);
+    }
+
+    #[test]
+    fn test_parse_loose_list() {
+        let parser = parse_unorderd_list();
+
+        match parser("- a\n- b\n") {
+            Some((Block::List(ListType::Unordered { loose, .. }), _)) => assert!(!loose),
+            _ => panic!("Failed to parse tight list"),
+        }
+        match parser("- a\n\n- b\n") {
+            Some((Block::List(ListType::Unordered { items, loose }), _)) => {
+                assert!(loose);
+                assert_eq!(items.len(), 2);
+            }
+            _ => panic!("Failed to parse loose list"),
+        }
+        match parser("- a\n\n  second paragraph\n- b\n") {
+            Some((Block::List(ListType::Unordered { items, loose }), _)) => {
+                assert!(loose);
+                assert_eq!(items[0].blocks.len(), 2);
+            }
+            _ => panic!("Failed to parse loose list"),
+        }
+        // 入れ子のリストがlooseでも外側はtightのまま
+        match parser("- a\n  - b\n\n  - c\n- d\n") {
+            Some((Block::List(ListType::Unordered { items, loose }), _)) => {
+                assert!(!loose);
+                assert_eq!(items.len(), 2);
+            }
+            _ => panic!("Failed to parse nested loose list"),
+        }
     }
 
     #[test]
@@ -267,26 +1545,221 @@ mod tests {
         let input = "- [x] Done task\n- [ ] Pending task\n- Normal item\n";
 
         match parser(input) {
-            Some((Block::List(ListType::Unordered(items)), rest)) => {
+            Some((Block::List(ListType::Unordered { items, .. }), rest)) => {
                 assert_eq!(rest, "");
                 assert_eq!(items.len(), 3);
 
                 assert_eq!(items[0].checked, Some(true));
-                assert_eq!(items[0].content[0], Inline::Text("Done task".to_string()));
+                assert_eq!(
+                    items[0].blocks,
+                    vec![Block::Paragraph(vec![Inline::Text(
+                        "Done task".to_string()
+                    )])]
+                );
 
                 assert_eq!(items[1].checked, Some(false));
                 assert_eq!(
-                    items[1].content[0],
-                    Inline::Text("Pending task".to_string())
+                    items[1].blocks,
+                    vec![Block::Paragraph(vec![Inline::Text(
+                        "Pending task".to_string()
+                    )])]
                 );
 
                 assert_eq!(items[2].checked, None);
-                assert_eq!(items[2].content[0], Inline::Text("Normal item".to_string()));
+                assert_eq!(
+                    items[2].blocks,
+                    vec![Block::Paragraph(vec![Inline::Text(
+                        "Normal item".to_string()
+                    )])]
+                );
             }
             _ => panic!("Failed to parse ordered list"),
         }
     }
 
+    #[test]
+    fn test_parse_table() {
+        let parser = parse_table();
+        let input = "| Left | Center | Right | None |\n|:---|:---:|---:|---|\n| a | **b** | c |\n| 1 | 2 | 3 | 4 |\n\nafter\n";
+
+        match parser(input) {
+            Some((
+                Block::Table {
+                    headers,
+                    alignments,
+                    rows,
+                },
+                rest,
+            )) => {
+                assert_eq!(rest, "\nafter\n");
+                assert_eq!(headers.len(), 4);
+                assert_eq!(headers[0], vec![Inline::Text("Left".to_string())]);
+                assert_eq!(
+                    alignments,
+                    vec![
+                        Alignment::Left,
+                        Alignment::Center,
+                        Alignment::Right,
+                        Alignment::None
+                    ]
+                );
+                assert_eq!(rows.len(), 2);
+                assert_eq!(
+                    rows[0][1],
+                    vec![Inline::Strong(vec![Inline::Text("b".to_string())])]
+                );
+                assert_eq!(rows[0][3], vec![]);
+                assert_eq!(rows[1][3], vec![Inline::Text("4".to_string())]);
+            }
+            _ => panic!("Failed to parse table"),
+        }
+    }
+
+    #[test]
+    fn test_parse_table_escaped_pipe() {
+        let parser = parse_table();
+        let input = "a | b\n--|--\n`x` | a \\| b\n";
+
+        match parser(input) {
+            Some((Block::Table { rows, .. }, rest)) => {
+                assert_eq!(rest, "");
+                assert_eq!(rows[0].len(), 2);
+                assert_eq!(rows[0][1], vec![Inline::Text("a | b".to_string())]);
+            }
+            _ => panic!("Failed to parse table"),
+        }
+    }
+
+    #[test]
+    fn test_parse_table_requires_delimiter_row() {
+        let parser = parse_table();
+
+        assert_eq!(parser("| a | b |\nnot a delimiter\n"), None);
+        assert_eq!(parser("| a | b |\n|---|\n"), None);
+    }
+
+    #[test]
+    fn test_parse_footnote_definition() {
+        let parser = parse_footnote_definition();
+        let input = "[^1]: First line\n    **continued**\nNext paragraph\n";
+
+        match parser(input) {
+            Some((Block::FootnoteDefinition { label, blocks, .. }, rest)) => {
+                assert_eq!(rest, "Next paragraph\n");
+                assert_eq!(label, "1");
+                assert_eq!(
+                    blocks,
+                    vec![Block::Paragraph(vec![
+                        Inline::Text("First line".to_string()),
+                        Inline::SoftBreak,
+                        Inline::Strong(vec![Inline::Text("continued".to_string())])
+                    ])]
+                );
+            }
+            _ => panic!("Failed to parse footnote definition"),
+        }
+
+        assert_eq!(parser("[^1] not a definition\n"), None);
+    }
+
+    #[test]
+    fn test_parse_callout() {
+        let parser = parse_blockquote();
+        let input = "> [!Warning] Be **careful**\n> Body text\n";
+        let input2 = "> [!tip]-\n> Folded\n";
+        let input3 = "> [!note]+ Open\n";
+
+        match parser(input) {
+            Some((
+                Block::Callout {
+                    kind,
+                    title,
+                    foldable,
+                    blocks,
+                    ..
+                },
+                rest,
+            )) => {
+                assert_eq!(rest, "");
+                assert_eq!(kind, "warning");
+                assert_eq!(
+                    title,
+                    Some(vec![
+                        Inline::Text("Be ".to_string()),
+                        Inline::Strong(vec![Inline::Text("careful".to_string())])
+                    ])
+                );
+                assert!(!foldable);
+                assert_eq!(
+                    blocks,
+                    vec![Block::Paragraph(vec![Inline::Text(
+                        "Body text".to_string()
+                    )])]
+                );
+            }
+            _ => panic!("Failed to parse callout"),
+        }
+
+        match parser(input2) {
+            Some((
+                Block::Callout {
+                    kind,
+                    title,
+                    foldable,
+                    default_open,
+                    ..
+                },
+                _,
+            )) => {
+                assert_eq!(kind, "tip");
+                assert_eq!(title, None);
+                assert!(foldable);
+                assert!(!default_open);
+            }
+            _ => panic!("Failed to parse folded callout"),
+        }
+
+        match parser(input3) {
+            Some((
+                Block::Callout {
+                    foldable,
+                    default_open,
+                    blocks,
+                    ..
+                },
+                _,
+            )) => {
+                assert!(foldable);
+                assert!(default_open);
+                assert!(blocks.is_empty());
+            }
+            _ => panic!("Failed to parse open callout"),
+        }
+
+        match parser("> Quote with [!note] inside\n") {
+            Some((Block::Blockquote(_), _)) => {}
+            _ => panic!("Marker in the middle should stay a blockquote"),
+        }
+    }
+
+    #[test]
+    fn test_parse_callout_paragraphs() {
+        let text = |s: &str| Block::Paragraph(vec![Inline::Text(s.to_string())]);
+
+        match parse_blockquote()("> [!note] T\n> first\n>\n> second\n") {
+            Some((Block::Callout { blocks, .. }, "")) => {
+                assert_eq!(blocks, vec![text("first"), text("second")]);
+            }
+            result => panic!("Failed to parse callout: {:?}", result),
+        }
+        match parse_blockquote()("> one\n>\n> two\n") {
+            Some((Block::Blockquote(blocks), "")) => {
+                assert_eq!(blocks, vec![text("one"), text("two")]);
+            }
+            result => panic!("Failed to parse blockquote: {:?}", result),
+        }
+    }
+
     #[test]
     fn test_parse_blockquote() {
         let parser = parse_blockquote();
@@ -313,7 +1786,7 @@ mod tests {
                 assert_eq!(rest, "");
                 assert_eq!(blocks.len(), 2);
 
-                if let Block::Heading { level, content } = &blocks[0] {
+                if let Block::Heading { level, content, .. } = &blocks[0] {
                     assert_eq!(*level, 1);
                     assert_eq!(content[0], Inline::Text("Heading inside".to_string()));
                 } else {
@@ -1,44 +1,727 @@
-use std::collections::HashMap;
-
-pub fn parse_front_matter(content: &str) -> (Option<HashMap<String, String>>, &str) {
-    if !content.starts_with("---\n") {
-        return (None, content);
-    }
-
-    if let Some(end_fm_pos) = content.get(4..).and_then(|s| s.find("---\n")) {
-        let fm_str = &content[4..4 + end_fm_pos];
-        let body_str = &content[4 + end_fm_pos + 4..];
-
-        let mut data = HashMap::new();
-        let mut current_list_key: Option<String> = None;
-
-        for l in fm_str.lines() {
-            let trimed = l.trim();
-
-            if trimed.starts_with("- ") {
-                if let Some(key) = &current_list_key {
-                    if let Some(value) = trimed.strip_prefix("- ") {
-                        let entry = data.entry(key.to_string()).or_insert(String::new());
-                        if !entry.is_empty() {
-                            entry.push_str(", ");
-                        }
-                        entry.push_str(value);
-                    }
-                }
-                continue;
-            }
+mod json;
+pub mod toml;
+mod yaml;
+
+use std::fmt;
+
+// front matterの値
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Date(Date),
+    List(Vec<Value>),
+    // 書かれた順を保つ
+    Map(Vec<(String, Value)>),
+}
+
+// `2024-01-02` か `2024-01-02T03:04:05+09:00`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    // (時, 分, 秒)
+    pub time: Option<(u32, u32, u32)>,
+    // UTCからのずれ(分)。`Z` は0
+    pub offset: Option<i32>,
+}
+
+impl Date {
+    // UNIX時刻(秒)をUTCの日時にする
+    pub fn from_unix_time(secs: i64) -> Date {
+        let days = secs.div_euclid(86400);
+        let seconds = secs.rem_euclid(86400) as u32;
+
+        // 0000-03-01からの日数として数え、グレゴリオ暦の400年周期で年月日を求める
+        let days = days + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days.rem_euclid(146097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        } as u32;
+        let year = (year_of_era + era * 400 + i64::from(month <= 2)) as i32;
+
+        Date {
+            year,
+            month,
+            day,
+            time: Some((seconds / 3600, seconds / 60 % 60, seconds % 60)),
+            offset: Some(0),
+        }
+    }
+
+    // 1970-01-01からの日数。from_unix_timeの逆
+    pub fn days_since_epoch(&self) -> i64 {
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let month = i64::from(self.month);
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5
+            + i64::from(self.day)
+            - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+        era * 146097 + day_of_era - 719468
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FrontMatter {
+    values: Vec<(String, Value)>,
+}
+
+// 行番号はファイルの先頭を1行目とする
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrontMatterError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for FrontMatterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid front matter at line {}: {}",
+            self.line, self.message
+        )
+    }
+}
+
+impl std::error::Error for FrontMatterError {}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_date(&self) -> Option<&Date> {
+        match self {
+            Value::Date(date) => Some(date),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    // 文字列のリストか、`,` 区切りの文字列
+    fn as_str_list(&self) -> Vec<&str> {
+        match self {
+            Value::List(values) => values.iter().filter_map(Value::as_str).collect(),
+            Value::String(s) => s
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl FrontMatter {
+    pub fn new(values: Vec<(String, Value)>) -> Self {
+        FrontMatter { values }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.values.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub fn values(&self) -> &[(String, Value)] {
+        &self.values
+    }
+
+    pub fn to_value(&self) -> Value {
+        Value::Map(self.values.clone())
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(Value::as_str)
+    }
+
+    // `key: true` か。以前の `key: "true"` も受け付ける
+    pub fn flag(&self, key: &str) -> bool {
+        match self.get(key) {
+            Some(Value::Bool(flag)) => *flag,
+            Some(Value::String(flag)) => flag == "true",
+            _ => false,
+        }
+    }
+
+    pub fn publish(&self) -> bool {
+        self.flag("publish")
+    }
+
+    pub fn noindex(&self) -> bool {
+        self.flag("noindex")
+    }
+
+    // JSONでは日付が文字列になる
+    fn date_of(&self, key: &str) -> Option<Date> {
+        match self.get(key)? {
+            Value::Date(date) => Some(*date),
+            Value::String(date) => parse_date(date),
+            _ => None,
+        }
+    }
+
+    pub fn date(&self) -> Option<Date> {
+        self.date_of("date")
+    }
+
+    // 更新日。`updated` か `lastmod`
+    pub fn updated(&self) -> Option<Date> {
+        self.date_of("updated").or_else(|| self.date_of("lastmod"))
+    }
+
+    pub fn slug(&self) -> Option<&str> {
+        self.get_str("slug").filter(|s| !s.is_empty())
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.get_str("title").filter(|s| !s.is_empty())
+    }
 
-            if let Some((key, value)) = trimed.split_once(":") {
-                if value.is_empty() {
-                    current_list_key = Some(key.to_string());
-                    data.insert(key.to_string(), String::new());
-                } else {
-                    data.insert(key.to_string(), value.trim().to_string());
-                    current_list_key = None;
-                }
+    pub fn layout(&self) -> Option<&str> {
+        self.get_str("layout").filter(|s| !s.is_empty())
+    }
+
+    // Obsidianのタグは先頭の `#` を付けても付けなくてもよい
+    pub fn tags(&self) -> Vec<&str> {
+        self.get("tags")
+            .map(Value::as_str_list)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tag| tag.trim_start_matches('#'))
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
+
+    pub fn aliases(&self) -> Vec<&str> {
+        ["aliases", "alias"]
+            .iter()
+            .filter_map(|key| self.get(key))
+            .flat_map(Value::as_str_list)
+            .collect()
+    }
+}
+
+fn error<T>(line: usize, message: impl Into<String>) -> Result<T, FrontMatterError> {
+    Err(FrontMatterError {
+        line,
+        message: message.into(),
+    })
+}
+
+fn parse_digits(s: &str, len: usize) -> Option<u32> {
+    if s.len() == len && s.bytes().all(|b| b.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+// 閏年を考えた月の日数
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn parse_date(s: &str) -> Option<Date> {
+    let (date, rest) = s.split_at_checked(10)?;
+    let mut parts = date.split('-');
+    let year = parse_digits(parts.next()?, 4)? as i32;
+    let month = parse_digits(parts.next()?, 2)?;
+    let day = parse_digits(parts.next()?, 2)?;
+    // 2024-02-30 のような存在しない日付は文字列のまま
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let mut date = Date {
+        year,
+        month,
+        day,
+        time: None,
+        offset: None,
+    };
+    if rest.is_empty() {
+        return Some(date);
+    }
+
+    let rest = rest.strip_prefix(['T', 't', ' '])?;
+    let time_len = rest
+        .find(|c: char| !c.is_ascii_digit() && c != ':' && c != '.')
+        .unwrap_or(rest.len());
+    let (time, zone) = rest.split_at(time_len);
+    let mut time_parts = time.split(':');
+    let hour = parse_digits(time_parts.next()?, 2)?;
+    let minute = parse_digits(time_parts.next()?, 2)?;
+    let second = match time_parts.next() {
+        // 小数点以下の秒は捨てる
+        Some(second) => parse_digits(second.split('.').next()?, 2)?,
+        None => 0,
+    };
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    date.time = Some((hour, minute, second));
+
+    let zone = zone.trim_start();
+    date.offset = match zone {
+        "" => None,
+        "Z" | "z" => Some(0),
+        _ => {
+            let sign = match zone.chars().next()? {
+                '+' => 1,
+                '-' => -1,
+                _ => return None,
+            };
+            let (hours, minutes) = zone[1..].split_once(':').unwrap_or((&zone[1..], "00"));
+            let hours = parse_digits(hours, 2)? as i32;
+            let minutes = parse_digits(minutes, 2)? as i32;
+            Some(sign * (hours * 60 + minutes))
+        }
+    };
+
+    Some(date)
+}
+
+// `---` `+++` の行で囲まれた部分と本文に分ける
+fn split_fenced<'a>(
+    content: &'a str,
+    fence: &str,
+) -> Result<Option<(&'a str, &'a str)>, FrontMatterError> {
+    let mut lines = content.split_inclusive('\n');
+    match lines.next() {
+        Some(first) if first.ends_with('\n') && first.trim_end() == fence => {}
+        _ => return Ok(None),
+    }
+
+    let mut offset = content.find('\n').unwrap_or(0) + 1;
+    for line in lines {
+        if line.trim_end() == fence {
+            return Ok(Some((&content[..offset], &content[offset + line.len()..])));
+        }
+        offset += line.len();
+    }
+
+    error(1, format!("front matter is not closed with `{}`", fence))
+}
+
+// YAML(`---`)、TOML(`+++`)、JSON(`{`)のfront matterと本文を分ける
+pub fn parse_front_matter(content: &str) -> Result<(Option<FrontMatter>, &str), FrontMatterError> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+
+    // CRLFの行末はLFにしてから読む
+    let normalize = |source: &str| source.replace("\r\n", "\n");
+    // 開きの行を除いたfront matterは2行目から始まる
+    let (front_matter, body) = if let Some((source, body)) = split_fenced(content, "---")? {
+        let source = normalize(source);
+        let source = &source[source.find('\n').unwrap_or(0) + 1..];
+        (FrontMatter::new(yaml::parse_yaml(source, 2)?), body)
+    } else if let Some((source, body)) = split_fenced(content, "+++")? {
+        let source = normalize(source);
+        let source = &source[source.find('\n').unwrap_or(0) + 1..];
+        (FrontMatter::new(toml::parse_toml(source, 2)?), body)
+    } else if content.starts_with('{') && content[1..].trim_start().starts_with(['"', '}']) {
+        let (front_matter, rest) = json::parse_json(content, 1)?;
+        // 閉じ括弧の後ろは改行だけ
+        let rest = rest.trim_start_matches([' ', '\t']);
+        let body = match rest
+            .strip_prefix("\r\n")
+            .or_else(|| rest.strip_prefix('\n'))
+        {
+            Some(body) => body,
+            None if rest.is_empty() => rest,
+            None => {
+                let line = 1 + content[..content.len() - rest.len()].matches('\n').count();
+                return error(line, "unexpected characters after front matter");
             }
+        };
+        (front_matter, body)
+    } else {
+        return Ok((None, content));
+    };
+
+    Ok((Some(front_matter), body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yaml: &str) -> Result<FrontMatter, FrontMatterError> {
+        let content = format!("---\n{}---\nbody\n", yaml);
+        parse_front_matter(&content).map(|(fm, _)| fm.unwrap())
+    }
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn test_parse_front_matter_formats() {
+        let title = |content: &str| {
+            let (fm, body) = parse_front_matter(content).unwrap();
+            (fm.unwrap().title().map(str::to_string), body.to_string())
+        };
+
+        assert_eq!(
+            title("---\r\ntitle: a\r\n---\r\nbody\r\n"),
+            (Some("a".to_string()), "body\r\n".to_string())
+        );
+        assert_eq!(
+            title("+++\ntitle = \"b\"\n+++\nbody\n"),
+            (Some("b".to_string()), "body\n".to_string())
+        );
+        assert_eq!(
+            title("\u{feff}+++\r\ntitle = 'c'\r\n+++\r\n"),
+            (Some("c".to_string()), "".to_string())
+        );
+        assert_eq!(
+            title("{\n  \"title\": \"d\"\n}\nbody\n"),
+            (Some("d".to_string()), "body\n".to_string())
+        );
+        assert_eq!(
+            parse_front_matter("{{template}}\n"),
+            Ok((None, "{{template}}\n"))
+        );
+        assert_eq!(
+            parse_front_matter("+++\ntitle = \"a\"\nx\n+++\n")
+                .unwrap_err()
+                .line,
+            3
+        );
+        assert_eq!(
+            parse_front_matter("{\"title\": \"a\"} text\n")
+                .unwrap_err()
+                .line,
+            1
+        );
+    }
+
+    #[test]
+    fn test_parse_front_matter_body() {
+        assert_eq!(
+            parse_front_matter("---\ntitle: a\n---\n# Body\n").map(|(_, body)| body),
+            Ok("# Body\n")
+        );
+        assert_eq!(
+            parse_front_matter("# No front matter\n"),
+            Ok((None, "# No front matter\n"))
+        );
+        assert_eq!(
+            parse_front_matter("---\n---\nbody"),
+            Ok((Some(FrontMatter::default()), "body"))
+        );
+    }
+
+    #[test]
+    fn test_parse_scalars() {
+        let fm = parse(concat!(
+            "title: \"a: b\"\n",
+            "url: https://example.com/a#b\n",
+            "quote: 'it''s'\n",
+            "escaped: \"tab\\there \\u00e9\"\n",
+            "publish: true\n",
+            "draft: False\n",
+            "count: 42\n",
+            "ratio: -1.5\n",
+            "version: 1.2.3\n",
+            "empty:\n",
+            "tilde: ~\n",
+            "comment: value # comment\n",
+            "hash: C#\n",
+        ))
+        .unwrap();
+
+        assert_eq!(fm.get("title"), Some(&string("a: b")));
+        assert_eq!(fm.get("url"), Some(&string("https://example.com/a#b")));
+        assert_eq!(fm.get("quote"), Some(&string("it's")));
+        assert_eq!(fm.get("escaped"), Some(&string("tab\there é")));
+        assert_eq!(fm.get("publish"), Some(&Value::Bool(true)));
+        assert_eq!(fm.get("draft"), Some(&Value::Bool(false)));
+        assert_eq!(fm.get("count"), Some(&Value::Integer(42)));
+        assert_eq!(fm.get("ratio"), Some(&Value::Float(-1.5)));
+        assert_eq!(fm.get("version"), Some(&string("1.2.3")));
+        assert_eq!(fm.get("empty"), Some(&Value::Null));
+        assert_eq!(fm.get("tilde"), Some(&Value::Null));
+        assert_eq!(fm.get("comment"), Some(&string("value")));
+        assert_eq!(fm.get("hash"), Some(&string("C#")));
+    }
+
+    #[test]
+    fn test_parse_dates() {
+        let fm = parse(concat!(
+            "date: 2024-03-05\n",
+            "updated: 2024-03-05T10:20:30+09:00\n",
+            "utc: 2024-03-05 10:20Z\n",
+            "not_date: 2024-13-05\n",
+            "feb_30: 2024-02-30\n",
+            "leap: 2024-02-29\n",
+            "not_leap: 2023-02-29\n",
+            "century: 1900-02-29\n",
+            "april: 2024-04-31\n",
+        ))
+        .unwrap();
+
+        assert_eq!(
+            fm.get("date"),
+            Some(&Value::Date(Date {
+                year: 2024,
+                month: 3,
+                day: 5,
+                time: None,
+                offset: None
+            }))
+        );
+        assert_eq!(
+            fm.get("updated"),
+            Some(&Value::Date(Date {
+                year: 2024,
+                month: 3,
+                day: 5,
+                time: Some((10, 20, 30)),
+                offset: Some(540)
+            }))
+        );
+        assert_eq!(
+            fm.get("utc")
+                .and_then(Value::as_date)
+                .map(|d| (d.time, d.offset)),
+            Some((Some((10, 20, 0)), Some(0)))
+        );
+        assert_eq!(fm.get("not_date"), Some(&string("2024-13-05")));
+        assert_eq!(fm.get("feb_30"), Some(&string("2024-02-30")));
+        assert_eq!(
+            fm.get("leap").and_then(Value::as_date).map(|d| d.day),
+            Some(29)
+        );
+        assert_eq!(fm.get("not_leap"), Some(&string("2023-02-29")));
+        assert_eq!(fm.get("century"), Some(&string("1900-02-29")));
+        assert_eq!(fm.get("april"), Some(&string("2024-04-31")));
+    }
+
+    #[test]
+    fn test_date_from_unix_time() {
+        let date = |secs| {
+            let d = Date::from_unix_time(secs);
+            (d.year, d.month, d.day, d.time)
+        };
+
+        assert_eq!(date(0), (1970, 1, 1, Some((0, 0, 0))));
+        assert_eq!(date(951825600), (2000, 2, 29, Some((12, 0, 0))));
+        assert_eq!(date(1709640030), (2024, 3, 5, Some((12, 0, 30))));
+        assert_eq!(date(-1), (1969, 12, 31, Some((23, 59, 59))));
+        for secs in [0, 951825600, 1709640030, -86400 * 1000] {
+            assert_eq!(
+                Date::from_unix_time(secs).days_since_epoch(),
+                secs.div_euclid(86400)
+            );
         }
-        return (Some(data), body_str);
     }
-    (None, content)
+
+    #[test]
+    fn test_parse_collections() {
+        let fm = parse(concat!(
+            "tags:\n",
+            "  - rust\n",
+            "  - \"#web\"\n",
+            "aliases: [First, 'Second, with comma']\n",
+            "same_indent:\n",
+            "- 1\n",
+            "- 2\n",
+            "author:\n",
+            "  name: Alice\n",
+            "  links: {site: https://example.com, x: null}\n",
+            "people:\n",
+            "  - name: Bob\n",
+            "    age: 30\n",
+            "  - name: Carol\n",
+            "multi: [a,\n",
+            "  b]\n",
+        ))
+        .unwrap();
+
+        assert_eq!(fm.tags(), vec!["rust", "web"]);
+        assert_eq!(fm.aliases(), vec!["First", "Second, with comma"]);
+        assert_eq!(
+            fm.get("same_indent"),
+            Some(&Value::List(vec![Value::Integer(1), Value::Integer(2)]))
+        );
+        let author = fm.get("author").unwrap();
+        assert_eq!(author.get("name"), Some(&string("Alice")));
+        assert_eq!(
+            author.get("links"),
+            Some(&Value::Map(vec![
+                ("site".to_string(), string("https://example.com")),
+                ("x".to_string(), Value::Null),
+            ]))
+        );
+        assert_eq!(
+            fm.get("people"),
+            Some(&Value::List(vec![
+                Value::Map(vec![
+                    ("name".to_string(), string("Bob")),
+                    ("age".to_string(), Value::Integer(30)),
+                ]),
+                Value::Map(vec![("name".to_string(), string("Carol"))]),
+            ]))
+        );
+        assert_eq!(
+            fm.get("multi"),
+            Some(&Value::List(vec![string("a"), string("b")]))
+        );
+    }
+
+    #[test]
+    fn test_parse_nested_flow() {
+        let fm = parse(concat!(
+            "list: {a: [1, 2]}\n",
+            "map: {a: {b: 1}, c: d}\n",
+            "quoted: {a: \"x, y\", 'k: v': z}\n",
+            "json: {\"a\":1, \"b\": [true]}\n",
+            "items: [{name: a, tags: [x]}, {}]\n",
+        ))
+        .unwrap();
+
+        assert_eq!(
+            fm.get("list"),
+            Some(&Value::Map(vec![(
+                "a".to_string(),
+                Value::List(vec![Value::Integer(1), Value::Integer(2)])
+            )]))
+        );
+        assert_eq!(
+            fm.get("map"),
+            Some(&Value::Map(vec![
+                (
+                    "a".to_string(),
+                    Value::Map(vec![("b".to_string(), Value::Integer(1))])
+                ),
+                ("c".to_string(), string("d")),
+            ]))
+        );
+        assert_eq!(
+            fm.get("quoted"),
+            Some(&Value::Map(vec![
+                ("a".to_string(), string("x, y")),
+                ("k: v".to_string(), string("z")),
+            ]))
+        );
+        assert_eq!(
+            fm.get("json"),
+            Some(&Value::Map(vec![
+                ("a".to_string(), Value::Integer(1)),
+                ("b".to_string(), Value::List(vec![Value::Bool(true)])),
+            ]))
+        );
+        assert_eq!(
+            fm.get("items"),
+            Some(&Value::List(vec![
+                Value::Map(vec![
+                    ("name".to_string(), string("a")),
+                    ("tags".to_string(), Value::List(vec![string("x")])),
+                ]),
+                Value::Map(Vec::new()),
+            ]))
+        );
+        assert_eq!(parse("a: {: 1}\n").unwrap_err().line, 2);
+    }
+
+    #[test]
+    fn test_parse_block_scalars() {
+        let fm = parse(concat!(
+            "literal: |\n",
+            "  line 1\n",
+            "    indented\n",
+            "\n",
+            "  line 3\n",
+            "folded: >-\n",
+            "  a\n",
+            "  b\n",
+            "\n",
+            "  c\n",
+            "plain: first\n",
+            "  continued\n",
+        ))
+        .unwrap();
+
+        assert_eq!(
+            fm.get("literal"),
+            Some(&string("line 1\n  indented\n\nline 3\n"))
+        );
+        assert_eq!(fm.get("folded"), Some(&string("a b\nc")));
+        assert_eq!(fm.get("plain"), Some(&string("first continued")));
+    }
+
+    #[test]
+    fn test_accessors() {
+        let fm = parse("publish: true\nslug: my-page\ntitle: ''\ntags: a, b\n").unwrap();
+
+        assert!(fm.publish());
+        assert_eq!(fm.slug(), Some("my-page"));
+        assert_eq!(fm.title(), None);
+        assert_eq!(fm.tags(), vec!["a", "b"]);
+        assert!(!parse("publish: \"yes\"\n").unwrap().publish());
+        assert!(!fm.noindex());
+
+        let fm = parse("noindex: true\ndate: 2024-03-01\nlastmod: \"2024-03-09\"\n").unwrap();
+        assert!(fm.noindex());
+        assert_eq!(fm.updated().map(|d| d.day), Some(9));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let line_of = |yaml: &str| parse(yaml).unwrap_err().line;
+
+        assert_eq!(line_of("title: a\nbroken line\n"), 3);
+        assert_eq!(line_of("title: a\n  nested: b\n"), 3);
+        assert_eq!(line_of("title: a\ntitle: b\n"), 3);
+        assert_eq!(line_of("title: \"open\n"), 2);
+        assert_eq!(line_of("tags: [a, b\n"), 2);
+        assert_eq!(line_of("\ttitle: a\n"), 2);
+        assert_eq!(
+            parse("a: 1\na: 2\n").unwrap_err().to_string(),
+            "invalid front matter at line 3: duplicate key `a`"
+        );
+        assert_eq!(parse_front_matter("---\ntitle: a\n").unwrap_err().line, 1);
+    }
 }
@@ -0,0 +1,254 @@
+use super::{FrontMatter, FrontMatterError, Value, error};
+
+struct JsonParser<'a> {
+    input: &'a str,
+    // ファイル全体での行番号
+    line: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input.chars().next()
+    }
+
+    fn advance(&mut self, len: usize) {
+        self.line += self.input[..len].matches('\n').count();
+        self.input = &self.input[len..];
+    }
+
+    fn skip_whitespace(&mut self) {
+        let len = self.input.len() - self.input.trim_start().len();
+        self.advance(len);
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), FrontMatterError> {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.advance(1);
+            Ok(())
+        } else {
+            error(self.line, format!("expected `{}`", c))
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, FrontMatterError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.parse_object().map(Value::Map),
+            Some('[') => self.parse_array(),
+            Some('"') => self.parse_string().map(Value::String),
+            Some(_) => self.parse_literal(),
+            None => error(self.line, "expected a value"),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Vec<(String, Value)>, FrontMatterError> {
+        self.expect('{')?;
+        let mut entries: Vec<(String, Value)> = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.advance(1);
+            return Ok(entries);
+        }
+
+        loop {
+            self.skip_whitespace();
+            let line = self.line;
+            if self.peek() != Some('"') {
+                return error(line, "expected a string key");
+            }
+            let key = self.parse_string()?;
+            if entries.iter().any(|(k, _)| *k == key) {
+                return error(line, format!("duplicate key `{}`", key));
+            }
+            self.expect(':')?;
+            let value = self.parse_value()?;
+            entries.push((key, value));
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.advance(1),
+                Some('}') => {
+                    self.advance(1);
+                    return Ok(entries);
+                }
+                _ => return error(self.line, "expected `,` or `}` in object"),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Value, FrontMatterError> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.advance(1);
+            return Ok(Value::List(values));
+        }
+
+        loop {
+            values.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.advance(1),
+                Some(']') => {
+                    self.advance(1);
+                    return Ok(Value::List(values));
+                }
+                _ => return error(self.line, "expected `,` or `]` in array"),
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, FrontMatterError> {
+        let hex = self.input.get(..4).unwrap_or("");
+        let code = u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|_| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| FrontMatterError {
+                line: self.line,
+                message: format!("invalid escape `\\u{}`", hex),
+            })?;
+        self.advance(4);
+
+        Ok(code)
+    }
+
+    fn parse_string(&mut self) -> Result<String, FrontMatterError> {
+        self.advance(1);
+        let mut value = String::new();
+        loop {
+            let Some(c) = self.peek() else {
+                return error(self.line, "unterminated string");
+            };
+            self.advance(c.len_utf8());
+            match c {
+                '"' => return Ok(value),
+                '\n' => return error(self.line - 1, "unterminated string"),
+                '\\' => {
+                    let Some(escaped) = self.peek() else {
+                        return error(self.line, "unterminated string");
+                    };
+                    self.advance(escaped.len_utf8());
+                    match escaped {
+                        '"' | '\\' | '/' => value.push(escaped),
+                        'b' => value.push('\u{8}'),
+                        'f' => value.push('\u{c}'),
+                        'n' => value.push('\n'),
+                        'r' => value.push('\r'),
+                        't' => value.push('\t'),
+                        'u' => {
+                            let mut code = self.parse_hex4()?;
+                            // サロゲートペア
+                            if (0xd800..0xdc00).contains(&code) && self.input.starts_with("\\u") {
+                                self.advance(2);
+                                let low = self.parse_hex4()?;
+                                code = 0x10000
+                                    + ((code - 0xd800) << 10)
+                                    + (low.wrapping_sub(0xdc00) & 0x3ff);
+                            }
+                            value.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        c => return error(self.line, format!("invalid escape `\\{}`", c)),
+                    }
+                }
+                c => value.push(c),
+            }
+        }
+    }
+
+    fn parse_literal(&mut self) -> Result<Value, FrontMatterError> {
+        let len = self
+            .input
+            .find(|c: char| c.is_whitespace() || matches!(c, ',' | ']' | '}'))
+            .unwrap_or(self.input.len());
+        let token = &self.input[..len];
+
+        let value = match token {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            "null" => Value::Null,
+            _ if token.contains(['.', 'e', 'E']) => match token.parse::<f64>() {
+                Ok(f)
+                    if f.is_finite()
+                        && token.starts_with(|c: char| c == '-' || c.is_ascii_digit()) =>
+                {
+                    Value::Float(f)
+                }
+                _ => return error(self.line, format!("invalid value `{}`", token)),
+            },
+            _ => match token.parse::<i64>() {
+                Ok(n) if !token.starts_with('+') => Value::Integer(n),
+                _ => return error(self.line, format!("invalid value `{}`", token)),
+            },
+        };
+        self.advance(len);
+
+        Ok(value)
+    }
+}
+
+// 先頭のJSONオブジェクトを読み、残りの入力を返す
+pub fn parse_json(
+    source: &str,
+    first_line: usize,
+) -> Result<(FrontMatter, &str), FrontMatterError> {
+    let mut parser = JsonParser {
+        input: source,
+        line: first_line,
+    };
+    let entries = parser.parse_object()?;
+
+    Ok((FrontMatter::new(entries), parser.input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json() {
+        let source = concat!(
+            "{\n",
+            "  \"title\": \"Hello \\\"JSON\\\" \\ud83d\\ude00\",\n",
+            "  \"draft\": false,\n",
+            "  \"weight\": -3,\n",
+            "  \"ratio\": 1.5e2,\n",
+            "  \"tags\": [\"a\", null],\n",
+            "  \"params\": {}\n",
+            "}\n",
+            "# Body\n",
+        );
+        let (front_matter, rest) = parse_json(source, 1).unwrap();
+
+        assert_eq!(rest, "\n# Body\n");
+        assert_eq!(
+            front_matter,
+            FrontMatter::new(vec![
+                (
+                    "title".to_string(),
+                    Value::String("Hello \"JSON\" 😀".to_string())
+                ),
+                ("draft".to_string(), Value::Bool(false)),
+                ("weight".to_string(), Value::Integer(-3)),
+                ("ratio".to_string(), Value::Float(150.0)),
+                (
+                    "tags".to_string(),
+                    Value::List(vec![Value::String("a".to_string()), Value::Null])
+                ),
+                ("params".to_string(), Value::Map(Vec::new())),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_json_errors() {
+        let line_of = |source: &str| parse_json(source, 1).unwrap_err().line;
+
+        assert_eq!(line_of("{\n  \"a\": 1\n  \"b\": 2\n}"), 3);
+        assert_eq!(line_of("{\n  \"a\": 1,\n  \"a\": 2\n}"), 3);
+        assert_eq!(line_of("{\n  \"a\": tru\n}"), 2);
+        assert_eq!(line_of("{\n  \"a\": \"open\n}"), 2);
+        assert_eq!(line_of("{\n  \"a\": [1,\n"), 3);
+    }
+}
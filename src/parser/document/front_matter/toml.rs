@@ -0,0 +1,582 @@
+use super::{FrontMatterError, Value, error, parse_date};
+
+// `[table]` `[[array]]` のヘッダーで区切られたTOML
+struct TomlParser<'a> {
+    input: &'a str,
+    // ファイル全体での行番号
+    line: usize,
+}
+
+fn is_bare_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+// `a.b` のキーで表をたどる。配列の表は最後の要素をたどる
+fn table_mut<'t>(
+    mut table: &'t mut Vec<(String, Value)>,
+    path: &[String],
+    line: usize,
+) -> Result<&'t mut Vec<(String, Value)>, FrontMatterError> {
+    for key in path {
+        let i = match table.iter().position(|(k, _)| k == key) {
+            Some(i) => i,
+            None => {
+                table.push((key.clone(), Value::Map(Vec::new())));
+                table.len() - 1
+            }
+        };
+        table = match &mut table[i].1 {
+            Value::Map(entries) => entries,
+            Value::List(values) => match values.last_mut() {
+                Some(Value::Map(entries)) => entries,
+                _ => return error(line, format!("key `{}` is not a table", key)),
+            },
+            _ => return error(line, format!("key `{}` is not a table", key)),
+        };
+    }
+
+    Ok(table)
+}
+
+fn insert(
+    table: &mut Vec<(String, Value)>,
+    keys: &[String],
+    value: Value,
+    line: usize,
+) -> Result<(), FrontMatterError> {
+    let (last, path) = keys.split_last().expect("keys are never empty");
+    let table = table_mut(table, path, line)?;
+    if table.iter().any(|(k, _)| k == last) {
+        return error(line, format!("duplicate key `{}`", keys.join(".")));
+    }
+    table.push((last.clone(), value));
+
+    Ok(())
+}
+
+// `1_000` `0xff` `0o17` `0b101` `1e3` `inf`
+fn parse_number(token: &str) -> Option<Value> {
+    let (sign, unsigned) = match token.strip_prefix(['+', '-']) {
+        Some(rest) => (if token.starts_with('-') { -1 } else { 1 }, rest),
+        None => (1, token),
+    };
+    // `_` は数字の間にだけ書ける
+    if unsigned.starts_with('_') || unsigned.ends_with('_') || unsigned.contains("__") {
+        return None;
+    }
+    let digits = unsigned.replace('_', "");
+
+    for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        if let Some(digits) = digits.strip_prefix(prefix) {
+            if sign == -1 || token.starts_with('+') {
+                return None;
+            }
+            return i64::from_str_radix(digits, radix).ok().map(Value::Integer);
+        }
+    }
+    match digits.as_str() {
+        "inf" => return Some(Value::Float(sign as f64 * f64::INFINITY)),
+        "nan" => return Some(Value::Float(f64::NAN)),
+        _ => {}
+    }
+    if !digits.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    // 先頭の0は許されない
+    if digits.len() > 1
+        && digits.starts_with('0')
+        && digits[1..].starts_with(|c: char| c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    if let Ok(n) = digits.parse::<i64>() {
+        return Some(Value::Integer(sign * n));
+    }
+    if digits.ends_with('.') || digits.contains(".e") || digits.contains(".E") {
+        return None;
+    }
+    digits
+        .parse::<f64>()
+        .ok()
+        .map(|f| Value::Float(sign as f64 * f))
+}
+
+impl<'a> TomlParser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input.chars().next()
+    }
+
+    fn advance(&mut self, len: usize) {
+        self.line += self.input[..len].matches('\n').count();
+        self.input = &self.input[len..];
+    }
+
+    fn skip_whitespace(&mut self) {
+        let len = self.input.len() - self.input.trim_start_matches([' ', '\t']).len();
+        self.advance(len);
+    }
+
+    fn skip_comment(&mut self) {
+        if self.input.starts_with('#') {
+            let len = self.input.find('\n').unwrap_or(self.input.len());
+            self.advance(len);
+        }
+    }
+
+    // 空行とコメントを飛ばす。配列の中では改行を挟める
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_whitespace();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n') => self.advance(1),
+                _ => return,
+            }
+        }
+    }
+
+    fn expect(&mut self, s: &str) -> Result<(), FrontMatterError> {
+        if self.input.starts_with(s) {
+            self.advance(s.len());
+            Ok(())
+        } else {
+            error(self.line, format!("expected `{}`", s))
+        }
+    }
+
+    // 値の後ろには、コメントと改行しか書けない
+    fn expect_line_end(&mut self) -> Result<(), FrontMatterError> {
+        self.skip_whitespace();
+        self.skip_comment();
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.advance(1);
+                Ok(())
+            }
+            Some(c) => error(self.line, format!("unexpected `{}` after value", c)),
+        }
+    }
+
+    fn parse_document(&mut self) -> Result<Vec<(String, Value)>, FrontMatterError> {
+        let mut root = Vec::new();
+        let mut current: Vec<String> = Vec::new();
+        let mut defined: Vec<Vec<String>> = Vec::new();
+
+        loop {
+            self.skip_blank();
+            if self.input.is_empty() {
+                return Ok(root);
+            }
+            let line = self.line;
+
+            if self.input.starts_with("[[") {
+                self.advance(2);
+                let path = self.parse_key()?;
+                self.expect("]]")?;
+                let (last, parent) = path.split_last().expect("keys are never empty");
+                let table = table_mut(&mut root, parent, line)?;
+                match table.iter_mut().find(|(k, _)| k == last) {
+                    Some((_, Value::List(values))) => values.push(Value::Map(Vec::new())),
+                    Some(_) => {
+                        return error(
+                            line,
+                            format!("key `{}` is not an array of tables", path.join(".")),
+                        );
+                    }
+                    None => table.push((last.clone(), Value::List(vec![Value::Map(Vec::new())]))),
+                }
+                // 新しい要素の中では同じ表をもう一度定義できる
+                defined.retain(|table| !table.starts_with(&path));
+                current = path;
+            } else if self.input.starts_with('[') {
+                self.advance(1);
+                let path = self.parse_key()?;
+                self.expect("]")?;
+                if defined.contains(&path) {
+                    return error(line, format!("table `{}` is defined twice", path.join(".")));
+                }
+                table_mut(&mut root, &path, line)?;
+                defined.push(path.clone());
+                current = path;
+            } else {
+                let keys = self.parse_key()?;
+                self.expect("=")?;
+                self.skip_whitespace();
+                let value = self.parse_value()?;
+                insert(table_mut(&mut root, &current, line)?, &keys, value, line)?;
+            }
+
+            self.expect_line_end()?;
+        }
+    }
+
+    // `a."b.c".d` のドット区切りのキー
+    fn parse_key(&mut self) -> Result<Vec<String>, FrontMatterError> {
+        let mut keys = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let key = match self.peek() {
+                Some('"') | Some('\'') => match self.parse_value()? {
+                    Value::String(key) => key,
+                    _ => return error(self.line, "invalid key"),
+                },
+                _ => {
+                    let len = self
+                        .input
+                        .find(|c: char| !is_bare_key_char(c))
+                        .unwrap_or(self.input.len());
+                    if len == 0 {
+                        return error(self.line, "expected a key");
+                    }
+                    let key = self.input[..len].to_string();
+                    self.advance(len);
+                    key
+                }
+            };
+            keys.push(key);
+
+            self.skip_whitespace();
+            if self.peek() == Some('.') {
+                self.advance(1);
+            } else {
+                return Ok(keys);
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, FrontMatterError> {
+        if self.input.starts_with("\"\"\"") {
+            return self.parse_multiline_string("\"\"\"", true);
+        }
+        if self.input.starts_with("'''") {
+            return self.parse_multiline_string("'''", false);
+        }
+
+        match self.peek() {
+            Some('"') => self.parse_basic_string(),
+            Some('\'') => {
+                self.advance(1);
+                let len = self
+                    .input
+                    .find(['\'', '\n'])
+                    .filter(|&i| self.input[i..].starts_with('\''))
+                    .ok_or_else(|| FrontMatterError {
+                        line: self.line,
+                        message: "unterminated literal string".to_string(),
+                    })?;
+                let value = self.input[..len].to_string();
+                self.advance(len + 1);
+                Ok(Value::String(value))
+            }
+            Some('[') => self.parse_array(),
+            Some('{') => self.parse_inline_table(),
+            Some(_) => self.parse_scalar(),
+            None => error(self.line, "expected a value"),
+        }
+    }
+
+    // 真偽値、数値、日付
+    fn parse_scalar(&mut self) -> Result<Value, FrontMatterError> {
+        let mut len = self
+            .input
+            .find(|c: char| c.is_whitespace() || matches!(c, ',' | ']' | '}' | '#'))
+            .unwrap_or(self.input.len());
+        // `1979-05-27 07:32:00` の日付と時刻の間の空白
+        if len == 10
+            && self.input[len..].starts_with(' ')
+            && self.input[len + 1..].starts_with(|c: char| c.is_ascii_digit())
+            && parse_date(&self.input[..len]).is_some()
+        {
+            len = self.input[len + 1..]
+                .find(|c: char| c.is_whitespace() || matches!(c, ',' | ']' | '}' | '#'))
+                .map_or(self.input.len(), |i| len + 1 + i);
+        }
+        let token = &self.input[..len];
+
+        let value = match token {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => match parse_date(token)
+                .map(Value::Date)
+                .or_else(|| parse_number(token))
+            {
+                Some(value) => value,
+                // 日付のない時刻 `07:32:00` はそのまま文字列にする
+                None if token.len() >= 5
+                    && parse_date(&format!("2000-01-01T{}", token)).is_some() =>
+                {
+                    Value::String(token.to_string())
+                }
+                None => return error(self.line, format!("invalid value `{}`", token)),
+            },
+        };
+        self.advance(len);
+
+        Ok(value)
+    }
+
+    fn parse_escape(&mut self) -> Result<char, FrontMatterError> {
+        let c = self.peek().ok_or_else(|| FrontMatterError {
+            line: self.line,
+            message: "unterminated string".to_string(),
+        })?;
+        self.advance(c.len_utf8());
+        let hex_len = match c {
+            'b' => return Ok('\u{8}'),
+            't' => return Ok('\t'),
+            'n' => return Ok('\n'),
+            'f' => return Ok('\u{c}'),
+            'r' => return Ok('\r'),
+            'e' => return Ok('\u{1b}'),
+            '"' => return Ok('"'),
+            '\\' => return Ok('\\'),
+            'u' => 4,
+            'U' => 8,
+            c => return error(self.line, format!("invalid escape `\\{}`", c)),
+        };
+        let hex = self.input.get(..hex_len).unwrap_or("");
+        let c = u32::from_str_radix(hex, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| FrontMatterError {
+                line: self.line,
+                message: format!("invalid escape `\\{}{}`", c, hex),
+            })?;
+        self.advance(hex_len);
+
+        Ok(c)
+    }
+
+    fn parse_basic_string(&mut self) -> Result<Value, FrontMatterError> {
+        self.advance(1);
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.advance(1);
+                    return Ok(Value::String(value));
+                }
+                Some('\\') => {
+                    self.advance(1);
+                    value.push(self.parse_escape()?);
+                }
+                Some('\n') | None => return error(self.line, "unterminated string"),
+                Some(c) => {
+                    self.advance(c.len_utf8());
+                    value.push(c);
+                }
+            }
+        }
+    }
+
+    // `"""` と `'''`。開きの直後の改行は含めない
+    fn parse_multiline_string(
+        &mut self,
+        quote: &str,
+        escape: bool,
+    ) -> Result<Value, FrontMatterError> {
+        let start = self.line;
+        self.advance(3);
+        if self.input.starts_with('\n') {
+            self.advance(1);
+        }
+
+        let mut value = String::new();
+        loop {
+            if self.input.starts_with(quote) {
+                // 閉じの直前の引用符は2つまで値に含める `""""`
+                let extra = self.input[3..]
+                    .chars()
+                    .take_while(|c| quote.starts_with(*c))
+                    .count()
+                    .min(2);
+                value.push_str(&self.input[3..3 + extra]);
+                self.advance(3 + extra);
+                return Ok(Value::String(value));
+            }
+            match self.peek() {
+                // 行末の `\` は次の空白でない文字までを取り除く
+                Some('\\') if escape => {
+                    let rest = self.input[1..].trim_start_matches([' ', '\t']);
+                    if rest.starts_with('\n') {
+                        let len = self.input.len() - rest.trim_start().len();
+                        self.advance(len);
+                    } else {
+                        self.advance(1);
+                        value.push(self.parse_escape()?);
+                    }
+                }
+                Some(c) => {
+                    self.advance(c.len_utf8());
+                    value.push(c);
+                }
+                None => return error(start, "unterminated multi-line string"),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Value, FrontMatterError> {
+        let start = self.line;
+        self.advance(1);
+        let mut values = Vec::new();
+        loop {
+            self.skip_blank();
+            match self.peek() {
+                Some(']') => {
+                    self.advance(1);
+                    return Ok(Value::List(values));
+                }
+                None => return error(start, "unterminated array"),
+                _ => {}
+            }
+            values.push(self.parse_value()?);
+            self.skip_blank();
+            match self.peek() {
+                Some(',') => self.advance(1),
+                Some(']') => {}
+                None => return error(start, "unterminated array"),
+                _ => return error(self.line, "expected `,` or `]` in array"),
+            }
+        }
+    }
+
+    fn parse_inline_table(&mut self) -> Result<Value, FrontMatterError> {
+        self.advance(1);
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.advance(1);
+            return Ok(Value::Map(entries));
+        }
+        loop {
+            let line = self.line;
+            let keys = self.parse_key()?;
+            self.expect("=")?;
+            self.skip_whitespace();
+            let value = self.parse_value()?;
+            insert(&mut entries, &keys, value, line)?;
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.advance(1),
+                Some('}') => {
+                    self.advance(1);
+                    return Ok(Value::Map(entries));
+                }
+                _ => return error(self.line, "expected `,` or `}` in inline table"),
+            }
+        }
+    }
+}
+
+// first_lineはsourceの1行目のファイルでの行番号
+pub fn parse_toml(
+    source: &str,
+    first_line: usize,
+) -> Result<Vec<(String, Value)>, FrontMatterError> {
+    TomlParser {
+        input: source,
+        line: first_line,
+    }
+    .parse_document()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::document::front_matter::Date;
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn test_parse_toml() {
+        let source = concat!(
+            "# comment\n",
+            "title = \"Hello \\\"TOML\\\"\" # trailing\n",
+            "path = 'C:\\dir'\n",
+            "draft = false\n",
+            "weight = 1_000\n",
+            "mask = 0xff\n",
+            "ratio = 6.5e-1\n",
+            "date = 1979-05-27 07:32:00Z\n",
+            "tags = [\n",
+            "  \"rust\", # first\n",
+            "  \"web\",\n",
+            "]\n",
+            "author = { name = \"Alice\", site.url = \"https://example.com\" }\n",
+            "body = \"\"\"\n",
+            "one \\\n",
+            "   two\"\"\"\n",
+            "\n",
+            "[extra.\"a.b\"]\n",
+            "x = 1\n",
+            "\n",
+            "[[links]]\n",
+            "name = \"first\"\n",
+            "[[links]]\n",
+            "name = \"second\"\n",
+        );
+        let values = parse_toml(source, 2).unwrap();
+        let get = |key: &str| values.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+
+        assert_eq!(get("title"), Some(&string("Hello \"TOML\"")));
+        assert_eq!(get("path"), Some(&string("C:\\dir")));
+        assert_eq!(get("draft"), Some(&Value::Bool(false)));
+        assert_eq!(get("weight"), Some(&Value::Integer(1000)));
+        assert_eq!(get("mask"), Some(&Value::Integer(255)));
+        assert_eq!(get("ratio"), Some(&Value::Float(0.65)));
+        assert_eq!(
+            get("date"),
+            Some(&Value::Date(Date {
+                year: 1979,
+                month: 5,
+                day: 27,
+                time: Some((7, 32, 0)),
+                offset: Some(0),
+            }))
+        );
+        assert_eq!(
+            get("tags"),
+            Some(&Value::List(vec![string("rust"), string("web")]))
+        );
+        assert_eq!(
+            get("author"),
+            Some(&Value::Map(vec![
+                ("name".to_string(), string("Alice")),
+                (
+                    "site".to_string(),
+                    Value::Map(vec![("url".to_string(), string("https://example.com"))])
+                ),
+            ]))
+        );
+        assert_eq!(get("body"), Some(&string("one two")));
+        assert_eq!(
+            get("extra").and_then(|extra| extra.get("a.b")),
+            Some(&Value::Map(vec![("x".to_string(), Value::Integer(1))]))
+        );
+        assert_eq!(
+            get("links"),
+            Some(&Value::List(vec![
+                Value::Map(vec![("name".to_string(), string("first"))]),
+                Value::Map(vec![("name".to_string(), string("second"))]),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_parse_toml_errors() {
+        let line_of = |source: &str| parse_toml(source, 2).unwrap_err().line;
+
+        assert_eq!(line_of("a = 1\nb\n"), 3);
+        assert_eq!(line_of("a = 1\na = 2\n"), 3);
+        assert_eq!(line_of("[t]\n[t]\n"), 3);
+        assert_eq!(line_of("a = \"open\n"), 2);
+        assert_eq!(line_of("a = [1,\n2\n"), 2);
+        assert_eq!(line_of("a = 1 b\n"), 2);
+        assert_eq!(line_of("a = 01\n"), 2);
+        assert_eq!(line_of("a = 1\n[a]\n"), 3);
+    }
+}
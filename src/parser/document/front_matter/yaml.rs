@@ -0,0 +1,551 @@
+use super::{FrontMatterError, Value, error, parse_date};
+
+struct Line<'a> {
+    // ファイル全体での行番号
+    number: usize,
+    indent: usize,
+    text: &'a str,
+}
+
+// 引用符の外にある ` #` 以降を取り除く
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    let mut prev = ' ';
+    for (i, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' && prev.is_whitespace() => return text[..i].trim_end(),
+            None => {}
+        }
+        prev = c;
+    }
+
+    text.trim_end()
+}
+
+// `key: value` の `:` の位置。引用符で囲まれたキーの中の `:` は無視する
+fn find_key_separator(text: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if (c == '"' || c == '\'') && i == 0 => quote = Some(c),
+            None if c == ':' && text[i + 1..].chars().next().is_none_or(char::is_whitespace) => {
+                return Some(i);
+            }
+            None => {}
+        }
+    }
+
+    None
+}
+
+// 引用符のない値の型を決める
+fn plain_scalar(s: &str) -> Value {
+    match s {
+        "" | "~" | "null" | "Null" | "NULL" => return Value::Null,
+        "true" | "True" | "TRUE" => return Value::Bool(true),
+        "false" | "False" | "FALSE" => return Value::Bool(false),
+        _ => {}
+    }
+
+    if let Ok(n) = s.parse::<i64>() {
+        return Value::Integer(n);
+    }
+    if s.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+' || c == '.')
+        && !s.ends_with('.')
+        && let Ok(f) = s.parse::<f64>()
+        && f.is_finite()
+    {
+        return Value::Float(f);
+    }
+    if let Some(date) = parse_date(s) {
+        return Value::Date(date);
+    }
+
+    Value::String(s.to_string())
+}
+
+// 引用符で囲まれた文字列。閉じの引用符の後ろを返す
+fn quoted_scalar(s: &str, line: usize) -> Result<(String, &str), FrontMatterError> {
+    let mut chars = s.char_indices();
+    let quote = chars.next().map(|(_, c)| c);
+    let mut value = String::new();
+
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') => {
+                // `''` は `'` 1文字
+                if s[i + 1..].starts_with('\'') {
+                    chars.next();
+                    value.push('\'');
+                } else {
+                    return Ok((value, &s[i + 1..]));
+                }
+            }
+            (Some('"'), '"') => return Ok((value, &s[i + 1..])),
+            (Some('"'), '\\') => match chars.next().map(|(_, c)| c) {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some('r') => value.push('\r'),
+                Some('0') => value.push('\0'),
+                Some('/') => value.push('/'),
+                Some('"') => value.push('"'),
+                Some('\\') => value.push('\\'),
+                Some('u') => {
+                    let hex = s.get(i + 2..i + 6).unwrap_or("");
+                    let c = u32::from_str_radix(hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| FrontMatterError {
+                            line,
+                            message: format!("invalid escape `\\u{}`", hex),
+                        })?;
+                    value.push(c);
+                    (0..4).for_each(|_| {
+                        chars.next();
+                    });
+                }
+                Some(c) => return error(line, format!("invalid escape `\\{}`", c)),
+                None => break,
+            },
+            _ => value.push(c),
+        }
+    }
+
+    error(line, "unterminated quoted string")
+}
+
+// `[a, b]` `{a: 1}` の中身
+struct FlowParser<'a> {
+    input: &'a str,
+    line: usize,
+}
+
+impl<'a> FlowParser<'a> {
+    fn skip_whitespace(&mut self) {
+        self.input = self.input.trim_start();
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), FrontMatterError> {
+        self.skip_whitespace();
+        match self.input.strip_prefix(c) {
+            Some(rest) => {
+                self.input = rest;
+                Ok(())
+            }
+            None => error(self.line, format!("expected `{}` in flow collection", c)),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, FrontMatterError> {
+        self.skip_whitespace();
+        match self.input.chars().next() {
+            Some('[') => self.parse_list(),
+            Some('{') => self.parse_map(),
+            Some('"') | Some('\'') => {
+                let (value, rest) = quoted_scalar(self.input, self.line)?;
+                self.input = rest;
+                Ok(Value::String(value))
+            }
+            _ => {
+                let end = self.input.find([',', ']', '}']).unwrap_or(self.input.len());
+                let value = plain_scalar(self.input[..end].trim());
+                self.input = &self.input[end..];
+                Ok(value)
+            }
+        }
+    }
+
+    fn parse_list(&mut self) -> Result<Value, FrontMatterError> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if let Some(rest) = self.input.strip_prefix(']') {
+                self.input = rest;
+                return Ok(Value::List(values));
+            }
+            values.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.input.chars().next() {
+                Some(',') => self.input = &self.input[1..],
+                Some(']') => {}
+                _ => return error(self.line, "expected `,` or `]` in flow sequence"),
+            }
+        }
+    }
+
+    // フロー形式のマッピングのキー。引用符のないキーは `: ` か `,` `}` の手前まで
+    fn parse_key(&mut self) -> Result<String, FrontMatterError> {
+        self.skip_whitespace();
+        if self.input.starts_with(['"', '\'']) {
+            let (key, rest) = quoted_scalar(self.input, self.line)?;
+            self.input = rest;
+            return Ok(key);
+        }
+
+        let end = self
+            .input
+            .char_indices()
+            .find(|&(i, c)| {
+                c == ',' || c == '}' || {
+                    c == ':'
+                        && self.input[i + 1..]
+                            .chars()
+                            .next()
+                            .is_none_or(|next| next.is_whitespace() || ",[]{}".contains(next))
+                }
+            })
+            .map_or(self.input.len(), |(i, _)| i);
+        let key = self.input[..end].trim();
+        if key.is_empty() {
+            return error(self.line, "empty key in flow mapping");
+        }
+        self.input = &self.input[end..];
+
+        Ok(key.to_string())
+    }
+
+    fn parse_map(&mut self) -> Result<Value, FrontMatterError> {
+        self.expect('{')?;
+        let mut entries: Vec<(String, Value)> = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if let Some(rest) = self.input.strip_prefix('}') {
+                self.input = rest;
+                return Ok(Value::Map(entries));
+            }
+
+            let key = self.parse_key()?;
+            // 値を省略したキー `{a, b: 1}` はnull
+            self.skip_whitespace();
+            let value = match self.input.strip_prefix(':') {
+                Some(rest) => {
+                    self.input = rest;
+                    self.parse_value()?
+                }
+                None => Value::Null,
+            };
+            entries.push((key, value));
+
+            self.skip_whitespace();
+            match self.input.chars().next() {
+                Some(',') => self.input = &self.input[1..],
+                Some('}') => {}
+                _ => return error(self.line, "expected `,` or `}` in flow mapping"),
+            }
+        }
+    }
+}
+
+struct YamlParser<'a> {
+    lines: Vec<Line<'a>>,
+    pos: usize,
+}
+
+impl<'a> YamlParser<'a> {
+    fn new(source: &'a str, first_line: usize) -> Result<Self, FrontMatterError> {
+        let mut lines = Vec::new();
+        for (i, raw) in source.lines().enumerate() {
+            let number = first_line + i;
+            let indent = raw.len() - raw.trim_start_matches(' ').len();
+            if raw[indent..].starts_with('\t') {
+                return error(number, "tabs are not allowed for indentation");
+            }
+            lines.push(Line {
+                number,
+                indent,
+                text: raw[indent..].trim_end(),
+            });
+        }
+
+        Ok(YamlParser { lines, pos: 0 })
+    }
+
+    fn is_blank(line: &Line) -> bool {
+        line.text.is_empty() || line.text.starts_with('#')
+    }
+
+    // 空行とコメント行を飛ばした次の行
+    fn peek(&mut self) -> Option<&Line<'a>> {
+        while self.pos < self.lines.len() && Self::is_blank(&self.lines[self.pos]) {
+            self.pos += 1;
+        }
+        self.lines.get(self.pos)
+    }
+
+    fn is_list_item(text: &str) -> bool {
+        text == "-" || text.starts_with("- ")
+    }
+
+    fn parse_document(&mut self) -> Result<Vec<(String, Value)>, FrontMatterError> {
+        let Some(line) = self.peek() else {
+            return Ok(Vec::new());
+        };
+        let (number, indent) = (line.number, line.indent);
+        if Self::is_list_item(line.text) {
+            return error(number, "front matter must be a mapping of keys to values");
+        }
+
+        let entries = self.parse_map(indent)?;
+        if let Some(line) = self.peek() {
+            return error(line.number, "unexpected indentation");
+        }
+
+        Ok(entries)
+    }
+
+    // indentより深くインデントされたブロック
+    fn parse_nested(&mut self, parent_indent: usize) -> Result<Value, FrontMatterError> {
+        match self.peek() {
+            Some(line) if line.indent > parent_indent => {
+                let indent = line.indent;
+                if Self::is_list_item(line.text) {
+                    self.parse_list(indent)
+                } else {
+                    self.parse_map(indent).map(Value::Map)
+                }
+            }
+            // YAMLではマッピングの値のリストはキーと同じインデントでもよい
+            Some(line) if line.indent == parent_indent && Self::is_list_item(line.text) => {
+                self.parse_list(parent_indent)
+            }
+            _ => Ok(Value::Null),
+        }
+    }
+
+    fn parse_map(&mut self, indent: usize) -> Result<Vec<(String, Value)>, FrontMatterError> {
+        let mut entries: Vec<(String, Value)> = Vec::new();
+
+        while let Some(line) = self.peek() {
+            if line.indent < indent {
+                break;
+            }
+            let (number, text) = (line.number, line.text);
+            if line.indent > indent {
+                return error(number, "unexpected indentation");
+            }
+            if Self::is_list_item(text) {
+                return error(number, "unexpected list item in a mapping");
+            }
+
+            let Some(separator) = find_key_separator(text) else {
+                return error(number, "expected `key: value`");
+            };
+            let key = text[..separator].trim();
+            let key = match key.chars().next() {
+                Some('"') | Some('\'') => quoted_scalar(key, number)?.0,
+                Some(_) => key.to_string(),
+                None => return error(number, "empty key"),
+            };
+            if entries.iter().any(|(k, _)| *k == key) {
+                return error(number, format!("duplicate key `{}`", key));
+            }
+
+            self.pos += 1;
+            let value = self.parse_value(text[separator + 1..].trim(), indent, number)?;
+            entries.push((key, value));
+        }
+
+        Ok(entries)
+    }
+
+    fn parse_list(&mut self, indent: usize) -> Result<Value, FrontMatterError> {
+        let mut values = Vec::new();
+
+        while let Some(line) = self.peek() {
+            if line.indent != indent || !Self::is_list_item(line.text) {
+                if line.indent > indent {
+                    return error(line.number, "unexpected indentation");
+                }
+                break;
+            }
+
+            let item = line.text[1..].trim_start();
+            let item_indent = indent + (line.text.len() - item.len());
+            let number = line.number;
+
+            // `- key: value` は項目の中身の位置から始まるマッピング
+            if !item.starts_with(['"', '\'', '[', '{'])
+                && find_key_separator(strip_comment(item)).is_some()
+            {
+                self.lines[self.pos] = Line {
+                    number,
+                    indent: item_indent,
+                    text: item,
+                };
+                values.push(Value::Map(self.parse_map(item_indent)?));
+                continue;
+            }
+
+            self.pos += 1;
+            values.push(self.parse_value(item, indent, number)?);
+        }
+
+        Ok(Value::List(values))
+    }
+
+    // `key:` や `-` の後ろに書かれた値
+    fn parse_value(
+        &mut self,
+        text: &str,
+        indent: usize,
+        number: usize,
+    ) -> Result<Value, FrontMatterError> {
+        let text = strip_comment(text);
+        match text.chars().next() {
+            None => self.parse_nested(indent),
+            Some('|') | Some('>') => self.parse_block_scalar(text, indent, number),
+            Some('[') | Some('{') => {
+                // 閉じ括弧まで次の行に続けて書ける
+                let mut source = text.to_string();
+                while !Self::is_balanced(&source) && self.pos < self.lines.len() {
+                    source.push(' ');
+                    source.push_str(strip_comment(self.lines[self.pos].text));
+                    self.pos += 1;
+                }
+                let mut parser = FlowParser {
+                    input: &source,
+                    line: number,
+                };
+                let value = parser.parse_value()?;
+                if !parser.input.trim().is_empty() {
+                    return error(number, "unexpected characters after flow collection");
+                }
+                Ok(value)
+            }
+            Some('"') | Some('\'') => {
+                let (value, rest) = quoted_scalar(text, number)?;
+                if !rest.trim().is_empty() {
+                    return error(number, "unexpected characters after quoted string");
+                }
+                Ok(Value::String(value))
+            }
+            Some(_) => {
+                // より深くインデントされた行は値の続き
+                let mut value = text.to_string();
+                while let Some(line) = self.peek()
+                    && line.indent > indent
+                    && !Self::is_list_item(line.text)
+                    && find_key_separator(line.text).is_none()
+                {
+                    value.push(' ');
+                    value.push_str(strip_comment(line.text));
+                    self.pos += 1;
+                }
+                Ok(plain_scalar(&value))
+            }
+        }
+    }
+
+    fn is_balanced(source: &str) -> bool {
+        let mut depth = 0i32;
+        let mut quote = None;
+        for c in source.chars() {
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None => match c {
+                    '"' | '\'' => quote = Some(c),
+                    '[' | '{' => depth += 1,
+                    ']' | '}' => depth -= 1,
+                    _ => {}
+                },
+            }
+        }
+        depth <= 0
+    }
+
+    // `|` はそのまま、`>` は行を空白でつなぐ。`-` で末尾の改行を取り除く
+    fn parse_block_scalar(
+        &mut self,
+        header: &str,
+        indent: usize,
+        number: usize,
+    ) -> Result<Value, FrontMatterError> {
+        let folded = header.starts_with('>');
+        let chomp = &header[1..];
+        if !matches!(chomp, "" | "-" | "+") {
+            return error(
+                number,
+                format!("unsupported block scalar header `{}`", header),
+            );
+        }
+
+        let mut lines: Vec<&str> = Vec::new();
+        let mut block_indent = None;
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.text.is_empty() {
+                lines.push("");
+                self.pos += 1;
+                continue;
+            }
+            if line.indent <= indent {
+                break;
+            }
+            let block_indent = *block_indent.get_or_insert(line.indent);
+            if line.indent < block_indent {
+                return error(
+                    line.number,
+                    "block scalar lines are less indented than the first",
+                );
+            }
+            lines.push(self.lines[self.pos].text);
+            self.pos += 1;
+        }
+        // 空行のインデントは分からないので、深い行の先頭の空白は元の行から戻す
+        let block_indent = block_indent.unwrap_or(0);
+        let first = self.pos - lines.len();
+        let lines: Vec<String> = lines
+            .iter()
+            .enumerate()
+            .map(|(i, text)| {
+                let line = &self.lines[first + i];
+                if text.is_empty() {
+                    String::new()
+                } else {
+                    format!("{}{}", " ".repeat(line.indent - block_indent), text)
+                }
+            })
+            .collect();
+
+        let trailing_blank = lines.iter().rev().take_while(|l| l.is_empty()).count();
+        let body = &lines[..lines.len() - trailing_blank];
+        let mut value = if folded {
+            // 空行は改行になり、続く行どうしは空白でつながる
+            let mut value = String::new();
+            for (i, line) in body.iter().enumerate() {
+                if line.is_empty() {
+                    value.push('\n');
+                } else if i > 0 && !body[i - 1].is_empty() {
+                    value.push(' ');
+                }
+                value.push_str(line);
+            }
+            value
+        } else {
+            body.join("\n")
+        };
+
+        match chomp {
+            "-" => {}
+            "+" => value.push_str(&"\n".repeat(trailing_blank + 1)),
+            _ if !body.is_empty() => value.push('\n'),
+            _ => {}
+        }
+
+        Ok(Value::String(value))
+    }
+}
+
+// first_lineはsourceの1行目のファイルでの行番号
+pub fn parse_yaml(
+    source: &str,
+    first_line: usize,
+) -> Result<Vec<(String, Value)>, FrontMatterError> {
+    YamlParser::new(source, first_line)?.parse_document()
+}
@@ -0,0 +1,181 @@
+use super::{Parser, many, some};
+use crate::choice;
+use crate::parser::lex::{character, string};
+use crate::parser::take_until;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlTag<'a> {
+    pub name: &'a str,
+    // 値は引用符を外しただけで、文字参照は元のまま
+    pub attributes: Vec<(&'a str, Option<&'a str>)>,
+    pub closing: bool,
+    pub self_closing: bool,
+}
+
+fn whitespace<'a>() -> impl Parser<'a, Vec<char>> {
+    some(character(|c| c == ' ' || c == '\t' || c == '\n'))
+}
+
+// 消費した部分の文字列を返すようにする
+fn recognize<'a, T>(parser: impl Parser<'a, T>) -> impl Parser<'a, &'a str> {
+    move |input: &'a str| {
+        let (_, rest) = parser.parse(input)?;
+        Some((&input[..input.len() - rest.len()], rest))
+    }
+}
+
+fn tag_name<'a>() -> impl Parser<'a, &'a str> {
+    recognize(
+        character(|c| c.is_ascii_alphabetic())
+            .and(many(character(|c| c.is_ascii_alphanumeric() || c == '-'))),
+    )
+}
+
+fn attribute<'a>() -> impl Parser<'a, (&'a str, Option<&'a str>)> {
+    let name = recognize(
+        character(|c| c.is_ascii_alphabetic() || c == '_' || c == ':').and(many(character(|c| {
+            c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == ':' || c == '-'
+        }))),
+    );
+
+    let unquoted = recognize(some(character(|c| {
+        !c.is_whitespace() && !matches!(c, '"' | '\'' | '=' | '<' | '>' | '`')
+    })));
+    let single_quoted = string("'")
+        .and(take_until("'"))
+        .and(string("'"))
+        .map(|((_, value), _)| value);
+    let double_quoted = string("\"")
+        .and(take_until("\""))
+        .and(string("\""))
+        .map(|((_, value), _)| value);
+    let value = many(whitespace())
+        .and(string("="))
+        .and(many(whitespace()))
+        .and(choice![single_quoted, double_quoted, unquoted])
+        .map(|(_, value)| Some(value));
+
+    whitespace()
+        .and(name)
+        .and(value.or(move |input| Some((None, input))))
+        .map(|((_, name), value)| (name, value))
+}
+
+// `<tag attr="value">` か `</tag>`
+pub fn parse_html_tag<'a>() -> impl Parser<'a, HtmlTag<'a>> {
+    let open = string("<")
+        .and(tag_name())
+        .and(many(attribute()))
+        .and(many(whitespace()))
+        .and(string("/>").or(string(">")))
+        .map(|((((_, name), attributes), _), end)| HtmlTag {
+            name,
+            attributes,
+            closing: false,
+            self_closing: end == "/>",
+        });
+    let close = string("</")
+        .and(tag_name())
+        .and(many(whitespace()))
+        .and(string(">"))
+        .map(|(((_, name), _), _)| HtmlTag {
+            name,
+            attributes: Vec::new(),
+            closing: true,
+            self_closing: false,
+        });
+
+    open.or(close)
+}
+
+// コメント、処理命令、宣言、CDATA
+pub fn parse_html_special<'a>() -> impl Parser<'a, &'a str> {
+    let delimited = |open: &'a str, close: &'a str| {
+        recognize(string(open).and(take_until(close)).and(string(close)))
+    };
+    let comment =
+        recognize(string("<!--").and(string(">").or(string("->")))).or(delimited("<!--", "-->"));
+    let declaration = recognize(
+        string("<!")
+            .and(character(|c| c.is_ascii_alphabetic()))
+            .and(take_until(">"))
+            .and(string(">")),
+    );
+
+    choice![
+        comment,
+        delimited("<?", "?>"),
+        delimited("<![CDATA[", "]]>"),
+        declaration
+    ]
+}
+
+// インラインに書かれた生のHTML
+pub fn parse_raw_html<'a>() -> impl Parser<'a, &'a str> {
+    recognize(parse_html_tag()).or(parse_html_special())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_html_tag() {
+        let parser = parse_html_tag();
+
+        assert_eq!(
+            parser("<a href=\"x.html\" target=_blank\n  download>text"),
+            Some((
+                HtmlTag {
+                    name: "a",
+                    attributes: vec![
+                        ("href", Some("x.html")),
+                        ("target", Some("_blank")),
+                        ("download", None)
+                    ],
+                    closing: false,
+                    self_closing: false,
+                },
+                "text"
+            ))
+        );
+        assert_eq!(
+            parser("<br/>"),
+            Some((
+                HtmlTag {
+                    name: "br",
+                    attributes: vec![],
+                    closing: false,
+                    self_closing: true,
+                },
+                ""
+            ))
+        );
+        assert_eq!(
+            parser("</kbd >"),
+            Some((
+                HtmlTag {
+                    name: "kbd",
+                    attributes: vec![],
+                    closing: true,
+                    self_closing: false,
+                },
+                ""
+            ))
+        );
+        assert_eq!(parser("< a>"), None);
+        assert_eq!(parser("<a href=>"), None);
+    }
+
+    #[test]
+    fn test_parse_raw_html() {
+        let parser = parse_raw_html();
+
+        assert_eq!(parser("<!-- note -->x"), Some(("<!-- note -->", "x")));
+        assert_eq!(parser("<?php echo 1; ?>"), Some(("<?php echo 1; ?>", "")));
+        assert_eq!(parser("<!DOCTYPE html>"), Some(("<!DOCTYPE html>", "")));
+        assert_eq!(parser("<![CDATA[a<b]]>"), Some(("<![CDATA[a<b]]>", "")));
+        assert_eq!(parser("<kbd>Ctrl</kbd>"), Some(("<kbd>", "Ctrl</kbd>")));
+        assert_eq!(parser("a < b"), None);
+    }
+}
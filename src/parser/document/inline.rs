@@ -1,11 +1,32 @@
-use super::ast::Inline;
+use super::ast::{Embed, Inline};
+use super::entity::{decode_entities, parse_entity};
+use super::html::parse_raw_html;
 use super::{Parser, some};
 use crate::choice;
+use crate::parser::id;
 use crate::parser::lex::{character, string};
-use crate::parser::{id, take_until};
+
+// `\` でエスケープされた文字を飛ばしながらtargetの手前までを取る
+fn take_until_unescaped<'a>(target: &'a str) -> impl Parser<'a, &'a str> {
+    move |input: &'a str| {
+        let mut chars = input.char_indices();
+        while let Some((i, c)) = chars.next() {
+            if input[i..].starts_with(target) {
+                return Some((&input[..i], &input[i..]));
+            }
+            if c == '\\' {
+                chars.next();
+            }
+        }
+        None
+    }
+}
 
 fn parse_text_context<'a>() -> impl Parser<'a, Vec<char>> {
-    let escaped = string("\\").and(character(|_| true)).map(|(_, c)| c);
+    // エスケープできるのはASCIIの記号だけ。`\a` の `\` はそのまま文字として残る
+    let escaped = string("\\")
+        .and(character(|c| c.is_ascii_punctuation()))
+        .map(|(_, c)| c);
     let normal = character(|c| {
         c != '\\'
             && c != '\n'
@@ -17,6 +38,9 @@ fn parse_text_context<'a>() -> impl Parser<'a, Vec<char>> {
             && c != '('
             && c != ')'
             && c != '!'
+            && c != '$'
+            && c != '<'
+            && c != '&'
     });
 
     let parser = choice![escaped, normal];
@@ -35,7 +59,7 @@ fn parse_bold_inline<'a>() -> impl Parser<'a, Inline> {
     // ((("**", "text"), "**"), rest)
     asterisk
         .clone()
-        .and(take_until("**"))
+        .and(take_until_unescaped("**"))
         .and(asterisk)
         .map(|((_open_tag, text), _close_tag)| {
             // restは絶対空になるはず
@@ -54,7 +78,7 @@ fn parse_strikethru_inline<'a>() -> impl Parser<'a, Inline> {
     // ((("~~", "text"), "~~"), rest)
     tilde
         .clone()
-        .and(take_until("~~"))
+        .and(take_until_unescaped("~~"))
         .and(tilde)
         .map(|((_open_tag, text), _close_tag)| {
             // restは絶対空になるはず
@@ -73,7 +97,7 @@ fn parse_italic_inline<'a>() -> impl Parser<'a, Inline> {
     // ((('*', "text"), '*'), rest)
     delimiter
         .clone()
-        .and(take_until("*"))
+        .and(take_until_unescaped("*"))
         .and(delimiter)
         .map(|((_open_tag, text), _close_tag)| {
             // restは絶対空になるはず
@@ -88,7 +112,7 @@ fn parse_italic_inline<'a>() -> impl Parser<'a, Inline> {
 // インラインコード
 fn parse_code_inline<'a>() -> impl Parser<'a, Inline> {
     let delimiter = character(|c| c == '`');
-    let text = parse_text_context().map(|chars| chars.into_iter().collect());
+    let text = some(character(|c| c != '`' && c != '\n')).map(|chars| chars.into_iter().collect());
 
     // ((('`', "text"), '`'), rest)
     delimiter
@@ -98,13 +122,59 @@ fn parse_code_inline<'a>() -> impl Parser<'a, Inline> {
         .map(|((_open_tag, text), _close_tag)| Inline::Code(text))
 }
 
+// 数式 `$...$` と `$$...$$`
+// `$5 and $10` のような金額の表記を巻き込まないよう、開きの`$`の直後と閉じの`$`の直前が
+// 空白でなく、閉じの`$`の直後が数字でないときだけ数式とみなす
+fn parse_math_inline<'a>() -> impl Parser<'a, Inline> {
+    move |input: &'a str| {
+        let (delimiter, display) = if input.starts_with("$$") {
+            ("$$", true)
+        } else {
+            ("$", false)
+        };
+        let rest = input.strip_prefix(delimiter)?;
+        if rest.starts_with('$') || rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+
+        let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+        let mut chars = line.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                // 最初に現れた`$`が閉じの条件を満たさなければ数式ではない
+                '$' => {
+                    let closes = line[i..].starts_with(delimiter)
+                        && !line[..i].ends_with(char::is_whitespace)
+                        && !line[i + delimiter.len()..].starts_with(|c: char| c.is_ascii_digit());
+                    if !closes {
+                        return None;
+                    }
+
+                    let math = Inline::Math {
+                        tex: line[..i].to_string(),
+                        display,
+                    };
+                    return Some((math, &rest[i + delimiter.len()..]));
+                }
+                _ => {}
+            }
+        }
+
+        None
+    }
+}
+
 // 画像
 fn parse_image_inline<'a>() -> impl Parser<'a, Inline> {
     let start = string("![");
     let mid = string("](");
     let end = character(|c| c == ')');
     let alt = parse_text_context().map(|chars| chars.into_iter().collect());
-    let url = some(character(|c| c != ')')).map(|chars| chars.into_iter().collect());
+    let url = some(character(|c| c != ')'))
+        .map(|chars| decode_entities(&chars.into_iter().collect::<String>()));
 
     // ((((("![", "alt"), "]("), url), end) rest)
     start
@@ -120,11 +190,17 @@ fn parse_link_inline<'a>() -> impl Parser<'a, Inline> {
     let start = string("[");
     let mid = string("](");
     let end = character(|c| c == ')');
-    let url = some(character(|c| c != ')')).map(|chars| chars.into_iter().collect());
+    // URLの中の `&amp;` なども文字に戻しておき、出力時に一度だけエスケープする
+    let url = some(character(|c| c != ')'))
+        .map(|chars| decode_entities(&chars.into_iter().collect::<String>()));
 
     // ((((("[", "text"), "]("), url), end) rest)
-    start.and(take_until("](")).and(mid).and(url).and(end).map(
-        |((((_start, text), _mid), url), _end)| {
+    start
+        .and(take_until_unescaped("]("))
+        .and(mid)
+        .and(url)
+        .and(end)
+        .map(|((((_start, text), _mid), url), _end)| {
             // restは絶対空になるはず
             if let Some((inlines, _rest)) = parse_inlines().parse(text) {
                 Inline::Link { text: inlines, url }
@@ -134,8 +210,7 @@ fn parse_link_inline<'a>() -> impl Parser<'a, Inline> {
                     url,
                 }
             }
-        },
-    )
+        })
 }
 
 fn parse_autolink_inline<'a>() -> impl Parser<'a, Inline> {
@@ -189,26 +264,106 @@ fn parse_wikilink_inline<'a>() -> impl Parser<'a, Inline> {
     let content_char = character(|c| c != '|' && c != ']' && c != '\n');
     let text = some(content_char).map(|chars| chars.into_iter().collect::<String>());
 
-    // ((((("[", "text"), "]("), url), end) rest)
+    // (((("[[", "target#fragment"), ("|", "text")), "]]"), rest)
     start
         .and(text.clone())
         .and((pipe.and(text)).or(id()))
         .and(end)
         .map(|(((_start, link), (_pipe, text)), _end)| {
-            if text == "" {
-                Inline::Link {
-                    text: vec![Inline::Text(link.clone())],
-                    url: link,
-                }
-            } else {
-                Inline::Link {
-                    text: vec![Inline::Text(text)],
-                    url: link,
-                }
+            let (target, fragment) = match link.split_once('#') {
+                Some((target, fragment)) => (target.to_string(), Some(fragment.to_string())),
+                None => (link.clone(), None),
+            };
+            let text = if text.is_empty() { link } else { text };
+
+            Inline::WikiLink {
+                target,
+                fragment,
+                text,
             }
         })
 }
 
+// `300` か `300x200`
+fn parse_embed_size(option: &str) -> Option<(u32, Option<u32>)> {
+    match option.split_once('x') {
+        Some((width, height)) => Some((
+            width.trim().parse().ok()?,
+            Some(height.trim().parse().ok()?),
+        )),
+        None => Some((option.trim().parse().ok()?, None)),
+    }
+}
+
+// 埋め込み `![[Note#Section]]` `![[image.png|300x200]]`
+fn parse_embed_inline<'a>() -> impl Parser<'a, Inline> {
+    let start = string("![[");
+    let end = string("]]");
+    let pipe = string("|");
+
+    let content_char = character(|c| c != '|' && c != ']' && c != '\n');
+    let text = some(content_char).map(|chars| chars.into_iter().collect::<String>());
+
+    start
+        .and(text.clone())
+        .and((pipe.and(text)).or(id()))
+        .and(end)
+        .map(|(((_start, link), (_pipe, option)), _end)| {
+            let (target, fragment) = match link.split_once('#') {
+                Some((target, fragment)) => (target.to_string(), Some(fragment.to_string())),
+                None => (link, None),
+            };
+            let (width, height, label) = match parse_embed_size(&option) {
+                Some((width, height)) => (Some(width), height, None),
+                None if option.is_empty() => (None, None, None),
+                None => (None, None, Some(option)),
+            };
+
+            Inline::Embed(Embed {
+                target,
+                fragment,
+                label,
+                width,
+                height,
+            })
+        })
+}
+
+// 脚注参照
+fn parse_footnote_ref_inline<'a>() -> impl Parser<'a, Inline> {
+    let start = string("[^");
+    let end = string("]");
+    let label = some(character(|c| c != ']' && !c.is_whitespace()))
+        .map(|chars| chars.into_iter().collect::<String>());
+
+    start
+        .and(label)
+        .and(end)
+        .map(|((_start, label), _end)| Inline::FootnoteRef {
+            label,
+            number: None,
+            occurrence: 0,
+        })
+}
+
+// 文字参照(`&copy;` `&#x1F600;`)
+fn parse_entity_inline<'a>() -> impl Parser<'a, Inline> {
+    parse_entity().map(Inline::Text)
+}
+
+// 生のHTML(タグ、コメントなど)
+fn parse_html_inline<'a>() -> impl Parser<'a, Inline> {
+    parse_raw_html().map(|html| Inline::Html(html.to_string()))
+}
+
+// 改行(行末の `\` はハードブレーク)
+fn parse_line_break_inline<'a>() -> impl Parser<'a, Inline> {
+    let hard = string("\\\n").map(|_| Inline::HardBreak);
+    let soft = string("\n").map(|_| Inline::SoftBreak);
+
+    choice![hard, soft]
+}
+
 // どの構文にもマッチしない記号
 fn parse_symbol_as_text<'a>() -> impl Parser<'a, Inline> {
     character(|c| c != '\n').map(|c| Inline::Text(c.to_string()))
@@ -216,7 +371,9 @@ fn parse_symbol_as_text<'a>() -> impl Parser<'a, Inline> {
 
 pub fn parse_inlines<'a>() -> impl Parser<'a, Vec<Inline>> {
     let inline = choice![
+        parse_embed_inline(),
         parse_image_inline(),
+        parse_footnote_ref_inline(),
         parse_wikilink_inline(),
         parse_link_inline(),
         parse_autolink_inline(),
@@ -224,7 +381,11 @@ pub fn parse_inlines<'a>() -> impl Parser<'a, Vec<Inline>> {
         parse_strikethru_inline(),
         parse_italic_inline(), // boldよりも後ろに書かないとダメ
         parse_code_inline(),
+        parse_math_inline(),
+        parse_html_inline(),
+        parse_entity_inline(),
         parse_text_inline(),
+        parse_line_break_inline(),
         parse_symbol_as_text()
     ];
 
@@ -242,7 +403,7 @@ mod tests {
         let input2 = "Hello**World";
         let input3 = "Line1\nLine2";
         let input4 = "**Bold";
-        //let input5 = r"not\\*Bold";
+        let input5 = r"not\\*Bold";
 
         assert_eq!(
             parser(input),
@@ -256,8 +417,15 @@ mod tests {
             parser(input3),
             Some((Inline::Text("Line1".to_string()), "\nLine2"))
         );
+        assert_eq!(
+            parser("Line1\\\nLine2"),
+            Some((Inline::Text("Line1".to_string()), "\\\nLine2"))
+        );
         assert_eq!(parser(input4), None);
-        //assert_eq!(parser(input5), Some((Inline::Text(r"not\*Bold".to_string()), "")));
+        assert_eq!(
+            parser(input5),
+            Some((Inline::Text(r"not\".to_string()), "*Bold"))
+        );
     }
 
     #[test]
@@ -340,6 +508,71 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_math_inline() {
+        let parser = parse_math_inline();
+
+        assert_eq!(
+            parser("$x^2$ rest"),
+            Some((
+                Inline::Math {
+                    tex: "x^2".to_string(),
+                    display: false
+                },
+                " rest"
+            ))
+        );
+        assert_eq!(
+            parser("$$\\frac{a}{b}$$"),
+            Some((
+                Inline::Math {
+                    tex: "\\frac{a}{b}".to_string(),
+                    display: true
+                },
+                ""
+            ))
+        );
+        assert_eq!(
+            parser("$a \\$ b$"),
+            Some((
+                Inline::Math {
+                    tex: "a \\$ b".to_string(),
+                    display: false
+                },
+                ""
+            ))
+        );
+        assert_eq!(parser("$ x$"), None);
+        assert_eq!(parser("$x $"), None);
+        assert_eq!(parser("$x\ny$"), None);
+    }
+
+    #[test]
+    fn test_parse_inlines_literal_dollars() {
+        let parser = parse_inlines();
+
+        assert_eq!(
+            parser("$5 and $10")
+                .map(|(inlines, _)| inlines.iter().all(|i| matches!(i, Inline::Text(_)))),
+            Some(true)
+        );
+        assert_eq!(
+            parser("$5 and $E=mc^2$").map(|(inlines, _)| inlines.last().cloned()),
+            Some(Some(Inline::Math {
+                tex: "E=mc^2".to_string(),
+                display: false
+            }))
+        );
+        assert_eq!(
+            parser(r"costs \$5"),
+            Some((vec![Inline::Text("costs $5".to_string())], ""))
+        );
+        assert_eq!(
+            parser("`$x$`"),
+            Some((vec![Inline::Code("$x$".to_string())], ""))
+        );
+    }
+
     #[test]
     fn test_parse_strikethru_inline() {
         let parser = parse_strikethru_inline();
@@ -393,13 +626,15 @@ mod tests {
         let parser = parse_wikilink_inline();
         let input = "[[Obsidian Note]]";
         let input2 = "[[File Name|Custom Label]]";
+        let input3 = "[[Note#Heading]]";
 
         assert_eq!(
             parser(input),
             Some((
-                Inline::Link {
-                    text: vec![Inline::Text("Obsidian Note".to_string())],
-                    url: "Obsidian Note".to_string()
+                Inline::WikiLink {
+                    target: "Obsidian Note".to_string(),
+                    fragment: None,
+                    text: "Obsidian Note".to_string()
                 },
                 ""
             ))
@@ -407,13 +642,191 @@ mod tests {
         assert_eq!(
             parser(input2),
             Some((
-                Inline::Link {
-                    text: vec![Inline::Text("Custom Label".to_string())],
-                    url: "File Name".to_string()
+                Inline::WikiLink {
+                    target: "File Name".to_string(),
+                    fragment: None,
+                    text: "Custom Label".to_string()
                 },
                 ""
             ))
         );
+        assert_eq!(
+            parser(input3),
+            Some((
+                Inline::WikiLink {
+                    target: "Note".to_string(),
+                    fragment: Some("Heading".to_string()),
+                    text: "Note#Heading".to_string()
+                },
+                ""
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_embed_inline() {
+        let parser = parse_embed_inline();
+        let embed = |target: &str, fragment: Option<&str>, label: Option<&str>, size| {
+            let (width, height) = size;
+            Inline::Embed(Embed {
+                target: target.to_string(),
+                fragment: fragment.map(str::to_string),
+                label: label.map(str::to_string),
+                width,
+                height,
+            })
+        };
+
+        assert_eq!(
+            parser("![[Note#Section]]"),
+            Some((embed("Note", Some("Section"), None, (None, None)), ""))
+        );
+        assert_eq!(
+            parser("![[photo.png|300]]"),
+            Some((embed("photo.png", None, None, (Some(300), None)), ""))
+        );
+        assert_eq!(
+            parser("![[photo.png|300x200]]"),
+            Some((embed("photo.png", None, None, (Some(300), Some(200))), ""))
+        );
+        assert_eq!(
+            parser("![[photo.png|A photo]]"),
+            Some((embed("photo.png", None, Some("A photo"), (None, None)), ""))
+        );
+        assert_eq!(parser("[[Note]]"), None);
+    }
+
+    #[test]
+    fn test_parse_footnote_ref_inline() {
+        let parser = parse_footnote_ref_inline();
+
+        assert_eq!(
+            parser("[^note-1] rest"),
+            Some((
+                Inline::FootnoteRef {
+                    label: "note-1".to_string(),
+                    number: None,
+                    occurrence: 0
+                },
+                " rest"
+            ))
+        );
+        assert_eq!(parser("[^]"), None);
+        assert_eq!(parser("[^a b]"), None);
+        assert_eq!(parser("[link](url)"), None);
+    }
+
+    #[test]
+    fn test_parse_escaped_inlines() {
+        let parser = parse_inlines();
+
+        assert_eq!(
+            parser(r"\[not a link\]\(url\) \`code\` \| \# \~\~x\~\~ \$5"),
+            Some((
+                vec![Inline::Text(
+                    "[not a link](url) `code` | # ~~x~~ $5".to_string()
+                )],
+                ""
+            ))
+        );
+        assert_eq!(
+            parser(r"*a \* b* **c\*\*d**"),
+            Some((
+                vec![
+                    Inline::Italic(vec![Inline::Text("a * b".to_string())]),
+                    Inline::Text(" ".to_string()),
+                    Inline::Strong(vec![Inline::Text("c**d".to_string())]),
+                ],
+                ""
+            ))
+        );
+        // ASCIIの記号以外の前の `\` は文字として残る
+        assert_eq!(
+            parser(r"C:\Users \あ"),
+            Some((
+                vec![
+                    Inline::Text("C:".to_string()),
+                    Inline::Text("\\".to_string()),
+                    Inline::Text("Users ".to_string()),
+                    Inline::Text("\\".to_string()),
+                    Inline::Text("あ".to_string()),
+                ],
+                ""
+            ))
+        );
+        // コード内ではエスケープしない
+        assert_eq!(
+            parser(r"`a\*b`"),
+            Some((vec![Inline::Code(r"a\*b".to_string())], ""))
+        );
+    }
+
+    #[test]
+    fn test_parse_entity_inline() {
+        let parser = parse_inlines();
+
+        assert_eq!(
+            parser("&copy; 2024 &#x1F600; AT&T &amp;lt;"),
+            Some((
+                vec![
+                    Inline::Text("©".to_string()),
+                    Inline::Text(" 2024 ".to_string()),
+                    Inline::Text("😀".to_string()),
+                    Inline::Text(" AT".to_string()),
+                    Inline::Text("&".to_string()),
+                    Inline::Text("T ".to_string()),
+                    Inline::Text("&".to_string()),
+                    Inline::Text("lt;".to_string()),
+                ],
+                ""
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_entity_in_url() {
+        let parser = parse_inlines();
+
+        assert_eq!(
+            parser("[a](/p?x=1&amp;y=2) ![b](/i.png?w=1&#38;h=2)"),
+            Some((
+                vec![
+                    Inline::Link {
+                        text: vec![Inline::Text("a".to_string())],
+                        url: "/p?x=1&y=2".to_string(),
+                    },
+                    Inline::Text(" ".to_string()),
+                    Inline::Image {
+                        alt: "b".to_string(),
+                        url: "/i.png?w=1&h=2".to_string(),
+                    },
+                ],
+                ""
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_html_inline() {
+        let parser = parse_inlines();
+
+        assert_eq!(
+            parser("Press <kbd>Ctrl</kbd> <!-- c --> a < b"),
+            Some((
+                vec![
+                    Inline::Text("Press ".to_string()),
+                    Inline::Html("<kbd>".to_string()),
+                    Inline::Text("Ctrl".to_string()),
+                    Inline::Html("</kbd>".to_string()),
+                    Inline::Text(" ".to_string()),
+                    Inline::Html("<!-- c -->".to_string()),
+                    Inline::Text(" a ".to_string()),
+                    Inline::Text("<".to_string()),
+                    Inline::Text(" b".to_string()),
+                ],
+                ""
+            ))
+        );
     }
 
     #[test]
@@ -13,13 +13,7 @@ where
 }
 
 pub fn string<'a>(s: &'a str) -> impl Parser<'a, &'a str> {
-    move |input: &'a str| {
-        if input.starts_with(s) {
-            Some((s, &input[s.len()..]))
-        } else {
-            None
-        }
-    }
+    move |input: &'a str| input.strip_prefix(s).map(|rest| (s, rest))
 }
 
 pub fn digit<'a>() -> impl Parser<'a, i32> {
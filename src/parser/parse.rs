@@ -4,9 +4,11 @@ use std::{fs, path::PathBuf};
 
 pub fn parse<'a>(path: &'a PathBuf) -> Result<MdDocument<'a>, Box<dyn std::error::Error>> {
     let content = fs::read_to_string(path)?;
-    let (front_matter, markdown_body) = front_matter::parse_front_matter(&content);
+    let (front_matter, markdown_body) = front_matter::parse_front_matter(&content)?;
+    // 本文のパーサーはLFの改行だけを扱う
+    let markdown_body = markdown_body.replace("\r\n", "\n");
     let parser = parse_document();
-    if let Some((doc, _rest)) = parser(markdown_body) {
+    if let Some((doc, _rest)) = parser(&markdown_body) {
         Ok(MdDocument {
             path,
             front_matter,